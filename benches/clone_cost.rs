@@ -0,0 +1,54 @@
+//! Compares the cost of cloning a large query with the deep copy of its clause
+//! lists that cloning made before the clauses were shared behind `Arc`
+//!
+//! Run with `cargo bench --bench clone_cost`.
+
+use std::hint::black_box;
+use std::time::{Duration, Instant};
+
+use quick_oxibooks_sql::{Operator, Order, Query, WhereClause};
+use quickbooks_types::Customer;
+
+const ITERATIONS: u32 = 10_000;
+
+/// A query with a few hundred clauses, like those built by rewrite middleware
+fn large_query() -> Query<Customer> {
+    let mut query = Query::<Customer>::new();
+    for i in 0..100 {
+        query = query
+            .select_field(format!("Field{i}"))
+            .with_condition(WhereClause::new(format!("Field{i}"), Operator::Equal).add_value(i))
+            .order_by(format!("Field{i}"), Order::Asc);
+    }
+    query.with_condition(WhereClause::in_iter("Id", 0..1000))
+}
+
+fn time(name: &str, mut f: impl FnMut()) -> Duration {
+    let start = Instant::now();
+    for _ in 0..ITERATIONS {
+        f();
+    }
+    let per_iteration = start.elapsed() / ITERATIONS;
+    println!("{name:>24}: {per_iteration:?} per clone");
+    per_iteration
+}
+
+fn main() {
+    let query = large_query();
+
+    let deep = time("deep copy (before)", || {
+        black_box((
+            query.fields().to_vec(),
+            query.conditions().to_vec(),
+            query.ordering().to_vec(),
+        ));
+    });
+    let shared = time("Query::clone (after)", || {
+        black_box(query.clone());
+    });
+    println!(
+        "{:>24}: {:.0}x",
+        "speedup",
+        deep.as_secs_f64() / shared.as_secs_f64().max(f64::EPSILON)
+    );
+}
@@ -0,0 +1,74 @@
+//! Counts the allocations and time taken to render a query with a 1000-value
+//! `IN` list, against formatting each value into its own string and joining
+//! them, as rendering did before fragments were written into one buffer
+//!
+//! Run with `cargo bench --bench render_in_list`.
+
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::hint::black_box;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::{Duration, Instant};
+
+use quick_oxibooks_sql::{Query, WhereClause};
+use quickbooks_types::Customer;
+
+const ITERATIONS: u32 = 1_000;
+
+/// The system allocator, counting each allocation and reallocation
+struct Counting;
+
+static ALLOCATIONS: AtomicUsize = AtomicUsize::new(0);
+
+unsafe impl GlobalAlloc for Counting {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        unsafe { System.alloc(layout) }
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        unsafe { System.dealloc(ptr, layout) }
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        unsafe { System.realloc(ptr, layout, new_size) }
+    }
+}
+
+#[global_allocator]
+static GLOBAL: Counting = Counting;
+
+/// Time `f` and count its allocations, per call
+fn measure(name: &str, mut f: impl FnMut() -> String) -> (Duration, usize) {
+    let allocations = ALLOCATIONS.load(Ordering::Relaxed);
+    let start = Instant::now();
+    for _ in 0..ITERATIONS {
+        black_box(f());
+    }
+    let per_iteration = start.elapsed() / ITERATIONS;
+    let allocations = (ALLOCATIONS.load(Ordering::Relaxed) - allocations) / ITERATIONS as usize;
+    println!("{name:>24}: {per_iteration:?} and {allocations} allocations per render");
+    (per_iteration, allocations)
+}
+
+/// Render the query by formatting each value into its own string, then joining them
+fn format_and_join(values: &[String]) -> String {
+    let list: Vec<_> = values.iter().map(|value| format!("'{value}'")).collect();
+    format!("select * from Customer where Id IN ({})", list.join(", "))
+}
+
+fn main() {
+    let query = Query::<Customer>::new().with_condition(WhereClause::in_iter("Id", 0..1000));
+    let values = query.conditions()[0].values.clone();
+    assert_eq!(format_and_join(&values), query.query_string());
+
+    let (joined, joined_allocations) =
+        measure("format and join (before)", || format_and_join(&values));
+    let (rendered, rendered_allocations) = measure("query_string (after)", || query.query_string());
+    println!(
+        "{:>24}: {:.1}x faster, {} fewer allocations",
+        "difference",
+        joined.as_secs_f64() / rendered.as_secs_f64().max(f64::EPSILON),
+        joined_allocations.saturating_sub(rendered_allocations)
+    );
+}
@@ -1,9 +1,15 @@
+use std::collections::HashSet;
+
 use proc_macro::TokenStream;
-use quote::quote;
+use proc_macro_crate::{FoundCrate, crate_name};
+use quote::{ToTokens, quote, quote_spanned};
 use syn::{
-    Ident, LitInt, Token, Type,
+    Ident, Token, Type,
+    buffer::Cursor,
+    ext::IdentExt,
     parse::{Parse, ParseStream},
     punctuated::Punctuated,
+    spanned::Spanned,
 };
 
 /// Builds a type-safe QuickBooks Online query at compile time.
@@ -16,20 +22,30 @@ use syn::{
 ///
 /// ```text
 /// qb_sql!(
-///     select [* | field1, field2, ...]
+///     [#[rename = function]]
+///     [#[crate = path]]
+///     select [[distinct] * | count | [distinct] field1 [as "Name"] [as alias], count(field2), ...]
 ///     from EntityType
-///     [where condition [and condition ...]]
-///     [order by field [asc|desc] [, field [asc|desc] ...]]
-///     [limit number [offset number]]
+///     [where condition [and|or condition ...]]
+///     [group by field [, field ...]]
+///     [order by field [asc|desc] [nulls first|last] [, field ...]]
+///     [limit number [offset number] | maxresults number [startposition number]]
 /// )
 /// ```
 ///
 /// # Supported Operators
 ///
 /// - `=` - Equality comparison
+/// - `!=`, `<>` - Inequality comparison
 /// - `>`, `<`, `>=`, `<=` - Numeric comparisons
 /// - `like` - Pattern matching (use `%` as wildcard)
 /// - `in` - Match against multiple values: `field in (val1, val2, ...)` or `field in (iterator)`
+/// - `not like`, `not in` - Negated forms of `like` and `in`
+/// - `between` - Match an inclusive range: `field between low and high`
+/// - `is null`, `is not null` - Match fields that are absent or present (no value is given).
+///   `= null` and `!= null` are accepted as aliases.
+///
+/// Any condition can be negated by prefixing it with `not`, e.g. `not display_name like "test%"`.
 ///
 /// # Examples
 ///
@@ -87,48 +103,158 @@ use syn::{
 ///
 /// # Notes
 ///
-/// - Field names are automatically converted from snake_case to CamelCase (e.g., `display_name` → `DisplayName`)
-/// - All field names are validated at compile time against the entity type
+/// - `#[rename = function]` before `select` names fields with `function` instead of camel-casing
+///   them. It is called with each field path as written, e.g. `"meta_data.create_time"`, and
+///   must return a `&'static str` or another value convertible to a field name
+/// - Keywords are case-insensitive, so `SELECT * FROM Customer WHERE ...` as copied from the
+///   QuickBooks API Explorer works as well
+/// - Field names are automatically converted from snake_case to CamelCase (e.g., `display_name` → `DisplayName`).
+///   The acronyms QuickBooks uppercases are kept that way (`ap_account_ref` → `APAccountRef`,
+///   `po_number` → `PONumber`); other names can be given with `as "Name"` or `#[rename = function]`
+/// - Nested fields are written as dotted paths, with each segment converted separately
+///   (e.g., `meta_data.last_updated_time` → `MetaData.LastUpdatedTime`). Paths in conditions
+///   and `order by` are checked in full at compile time, looking through `Option` fields.
+/// - Fields named after Rust keywords are written as raw identifiers, e.g. `r#type` → `Type`
+/// - A field followed by `as "Name"`, e.g. `where primary_email_addr.address as "PrimaryEmailAddr" = x`,
+///   is rendered as `Name` for QuickBooks names that don't follow from camel-casing. The field
+///   as written is still checked against the entity type, and the name takes precedence over
+///   `#[rename = function]`
+/// - A field that is selected or ordered by more than once is a compile error
+/// - A field followed by `?`, e.g. `where custom_column? = x`, isn't checked against the entity
+///   type, for columns QuickBooks accepts that the typed entity doesn't have. Other fields in
+///   the query are still checked
+/// - Condition values are checked against their field's type where both are known, so a
+///   number field can't be compared against a string (`balance like "foo"`) nor a text field
+///   against a number (`display_name > 5`). Fields whose last segment is `id` or `doc_number`
+///   accept both, since IDs and document numbers are usually written as numbers. Values of
+///   other types, including the items of a single `in` expression, aren't checked
+/// - All selected, filtered (including `in` lists) and ordered field names are validated at
+///   compile time against the entity type, with unknown fields reported at their position
+/// - An unknown field close to one of the entity's queryable fields, as listed in the runtime
+///   crate's `entity_metadata`, is additionally reported with a suggestion, e.g.
+///   "unknown field `dsplay_name` on Customer; did you mean `display_name`?"
+/// - The expansion refers to the runtime crate by absolute paths, so the macro needs no imports
+///   beyond itself and the entity type. A renamed dependency is found from the calling crate's
+///   manifest; when the macro is re-exported from another crate, `#[crate = path]` before
+///   `select` gives the path the runtime crate is reachable at, e.g. `#[crate = facade::sql]`
 /// - The generated query can be converted to a string with `.query_string()` or by displaying it
-/// - For the `in` operator, use a tuple for literals or a single iterator expression
+/// - `select count` produces a `CountQuery<T>` rendering `select count(*)`, which returns the
+///   number of matching entities instead of the entities themselves
+/// - A selected field can be wrapped in `count`, `max`, `min` or `sum`, e.g.
+///   `select type, count(id) from Customer group by type`, to select an aggregate
+/// - For the `in` operator, use a tuple for literals or a single iterator expression, which
+///   may borrow; it's consumed once, when the query is built
+/// - Values are rendered with the runtime crate's `QueryValue` trait rather than `Display`, so
+///   custom value types implement that trait
+/// - A value that's a bare identifier is a Rust variable, even if it's named like a field,
+///   since QuickBooks can't compare two fields; one that isn't in scope gets rustc's usual
+///   missing variable error, with no hint about comparing fields
+/// - String values can be concatenated with `+`, e.g. `display_name like "Jo" + suffix`; if
+///   every part is a literal the pattern is folded at compile time
+/// - The `limit`/`maxresults` count and the offset can be any `u32` expression, such as a
+///   variable, a const or a function call
+/// - `nulls first`/`nulls last` after an `order by` field places entities without the field
+///   first or last. QuickBooks doesn't support this, so it's only rendered in the `Standard`
+///   and `Postgres` dialects and ignored otherwise
+/// - A comparison can be followed by `in currency`, e.g. `balance >= 1000 in "USD"`, for
+///   multi-currency companies. This adds a `CurrencyRef = currency` filter grouped with the
+///   comparison, so the entity type must have a `currency_ref` field: `Account`, `Bill`,
+///   `Customer`, `Estimate`, `Payment`, `SalesReceipt` or `Vendor`. QuickBooks compares
+///   the amounts in the entity's own currency, not the company's home currency
+/// - Conditions can be grouped with parentheses, e.g. `where (a = 1 or b = 2) and c = 3`
+/// - The QuickBooks Online query language has limited support for `or`; it is rendered as
+///   written, but the API may reject queries that use it
 #[proc_macro]
 pub fn qb_sql(input: TokenStream) -> TokenStream {
     let query = syn::parse_macro_input!(input as SqlQuery);
-    let expanded = query.expand();
+    let expanded = query
+        .expand()
+        .unwrap_or_else(syn::Error::into_compile_error);
     TokenStream::from(expanded)
 }
 
 /// Represents the entire SQL query
 struct SqlQuery {
+    /// The path the expansion refers to the runtime crate by
+    krate: proc_macro2::TokenStream,
+    /// A function mapping field names to their QuickBooks names, from `#[rename = ...]`
+    rename: Option<syn::Path>,
+    distinct: bool,
     fields: FieldSelection,
     item_type: Type,
     conditions: Vec<Condition>,
+    groups: Vec<(usize, usize)>,
+    group_by: Vec<FieldPath>,
     order_by: Option<OrderBy>,
     limit: Option<LimitClause>,
 }
 
-/// Field selection (SELECT * or SELECT field1, field2, ...)
+/// Field selection (SELECT *, SELECT COUNT or SELECT field1, field2, ...)
 enum FieldSelection {
     All,
-    Specific(Vec<Ident>),
+    Count,
+    Specific(Vec<SelectedField>),
+}
+
+/// A selected field with an optional `as` alias
+struct SelectedField {
+    /// The aggregate function the field is wrapped in, e.g. `count(id)`
+    aggregate: Option<Aggregate>,
+    field: FieldPath,
+    alias: Option<Ident>,
+}
+
+/// An aggregate function applied to a selected field
+#[derive(Clone, Copy)]
+enum Aggregate {
+    Count,
+    Max,
+    Min,
+    Sum,
 }
 
 /// A single WHERE condition
 struct Condition {
-    field: Ident,
+    join: Connective,
+    negated: bool,
+    field: FieldPath,
     operator: Operator,
     values: Vec<syn::Expr>,
+    /// The currency given with `in "USD"` after a comparison's value
+    currency: Option<syn::Expr>,
+}
+
+/// A field, or a dotted path to a nested field such as `meta_data.last_updated_time`
+struct FieldPath {
+    segments: Vec<Ident>,
+    /// Whether the path was marked with `?` to skip checking it against the entity type
+    unchecked: bool,
+    /// The QuickBooks name given with `as "Name"`, used instead of the camel-cased path
+    qb_name: Option<syn::LitStr>,
+}
+
+/// How a condition is joined to the one before it
+#[derive(Clone, Copy)]
+enum Connective {
+    And,
+    Or,
 }
 
 /// Operator types
 enum Operator {
     Equal,
+    NotEqual,
     Less,
     Greater,
     LessEqual,
     GreaterEqual,
     In,
+    NotIn,
     Like,
+    NotLike,
+    IsNull,
+    IsNotNull,
+    Between,
 }
 
 /// ORDER BY clause
@@ -137,8 +263,9 @@ struct OrderBy {
 }
 
 struct OrderField {
-    field: Ident,
+    field: FieldPath,
     direction: Option<OrderDirection>,
+    nulls: Option<NullsPlacement>,
 }
 
 enum OrderDirection {
@@ -146,108 +273,344 @@ enum OrderDirection {
     Desc,
 }
 
-/// LIMIT clause with optional OFFSET
+/// Where `nulls first`/`nulls last` places entities without the ordered field
+enum NullsPlacement {
+    First,
+    Last,
+}
+
+/// LIMIT clause with optional OFFSET, or the equivalent MAXRESULTS and STARTPOSITION
 struct LimitClause {
-    number: LitInt,
+    number: syn::Expr,
     offset: Option<syn::Expr>,
+    /// Whether `offset` is a 1-based STARTPOSITION rather than a 0-based OFFSET
+    one_based: bool,
 }
 
 impl Parse for SqlQuery {
     fn parse(input: ParseStream) -> syn::Result<Self> {
+        // Parse optional #[rename = function] and #[crate = path]
+        let mut rename = None;
+        let mut krate = None;
+        for attr in syn::Attribute::parse_outer(input)? {
+            match &attr.meta {
+                syn::Meta::NameValue(meta) if meta.path.is_ident("rename") && rename.is_none() => {
+                    rename = match &meta.value {
+                        syn::Expr::Path(path) if path.qself.is_none() => Some(path.path.clone()),
+                        value => {
+                            return Err(syn::Error::new_spanned(
+                                value,
+                                "expected the path of a function",
+                            ));
+                        }
+                    };
+                }
+                syn::Meta::NameValue(meta) if meta.path.is_ident("crate") && krate.is_none() => {
+                    krate = match &meta.value {
+                        syn::Expr::Path(path) if path.qself.is_none() => {
+                            Some(path.path.to_token_stream())
+                        }
+                        value => {
+                            return Err(syn::Error::new_spanned(
+                                value,
+                                "expected the path of the quick-oxibooks-sql crate",
+                            ));
+                        }
+                    };
+                }
+                _ => {
+                    return Err(syn::Error::new_spanned(
+                        &attr,
+                        "expected at most one `#[rename = function]` and one `#[crate = path]` attribute",
+                    ));
+                }
+            }
+        }
+        let krate = match krate {
+            Some(krate) => krate,
+            None => runtime_crate(crate_name("quick-oxibooks-sql")),
+        };
+
         // Parse SELECT
-        input.parse::<kw::select>()?;
+        kw::select.parse(input)?;
+
+        // Parse optional DISTINCT, unless `distinct` is the only selected field
+        let distinct =
+            kw::distinct.peek(input) && !kw::from.peek2(input) && !input.peek2(Token![,]);
+        if distinct {
+            kw::distinct.parse(input)?;
+            if kw::count.peek(input) && kw::from.peek2(input) {
+                return Err(input.error("`distinct` can't be used with `select count`"));
+            }
+        }
 
         // Parse field selection
         let fields = if input.peek(Token![*]) {
             input.parse::<Token![*]>()?;
             FieldSelection::All
+        } else if kw::count.peek(input) && kw::from.peek2(input) {
+            kw::count.parse(input)?;
+            FieldSelection::Count
         } else {
-            let field_list = Punctuated::<Ident, Token![,]>::parse_separated_nonempty(input)?;
+            let field_list =
+                Punctuated::<SelectedField, Token![,]>::parse_separated_nonempty(input)?;
+            // The same field can be selected both plainly and in aggregates
+            let plain_fields = field_list.iter().filter(|f| f.aggregate.is_none());
+            reject_duplicates(plain_fields.map(|f| &f.field), "selected")?;
             FieldSelection::Specific(field_list.into_iter().collect())
         };
 
         // Parse FROM
-        input.parse::<kw::from>()?;
+        kw::from.parse(input)?;
         let item_type: Type = input.parse()?;
 
         let mut conditions = vec![];
+        let mut groups = vec![];
 
-        if input.peek(Token![where]) {
+        if kw::r#where.peek(input) {
             // Parse WHERE
-            input.parse::<Token![where]>()?;
-            // Parse first condition
-            conditions.push(Condition::parse(input)?);
-            // Parse additional AND conditions
-            while input.peek(kw::and) {
-                input.parse::<kw::and>()?;
-                conditions.push(Condition::parse(input)?);
-            }
+            kw::r#where.parse(input)?;
+            parse_conditions(input, Connective::And, &mut conditions, &mut groups)?;
         }
 
-        // Parse optional ORDER BY
-        let order_by = if input.peek(kw::order) {
-            Some(OrderBy::parse(input)?)
+        // Parse optional GROUP BY
+        let group_by = if kw::group.peek(input) {
+            kw::group.parse(input)?;
+            kw::by.parse(input)?;
+            Punctuated::<FieldPath, Token![,]>::parse_separated_nonempty(input)?
+                .into_iter()
+                .collect()
         } else {
-            None
+            Vec::new()
         };
 
-        // Parse optional LIMIT
-        let limit = if input.peek(kw::limit) {
-            Some(LimitClause::parse(input)?)
+        // Parse optional ORDER BY
+        let order_by = if kw::order.peek(input) {
+            Some(OrderBy::parse(input)?)
         } else {
             None
         };
 
+        // Parse optional LIMIT or MAXRESULTS/STARTPOSITION
+        let limit =
+            if kw::limit.peek(input) || kw::maxresults.peek(input) || kw::startposition.peek(input)
+            {
+                Some(LimitClause::parse(input)?)
+            } else {
+                None
+            };
+
         Ok(SqlQuery {
+            krate,
+            rename,
+            distinct,
             fields,
             item_type,
             conditions,
+            groups,
+            group_by,
             order_by,
             limit,
         })
     }
 }
 
+/// Fail at the second occurrence of a field that appears more than once in `fields`
+///
+/// Fields are compared by their QuickBooks names, so differently written paths
+/// that name the same field are caught too.
+fn reject_duplicates<'a>(
+    fields: impl IntoIterator<Item = &'a FieldPath>,
+    clause: &str,
+) -> syn::Result<()> {
+    let mut seen = HashSet::new();
+    for field in fields {
+        // Names that can't be camel-cased are reported when the query is expanded
+        let name = match field.name() {
+            Ok(name) => name.value(),
+            Err(_) => field.written().value(),
+        };
+        if seen.contains(&name) {
+            return Err(syn::Error::new(
+                field.root().span(),
+                format!("`{name}` is {clause} more than once"),
+            ));
+        }
+        seen.insert(name);
+    }
+    Ok(())
+}
+
+/// Parse a chain of conditions joined by `and`/`or`, flattening parenthesized groups
+/// into `conditions` and recording the index range each group covers in `groups`
+///
+/// `join` is the connective preceding the chain, which is applied to its first condition.
+fn parse_conditions(
+    input: ParseStream,
+    join: Connective,
+    conditions: &mut Vec<Condition>,
+    groups: &mut Vec<(usize, usize)>,
+) -> syn::Result<()> {
+    // Parse first condition
+    parse_condition_term(input, join, conditions, groups)?;
+    // Parse additional AND/OR conditions
+    loop {
+        let join = if kw::and.peek(input) {
+            kw::and.parse(input)?;
+            Connective::And
+        } else if kw::or.peek(input) {
+            kw::or.parse(input)?;
+            Connective::Or
+        } else {
+            break;
+        };
+        parse_condition_term(input, join, conditions, groups)?;
+    }
+    Ok(())
+}
+
+/// Parse either a single condition or a parenthesized group of conditions
+fn parse_condition_term(
+    input: ParseStream,
+    join: Connective,
+    conditions: &mut Vec<Condition>,
+    groups: &mut Vec<(usize, usize)>,
+) -> syn::Result<()> {
+    if !input.peek(syn::token::Paren) {
+        let mut condition = Condition::parse(input)?;
+        condition.join = join;
+        let currency = condition.currency.take();
+        let start = conditions.len();
+        conditions.push(condition);
+        // A currency is a companion `CurrencyRef` filter, grouped with the
+        // condition so that it only applies there within an `or` chain
+        if let Some(currency) = currency {
+            let currency_ref = Ident::new("currency_ref", currency.span());
+            conditions.push(Condition {
+                join: Connective::And,
+                negated: false,
+                field: FieldPath {
+                    segments: vec![currency_ref],
+                    unchecked: false,
+                    qb_name: None,
+                },
+                operator: Operator::Equal,
+                values: vec![currency],
+                currency: None,
+            });
+            groups.push((start, conditions.len()));
+        }
+        return Ok(());
+    }
+
+    let content;
+    syn::parenthesized!(content in input);
+    let start = conditions.len();
+    parse_conditions(&content, join, conditions, groups)?;
+    if !content.is_empty() {
+        return Err(content.error("expected `and`, `or` or the end of the condition group"));
+    }
+    groups.push((start, conditions.len()));
+    Ok(())
+}
+
+impl Condition {
+    /// Whether each value is checked against the field's kind, which is skipped
+    /// for the iterator given to an `in`
+    fn checks_values(&self) -> bool {
+        !self.values.is_empty() && !(self.operator.takes_list() && self.values.len() == 1)
+    }
+}
+
 impl Parse for Condition {
     fn parse(input: ParseStream) -> syn::Result<Self> {
-        let field: Ident = input.parse()?;
-        let operator = Operator::parse(input)?;
+        let negated = if kw::not.peek(input) {
+            kw::not.parse(input)?;
+            true
+        } else {
+            false
+        };
+        let field = input.parse()?;
+        let mut operator = Operator::parse(input)?;
+
+        // `= null` and `!= null` are null checks rather than comparisons against a value
+        if kw::null.peek(input) {
+            operator = match operator {
+                Operator::Equal => Operator::IsNull,
+                Operator::NotEqual => Operator::IsNotNull,
+                _ => return Err(input.error("`null` can only be compared with `=` or `!=`")),
+            };
+            kw::null.parse(input)?;
+        }
 
-        let values = if matches!(operator, Operator::In) {
+        let values = if matches!(operator, Operator::In | Operator::NotIn) {
             // Parse parenthesized list for IN operator
             let content;
             syn::parenthesized!(content in input);
             let exprs = Punctuated::<syn::Expr, Token![,]>::parse_separated_nonempty(&content)?;
             exprs.into_iter().collect()
+        } else if matches!(operator, Operator::IsNull | Operator::IsNotNull) {
+            // Null checks take no value
+            vec![]
+        } else if matches!(operator, Operator::Between) {
+            // The `and` between the bounds belongs to BETWEEN, not the condition chain,
+            // so it's consumed here before the chain can see it
+            let low = input.parse()?;
+            kw::and.parse(input)?;
+            let high = input.parse()?;
+            vec![low, high]
         } else {
             // Parse single value for other operators
             vec![input.parse()?]
         };
 
+        // Amounts can be compared in a given currency, e.g. `balance >= 100 in "USD"`
+        let currency = if operator.compares_amounts() && kw::r#in.peek(input) {
+            let keyword = input.span();
+            kw::r#in.parse(input)?;
+            if negated {
+                return Err(syn::Error::new(
+                    keyword,
+                    "a negated condition can't be compared in a currency",
+                ));
+            }
+            Some(input.parse()?)
+        } else {
+            None
+        };
+
         Ok(Condition {
+            join: Connective::And,
+            negated,
             field,
             operator,
             values,
+            currency,
         })
     }
 }
 
 impl Parse for Operator {
     fn parse(input: ParseStream) -> syn::Result<Self> {
-        let lookahead = input.lookahead1();
-
-        if lookahead.peek(Token![=]) {
+        if input.peek(Token![=]) {
             input.parse::<Token![=]>()?;
             Ok(Operator::Equal)
-        } else if lookahead.peek(Token![<]) {
+        } else if input.peek(Token![!=]) {
+            input.parse::<Token![!=]>()?;
+            Ok(Operator::NotEqual)
+        } else if input.peek(Token![<]) {
             input.parse::<Token![<]>()?;
             if input.peek(Token![=]) {
                 input.parse::<Token![=]>()?;
                 Ok(Operator::LessEqual)
+            } else if input.peek(Token![>]) {
+                // `<>` is the standard SQL spelling of `!=`
+                input.parse::<Token![>]>()?;
+                Ok(Operator::NotEqual)
             } else {
                 Ok(Operator::Less)
             }
-        } else if lookahead.peek(Token![>]) {
+        } else if input.peek(Token![>]) {
             input.parse::<Token![>]>()?;
             if input.peek(Token![=]) {
                 input.parse::<Token![=]>()?;
@@ -255,24 +618,55 @@ impl Parse for Operator {
             } else {
                 Ok(Operator::Greater)
             }
-        } else if lookahead.peek(Token![in]) {
-            input.parse::<Token![in]>()?;
+        } else if kw::r#in.peek(input) {
+            kw::r#in.parse(input)?;
             Ok(Operator::In)
-        } else if lookahead.peek(kw::like) {
-            input.parse::<kw::like>()?;
+        } else if kw::like.peek(input) {
+            kw::like.parse(input)?;
             Ok(Operator::Like)
+        } else if kw::not.peek(input) {
+            kw::not.parse(input)?;
+            if kw::r#in.peek(input) {
+                kw::r#in.parse(input)?;
+                Ok(Operator::NotIn)
+            } else if kw::like.peek(input) {
+                kw::like.parse(input)?;
+                Ok(Operator::NotLike)
+            } else {
+                Err(input.error("expected `in` or `like`"))
+            }
+        } else if kw::between.peek(input) {
+            kw::between.parse(input)?;
+            Ok(Operator::Between)
+        } else if kw::is.peek(input) {
+            kw::is.parse(input)?;
+            let negated = if kw::not.peek(input) {
+                kw::not.parse(input)?;
+                true
+            } else {
+                false
+            };
+            kw::null.parse(input)?;
+            Ok(if negated {
+                Operator::IsNotNull
+            } else {
+                Operator::IsNull
+            })
         } else {
-            Err(lookahead.error())
+            Err(input.error(
+                "expected one of: `=`, `!=`, `<>`, `<`, `>`, `<=`, `>=`, `in`, `like`, `not`, `between`, `is`",
+            ))
         }
     }
 }
 
 impl Parse for OrderBy {
     fn parse(input: ParseStream) -> syn::Result<Self> {
-        input.parse::<kw::order>()?;
-        input.parse::<kw::by>()?;
+        kw::order.parse(input)?;
+        kw::by.parse(input)?;
 
         let orders = Punctuated::<OrderField, Token![,]>::parse_separated_nonempty(input)?;
+        reject_duplicates(orders.iter().map(|o| &o.field), "ordered by")?;
 
         Ok(OrderBy {
             orders: orders.into_iter().collect(),
@@ -282,65 +676,327 @@ impl Parse for OrderBy {
 
 impl Parse for OrderField {
     fn parse(input: ParseStream) -> syn::Result<Self> {
-        let field: Ident = input.parse()?;
+        let field = input.parse()?;
 
-        let direction = if input.peek(kw::asc) {
-            input.parse::<kw::asc>()?;
+        let direction = if kw::asc.peek(input) {
+            kw::asc.parse(input)?;
             Some(OrderDirection::Asc)
-        } else if input.peek(kw::desc) {
-            input.parse::<kw::desc>()?;
+        } else if kw::desc.peek(input) {
+            kw::desc.parse(input)?;
             Some(OrderDirection::Desc)
         } else {
             None
         };
 
-        Ok(OrderField { field, direction })
+        let nulls = if kw::nulls.peek(input) {
+            kw::nulls.parse(input)?;
+            if kw::first.peek(input) {
+                kw::first.parse(input)?;
+                Some(NullsPlacement::First)
+            } else if kw::last.peek(input) {
+                kw::last.parse(input)?;
+                Some(NullsPlacement::Last)
+            } else {
+                return Err(input.error("expected `first` or `last` after `nulls`"));
+            }
+        } else {
+            None
+        };
+
+        Ok(OrderField {
+            field,
+            direction,
+            nulls,
+        })
     }
 }
 
-impl Parse for LimitClause {
+impl Parse for SelectedField {
     fn parse(input: ParseStream) -> syn::Result<Self> {
-        input.parse::<kw::limit>()?;
-        let number: LitInt = input.parse()?;
-
-        let offset = if input.peek(kw::offset) {
-            input.parse::<kw::offset>()?;
+        // A function call such as `count(id)` selects an aggregate of the field
+        let aggregate = [
+            (kw::count, Aggregate::Count),
+            (kw::max, Aggregate::Max),
+            (kw::min, Aggregate::Min),
+            (kw::sum, Aggregate::Sum),
+        ]
+        .into_iter()
+        .find(|(keyword, _)| keyword.peek(input) && input.peek2(syn::token::Paren));
+        let (aggregate, field) = match aggregate {
+            Some((keyword, aggregate)) => {
+                keyword.parse(input)?;
+                let content;
+                syn::parenthesized!(content in input);
+                let field = content.parse()?;
+                if !content.is_empty() {
+                    return Err(content.error("expected a single field"));
+                }
+                (Some(aggregate), field)
+            }
+            None => (None, input.parse()?),
+        };
+        let alias = if kw::r#as.peek(input) {
+            kw::r#as.parse(input)?;
             Some(input.parse()?)
         } else {
             None
         };
+        Ok(SelectedField {
+            aggregate,
+            field,
+            alias,
+        })
+    }
+}
 
-        Ok(LimitClause { number, offset })
+impl Parse for FieldPath {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        let mut segments = vec![input.parse()?];
+        while input.peek(Token![.]) {
+            input.parse::<Token![.]>()?;
+            segments.push(input.parse()?);
+        }
+        let unchecked = input.parse::<Option<Token![?]>>()?.is_some();
+        // `as` followed by an identifier is a select alias, parsed by `SelectedField`
+        let qb_name = if kw::r#as.peek(input) && input.peek2(syn::LitStr) {
+            kw::r#as.parse(input)?;
+            let name: syn::LitStr = input.parse()?;
+            if name.value().is_empty() {
+                return Err(syn::Error::new(name.span(), "field name can't be empty"));
+            }
+            Some(name)
+        } else {
+            None
+        };
+        Ok(FieldPath {
+            segments,
+            unchecked,
+            qb_name,
+        })
     }
 }
 
-impl SqlQuery {
-    fn expand(&self) -> proc_macro2::TokenStream {
-        let item_type = &self.item_type;
+impl FieldPath {
+    /// The field of the entity the path starts from
+    fn root(&self) -> &Ident {
+        &self.segments[0]
+    }
+
+    /// A statement checking that the first segment of the path is a field of `v`
+    ///
+    /// The statement is spanned to the field as written, so a missing field is
+    /// reported at that field rather than at the whole macro call.
+    fn root_check(&self) -> Option<proc_macro2::TokenStream> {
+        let root = self.root();
+        (!self.unchecked).then(|| quote_spanned! {root.span()=> let _ = &v.#root; })
+    }
 
-        // Collect all fields for type checking
-        let all_fields: Vec<&Ident> = {
-            let mut fields = Vec::new();
+    /// A statement checking that the whole path exists starting from `v`,
+    /// looking through optional fields along the way
+    fn path_check(&self) -> Option<proc_macro2::TokenStream> {
+        let root = self.root();
+        let expr = self.access();
+        (!self.unchecked).then(|| quote_spanned! {root.span()=> let _ = #expr; })
+    }
 
-            if let FieldSelection::Specific(ref select_fields) = self.fields {
-                fields.extend(select_fields.iter());
+    /// An expression for the kind of the field, as compared against in conditions
+    ///
+    /// The expression is spanned to `span`, the value compared against, apart from
+    /// the field access, which stays spanned to the field and also checks that it
+    /// exists. IDs and document numbers are strings that are often written as
+    /// numbers, so they accept both. Unchecked fields accept any value.
+    fn kind(
+        &self,
+        item_type: &Type,
+        krate: &proc_macro2::TokenStream,
+        span: proc_macro2::Span,
+    ) -> proc_macro2::TokenStream {
+        let krate = &respanned(krate, span);
+        if self.unchecked {
+            return quote_spanned! {span=>
+                ::std::marker::PhantomData::<#krate::__private::kind::Any>
+            };
+        }
+        let access = self.access();
+        let nested_imports = (self.segments.len() > 1).then(|| {
+            quote_spanned! {span=>
+                #[allow(unused_imports)]
+                use #krate::__private::{Direct as _, Nested, ThroughOption as _};
             }
+        });
+        let kind = quote_spanned! {span=>
+            #krate::__private::field_kind(|v: &#item_type| {
+                #nested_imports
+                (&Classify(#access)).kind()
+            })
+        };
+        let last = self.segments.last().unwrap().unraw();
+        if last == "id" || last == "doc_number" {
+            quote_spanned! {span=> #krate::__private::as_id(#kind) }
+        } else {
+            kind
+        }
+    }
 
-            fields.extend(self.conditions.iter().map(|c| &c.field));
+    /// An expression borrowing the field at the end of the path from `v`
+    fn access(&self) -> proc_macro2::TokenStream {
+        let root = self.root();
+        let mut expr = quote_spanned! {root.span()=> &v.#root };
+        for segment in &self.segments[1..] {
+            expr = quote_spanned! {segment.span()=> &(&Nested(#expr)).field().#segment };
+        }
+        expr
+    }
+
+    /// The QuickBooks name of the field, with each segment camel-cased and joined by `.`
+    /// unless a name was given with `as "Name"`
+    fn name(&self) -> syn::Result<syn::LitStr> {
+        if let Some(name) = &self.qb_name {
+            return Ok(name.clone());
+        }
+        let name = self
+            .segments
+            .iter()
+            .map(to_camel_case)
+            .collect::<syn::Result<Vec<_>>>()?
+            .join(".");
+        Ok(syn::LitStr::new(&name, self.root().span()))
+    }
+
+    /// The path as written in the macro, without raw identifier prefixes
+    fn written(&self) -> syn::LitStr {
+        let written = self
+            .segments
+            .iter()
+            .map(|segment| segment.unraw().to_string())
+            .collect::<Vec<_>>()
+            .join(".");
+        syn::LitStr::new(&written, self.root().span())
+    }
+}
+
+impl Parse for LimitClause {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        let clause = if kw::limit.peek(input) {
+            kw::limit.parse(input)?;
+            let number = input.parse()?;
+
+            let offset = if kw::offset.peek(input) {
+                kw::offset.parse(input)?;
+                Some(input.parse()?)
+            } else {
+                None
+            };
 
-            if let Some(ref order_by) = self.order_by {
-                fields.extend(order_by.orders.iter().map(|o| &o.field));
+            LimitClause {
+                number,
+                offset,
+                one_based: false,
+            }
+        } else {
+            // MAXRESULTS and STARTPOSITION may appear in either order
+            let span = input.span();
+            let mut number = None;
+            let mut offset = None;
+            loop {
+                if number.is_none() && kw::maxresults.peek(input) {
+                    kw::maxresults.parse(input)?;
+                    number = Some(input.parse::<syn::Expr>()?);
+                } else if offset.is_none() && kw::startposition.peek(input) {
+                    kw::startposition.parse(input)?;
+                    offset = Some(input.parse::<syn::Expr>()?);
+                } else {
+                    break;
+                }
             }
 
-            fields
+            let Some(number) = number else {
+                return Err(syn::Error::new(
+                    span,
+                    "`startposition` requires `maxresults` to also be given",
+                ));
+            };
+            LimitClause {
+                number,
+                offset,
+                one_based: true,
+            }
         };
 
+        if kw::limit.peek(input)
+            || kw::offset.peek(input)
+            || kw::maxresults.peek(input)
+            || kw::startposition.peek(input)
+        {
+            return Err(input
+                .error("use either `limit`/`offset` or `maxresults`/`startposition`, not both"));
+        }
+
+        Ok(clause)
+    }
+}
+
+impl SqlQuery {
+    /// The expression naming `field` in the generated query
+    ///
+    /// This is the camel-cased name, unless a rename function was given, in which
+    /// case the function is called with the field path as written. A name given
+    /// with `as "Name"` overrides both.
+    fn field_name(&self, field: &FieldPath) -> syn::Result<proc_macro2::TokenStream> {
+        match &self.rename {
+            Some(rename) if field.qb_name.is_none() => {
+                let written = field.written();
+                Ok(quote! { #rename(#written) })
+            }
+            _ => {
+                let name = field.name()?;
+                Ok(quote! { #name })
+            }
+        }
+    }
+
+    fn expand(&self) -> syn::Result<proc_macro2::TokenStream> {
+        let item_type = &self.item_type;
+        let krate = &self.krate;
+
+        // Collect all fields for type checking. Selected nested paths are only checked
+        // up to their first segment, while conditions, grouping and ordering check the
+        // full path.
+        let mut field_checks: Vec<_> = Vec::new();
+        if let FieldSelection::Specific(ref select_fields) = self.fields {
+            field_checks.extend(select_fields.iter().filter_map(|f| f.field.root_check()));
+        }
+        // Fields whose values are checked are already accessed by that check
+        field_checks.extend(
+            self.conditions
+                .iter()
+                .filter(|c| !c.checks_values())
+                .filter_map(|c| c.field.path_check()),
+        );
+        field_checks.extend(self.group_by.iter().filter_map(FieldPath::path_check));
+        if let Some(ref order_by) = self.order_by {
+            field_checks.extend(order_by.orders.iter().filter_map(|o| o.field.path_check()));
+        }
+
         // Generate type checking code
-        let type_check = if !all_fields.is_empty() {
+        let nested_imports = quote! {
+            use #krate::__private::{Direct as _, Nested, ThroughOption as _};
+        };
+        let type_check = if !field_checks.is_empty() {
+            let ordered = self.order_by.iter().flat_map(|order_by| &order_by.orders);
+            let nested_imports = self
+                .conditions
+                .iter()
+                .map(|c| &c.field)
+                .chain(&self.group_by)
+                .chain(ordered.map(|o| &o.field))
+                .any(|field| !field.unchecked && field.segments.len() > 1)
+                .then_some(&nested_imports);
             quote! {
                 const _: () = {
                     fn _check_fields(v: #item_type) {
-                        #(let _ = v.#all_fields;)*
+                        #nested_imports
+                        #(#field_checks)*
                     }
                 };
             }
@@ -348,25 +1004,93 @@ impl SqlQuery {
             quote! {}
         };
 
+        let entity = match item_type {
+            Type::Path(path) => path.path.segments.last().map(|s| s.ident.to_string()),
+            _ => None,
+        };
+        // An unknown field close to one of the entity's queryable fields is most
+        // likely a typo, so it gets a suggestion on top of rustc's missing field error
+        let suggestion_checks: Vec<_> = match &entity {
+            Some(entity) => {
+                let selected = match &self.fields {
+                    FieldSelection::Specific(fields) => fields.iter().map(|f| &f.field).collect(),
+                    FieldSelection::All | FieldSelection::Count => Vec::new(),
+                };
+                let ordered = self.order_by.iter().flat_map(|order_by| &order_by.orders);
+                selected
+                    .into_iter()
+                    .chain(self.conditions.iter().map(|c| &c.field))
+                    .chain(&self.group_by)
+                    .chain(ordered.map(|o| &o.field))
+                    // A name given with `as "Name"` is taken as written
+                    .filter(|field| !field.unchecked && field.qb_name.is_none())
+                    // Names that can't be camel-cased are left to `field_name` to
+                    // report, unless a rename function names them
+                    .filter_map(|field| Some((field, field.name().ok()?)))
+                    .map(|(field, name)| {
+                        let written = field.written();
+                        let krate = respanned(krate, field.root().span());
+                        quote_spanned! {field.root().span()=>
+                            const _: () = #krate::__private::suggest_field(
+                                #entity,
+                                #name,
+                                #written,
+                            );
+                        }
+                    })
+                    .collect()
+            }
+            None => Vec::new(),
+        };
+
+        // Each value is checked against the kind of its field, e.g. a number field
+        // can't be compared against a string. A single `in` expression is an
+        // iterator, whose items aren't checked.
+        let value_imports = self
+            .conditions
+            .iter()
+            .any(|c| !c.values.is_empty() && (!c.operator.takes_list() || c.values.len() > 1))
+            .then(|| {
+                // Only one of the traits is used when every value has a known kind
+                quote! {
+                    #[allow(unused_imports)]
+                    use #krate::__private::{Classify, KnownKind as _, UnknownKind as _};
+                }
+            });
+
         // Generate field selection code
         let field_code = match &self.fields {
-            FieldSelection::All => quote! {},
+            FieldSelection::All | FieldSelection::Count => quote! {},
             FieldSelection::Specific(fields) => {
-                let field_names: Vec<_> = fields
+                let selections: Vec<_> = fields
                     .iter()
                     .map(|f| {
-                        let name = to_camel_case(&f.to_string());
-                        quote! { stringify!(#name) }
+                        let name = self.field_name(&f.field)?;
+                        let alias = f.alias.as_ref().map(|alias| alias.unraw().to_string());
+                        Ok(match (f.aggregate, alias) {
+                            (Some(aggregate), Some(alias)) => {
+                                let aggregate = aggregate.to_tokens(krate);
+                                quote! {
+                                    let query = query.select_aggregate_as(#aggregate, #name, #alias);
+                                }
+                            }
+                            (Some(aggregate), None) => {
+                                let aggregate = aggregate.to_tokens(krate);
+                                quote! {
+                                    let query = query.select_aggregate(#aggregate, #name);
+                                }
+                            }
+                            (None, Some(alias)) => quote! {
+                                let query = query.select_field_as(#name, #alias);
+                            },
+                            (None, None) => quote! {
+                                let query = query.select_field(#name);
+                            },
+                        })
                     })
-                    .collect();
+                    .collect::<syn::Result<_>>()?;
 
-                quote! {
-                    #(
-                        unsafe {
-                            query = query.field(#field_names);
-                        }
-                    )*
-                }
+                quote! { #(#selections)* }
             }
         };
 
@@ -375,56 +1099,94 @@ impl SqlQuery {
             .conditions
             .iter()
             .map(|c| {
-                let field = &c.field;
-                let field_name = to_camel_case(&field.to_string());
-                let operator = c.operator.to_tokens();
+                let field_name = self.field_name(&c.field)?;
+                let operator = c.operator.to_tokens(krate);
                 let values = &c.values;
 
-                // For IN operator with a single expression, treat it as an iterator
-                let values_code = if matches!(c.operator, Operator::In) && values.len() == 1 {
+                // For IN operators with a single expression, treat it as an iterator
+                let clause_code = if c.operator.takes_list() && values.len() == 1 {
                     let expr = &values[0];
                     quote! {
-                      #expr.into_iter().map(|v| v.to_string()).collect::<Vec<String>>()
+                        #krate::WhereClause::new(#field_name, #operator).add_query_values(#expr)
                     }
                 } else {
-                    // Multiple values or non-IN operators: call to_string on each
-                    quote! { vec![#(#values.to_string()),*] }
+                    // Multiple values or non-IN operators: render each with QueryValue,
+                    // checking that it can be compared with the field
+                    let values = values.iter().map(|value| {
+                        let value_code = value_tokens(value);
+                        let field_kind = c.field.kind(item_type, krate, value.span());
+                        quote_spanned! {value.span()=>
+                            (&Classify(&(#value_code))).checked(#field_kind)
+                        }
+                    });
+                    quote! {
+                        #krate::WhereClause::new(#field_name, #operator)
+                            #(.add_query_value(#values))*
+                    }
                 };
+                let negate_code = c.negated.then(|| quote! { .negate() });
+                let join_code = match c.join {
+                    Connective::And => quote! {},
+                    Connective::Or => quote! { .joined_by(#krate::Connective::Or) },
+                };
+
+                // Values are borrowed so that variables used in the condition aren't moved
+                Ok(quote! {
+                    #[allow(clippy::needless_borrow, clippy::needless_borrows_for_generic_args)]
+                    let query = query.with_condition(#clause_code #negate_code #join_code);
+                })
+            })
+            .collect::<syn::Result<_>>()?;
 
+        // Generate condition grouping code
+        let group_code: Vec<_> = self
+            .groups
+            .iter()
+            .map(|(start, end)| {
                 quote! {
-                    let clause = WhereClause {
-                        field: stringify!(#field_name),
-                        operator: #operator,
-                        values: #values_code,
-                    };
-                    unsafe {
-                        query = query.condition(clause);
-                    }
+                    let query = query.group(#start..#end);
                 }
             })
             .collect();
 
+        // Generate group by code
+        let group_by_code: Vec<_> = self
+            .group_by
+            .iter()
+            .map(|field| {
+                let field_name = self.field_name(field)?;
+                Ok(quote! {
+                    let query = query.group_by(#field_name);
+                })
+            })
+            .collect::<syn::Result<_>>()?;
+
         // Generate order by code
         let order_code = if let Some(ref order_by) = self.order_by {
             let orders: Vec<_> = order_by
                 .orders
                 .iter()
                 .map(|o| {
-                    let field = &o.field;
-                    let field_name = to_camel_case(&field.to_string());
+                    let field_name = self.field_name(&o.field)?;
                     let direction = match &o.direction {
-                        Some(OrderDirection::Asc) => quote! { Order::Asc },
-                        Some(OrderDirection::Desc) => quote! { Order::Desc },
-                        None => quote! { Order::Asc },
+                        Some(OrderDirection::Asc) => quote! { #krate::Order::Asc },
+                        Some(OrderDirection::Desc) => quote! { #krate::Order::Desc },
+                        None => quote! { #krate::Order::Asc },
                     };
 
-                    quote! {
-                        unsafe {
-                            query = query.order(stringify!(#field_name), #direction);
-                        }
-                    }
+                    Ok(match &o.nulls {
+                        Some(NullsPlacement::First) => quote! {
+                            let query = query.order_by_nulls(#field_name, #direction, #krate::Nulls::First);
+                        },
+                        Some(NullsPlacement::Last) => quote! {
+                            let query = query.order_by_nulls(#field_name, #direction, #krate::Nulls::Last);
+                        },
+                        None => quote! {
+                            let query = query.order_by(#field_name, #direction);
+                        },
+                    })
                 })
-                .collect();
+                .collect::<syn::Result<_>>()?;
 
             quote! { #(#orders)* }
         } else {
@@ -434,76 +1196,380 @@ impl SqlQuery {
         // Generate limit code
         let limit_code = if let Some(ref limit) = self.limit {
             let number = &limit.number;
-            let offset_code = if let Some(ref offset) = limit.offset {
-                quote! { Some(#offset) }
-            } else {
-                quote! { None }
+            let offset_code = match limit.offset {
+                Some(ref offset) if limit.one_based => {
+                    quote! { Some(u32::saturating_sub(#offset, 1)) }
+                }
+                Some(ref offset) => quote! { Some(#offset) },
+                None => quote! { None },
             };
 
             quote! {
-                query = query.limit(#number, #offset_code);
+                let query = query.limit(#number, #offset_code);
             }
         } else {
             quote! {}
         };
 
-        quote! {
+        let distinct_code = self
+            .distinct
+            .then(|| quote! { let query = query.distinct(); });
+
+        let result = match self.fields {
+            FieldSelection::Count => quote! { query.into_count() },
+            _ => quote! { query },
+        };
+
+        Ok(quote! {
             {
                 #type_check
+                #(#suggestion_checks)*
+                #value_imports
 
-                let mut query = Query::<#item_type>::new();
+                let query = #krate::Query::<#item_type>::new();
 
+                #distinct_code
                 #field_code
                 #(#condition_code)*
+                #(#group_code)*
+                #(#group_by_code)*
                 #order_code
                 #limit_code
 
-                query
+                #result
             }
-        }
+        })
     }
 }
 
 impl Operator {
-    fn to_tokens(&self) -> proc_macro2::TokenStream {
+    /// Whether the operator compares against a parenthesized list of values
+    fn takes_list(&self) -> bool {
+        matches!(self, Operator::In | Operator::NotIn)
+    }
+
+    /// Whether the operator compares against an amount, which can be given a currency
+    fn compares_amounts(&self) -> bool {
+        matches!(
+            self,
+            Operator::Equal
+                | Operator::NotEqual
+                | Operator::Less
+                | Operator::Greater
+                | Operator::LessEqual
+                | Operator::GreaterEqual
+                | Operator::Between
+        )
+    }
+
+    fn to_tokens(&self, krate: &proc_macro2::TokenStream) -> proc_macro2::TokenStream {
+        match self {
+            Operator::Equal => quote! { #krate::Operator::Equal },
+            Operator::NotEqual => quote! { #krate::Operator::NotEqual },
+            Operator::Less => quote! { #krate::Operator::Less },
+            Operator::Greater => quote! { #krate::Operator::Greater },
+            Operator::LessEqual => quote! { #krate::Operator::LessEqual },
+            Operator::GreaterEqual => quote! { #krate::Operator::GreaterEqual },
+            Operator::In => quote! { #krate::Operator::In },
+            Operator::NotIn => quote! { #krate::Operator::NotIn },
+            Operator::Like => quote! { #krate::Operator::Like },
+            Operator::NotLike => quote! { #krate::Operator::NotLike },
+            Operator::IsNull => quote! { #krate::Operator::IsNull },
+            Operator::IsNotNull => quote! { #krate::Operator::IsNotNull },
+            Operator::Between => quote! { #krate::Operator::Between },
+        }
+    }
+}
+
+impl Aggregate {
+    fn to_tokens(self, krate: &proc_macro2::TokenStream) -> proc_macro2::TokenStream {
         match self {
-            Operator::Equal => quote! { Operator::Equal },
-            Operator::Less => quote! { Operator::Less },
-            Operator::Greater => quote! { Operator::Greater },
-            Operator::LessEqual => quote! { Operator::LessEqual },
-            Operator::GreaterEqual => quote! { Operator::GreaterEqual },
-            Operator::In => quote! { Operator::In },
-            Operator::Like => quote! { Operator::Like },
+            Aggregate::Count => quote! { #krate::Aggregate::Count },
+            Aggregate::Max => quote! { #krate::Aggregate::Max },
+            Aggregate::Min => quote! { #krate::Aggregate::Min },
+            Aggregate::Sum => quote! { #krate::Aggregate::Sum },
+        }
+    }
+}
+
+/// Generate the code for a condition value
+///
+/// Values concatenating string literals with `+`, e.g. `"Jo" + suffix`, are
+/// folded into a single literal when every part is a literal, and are built
+/// into a `String` at runtime otherwise.
+fn value_tokens(value: &syn::Expr) -> proc_macro2::TokenStream {
+    let mut parts = Vec::new();
+    concat_parts(value, &mut parts);
+    let is_str = |part: &&syn::Expr| {
+        matches!(
+            part,
+            syn::Expr::Lit(syn::ExprLit {
+                lit: syn::Lit::Str(_),
+                ..
+            })
+        )
+    };
+    if parts.len() < 2 || !parts.iter().any(is_str) {
+        return quote! { #value };
+    }
+
+    if parts.iter().all(is_str) {
+        let folded: String = parts
+            .iter()
+            .map(|part| match part {
+                syn::Expr::Lit(syn::ExprLit {
+                    lit: syn::Lit::Str(lit),
+                    ..
+                }) => lit.value(),
+                _ => unreachable!(),
+            })
+            .collect();
+        let folded = syn::LitStr::new(&folded, syn::spanned::Spanned::span(value));
+        quote! { #folded }
+    } else {
+        quote! { [#(::std::string::ToString::to_string(&(#parts))),*].concat() }
+    }
+}
+
+/// Collect the operands of a chain of `+` additions
+fn concat_parts<'a>(expr: &'a syn::Expr, parts: &mut Vec<&'a syn::Expr>) {
+    match expr {
+        syn::Expr::Binary(syn::ExprBinary {
+            left,
+            op: syn::BinOp::Add(_),
+            right,
+            ..
+        }) => {
+            concat_parts(left, parts);
+            concat_parts(right, parts);
+        }
+        expr => parts.push(expr),
+    }
+}
+
+/// The path to the runtime crate, given how the calling crate depends on it
+///
+/// A renamed dependency is referred to by its new name. The runtime crate's own
+/// tests and examples see it as `Itself`, where the `extern crate self` alias in
+/// the runtime crate and the extern prelude both resolve the crate name.
+/// Without a manifest to look in, the default name is assumed.
+fn runtime_crate(found: Result<FoundCrate, proc_macro_crate::Error>) -> proc_macro2::TokenStream {
+    match found {
+        Ok(FoundCrate::Name(name)) => {
+            let name = Ident::new(&name, proc_macro2::Span::call_site());
+            quote! { ::#name }
         }
+        Ok(FoundCrate::Itself) | Err(_) => quote! { ::quick_oxibooks_sql },
     }
 }
 
-/// Convert snake_case to CamelCase
-fn to_camel_case(s: &str) -> syn::Ident {
-    let camel = s
+/// `tokens` with every span, including those of delimiters, replaced by `span`
+///
+/// An error about an expression covers all of its tokens, so the path to the
+/// runtime crate in a check is respanned to the field or value being checked.
+/// Otherwise it keeps the span of the whole macro call, and so does the error.
+/// The paths are absolute or written by the caller, so they resolve the same.
+fn respanned(
+    tokens: &proc_macro2::TokenStream,
+    span: proc_macro2::Span,
+) -> proc_macro2::TokenStream {
+    tokens
+        .clone()
+        .into_iter()
+        .map(|mut tree| {
+            if let proc_macro2::TokenTree::Group(group) = &tree {
+                let stream = respanned(&group.stream(), span);
+                tree = proc_macro2::Group::new(group.delimiter(), stream).into();
+            }
+            tree.set_span(span);
+            tree
+        })
+        .collect()
+}
+
+/// Words QuickBooks writes in uppercase within field names, e.g. `APAccountRef`
+///
+/// `id` isn't included, since entity IDs are named `Id`.
+const ACRONYMS: &[&str] = &["ap", "ar", "po", "uri"];
+
+/// Convert a snake_case field name to CamelCase, uppercasing the words in [`ACRONYMS`]
+///
+/// Names with empty words, i.e. leading, trailing or repeated underscores, are
+/// rejected, since there's no QuickBooks name they'd map to.
+fn to_camel_case(ident: &Ident) -> syn::Result<String> {
+    let name = ident.unraw().to_string();
+    let problem = if name.starts_with('_') {
+        Some("starts with an underscore")
+    } else if name.ends_with('_') {
+        Some("ends with an underscore")
+    } else if name.contains("__") {
+        Some("contains repeated underscores")
+    } else {
+        None
+    };
+    if let Some(problem) = problem {
+        return Err(syn::Error::new(
+            ident.span(),
+            format!(
+                "field name `{name}` {problem}, so it can't be converted to a QuickBooks name; \
+                 give the name explicitly with `{name} as \"Name\"`"
+            ),
+        ));
+    }
+
+    Ok(name
         .split('_')
         .map(|word| {
+            if ACRONYMS.contains(&word) {
+                return word.to_uppercase();
+            }
             let mut chars = word.chars();
             match chars.next() {
                 None => String::new(),
                 Some(first) => first.to_uppercase().collect::<String>() + chars.as_str(),
             }
         })
-        .collect::<String>();
+        .collect())
+}
+
+/// A keyword of the query language
+///
+/// Keywords are matched case-insensitively, so queries copied from the QuickBooks
+/// API Explorer, which uppercases them, parse as written.
+#[derive(Clone, Copy)]
+struct Keyword(&'static str);
+
+impl Keyword {
+    fn matches(self, cursor: Cursor) -> bool {
+        cursor
+            .ident()
+            .is_some_and(|(ident, _)| ident.to_string().eq_ignore_ascii_case(self.0))
+    }
+
+    /// Whether the next token is this keyword
+    fn peek(self, input: ParseStream) -> bool {
+        self.matches(input.cursor())
+    }
+
+    /// Whether the token after the next one is this keyword
+    fn peek2(self, input: ParseStream) -> bool {
+        input
+            .cursor()
+            .token_tree()
+            .is_some_and(|(_, next)| self.matches(next))
+    }
 
-    syn::Ident::new(&camel, proc_macro2::Span::call_site())
+    /// Parse this keyword, naming it in the error if the next token is something else
+    fn parse(self, input: ParseStream) -> syn::Result<()> {
+        input.step(|cursor| {
+            if self.matches(*cursor) {
+                let (_, rest) = cursor.ident().unwrap();
+                Ok(((), rest))
+            } else {
+                Err(cursor.error(format!("expected `{}`", self.0)))
+            }
+        })
+    }
 }
 
 // Custom keywords
+#[allow(non_upper_case_globals)]
 mod kw {
-    syn::custom_keyword!(select);
-    syn::custom_keyword!(from);
-    syn::custom_keyword!(and);
-    syn::custom_keyword!(order);
-    syn::custom_keyword!(by);
-    syn::custom_keyword!(limit);
-    syn::custom_keyword!(offset);
-    syn::custom_keyword!(asc);
-    syn::custom_keyword!(desc);
-    syn::custom_keyword!(like);
+    use super::Keyword;
+
+    pub const select: Keyword = Keyword("select");
+    pub const count: Keyword = Keyword("count");
+    pub const max: Keyword = Keyword("max");
+    pub const min: Keyword = Keyword("min");
+    pub const sum: Keyword = Keyword("sum");
+    pub const distinct: Keyword = Keyword("distinct");
+    pub const from: Keyword = Keyword("from");
+    pub const r#where: Keyword = Keyword("where");
+    pub const and: Keyword = Keyword("and");
+    pub const or: Keyword = Keyword("or");
+    pub const group: Keyword = Keyword("group");
+    pub const order: Keyword = Keyword("order");
+    pub const by: Keyword = Keyword("by");
+    pub const limit: Keyword = Keyword("limit");
+    pub const offset: Keyword = Keyword("offset");
+    pub const maxresults: Keyword = Keyword("maxresults");
+    pub const startposition: Keyword = Keyword("startposition");
+    pub const asc: Keyword = Keyword("asc");
+    pub const desc: Keyword = Keyword("desc");
+    pub const nulls: Keyword = Keyword("nulls");
+    pub const first: Keyword = Keyword("first");
+    pub const last: Keyword = Keyword("last");
+    pub const r#as: Keyword = Keyword("as");
+    pub const r#in: Keyword = Keyword("in");
+    pub const like: Keyword = Keyword("like");
+    pub const not: Keyword = Keyword("not");
+    pub const is: Keyword = Keyword("is");
+    pub const null: Keyword = Keyword("null");
+    pub const between: Keyword = Keyword("between");
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_to_camel_case() {
+        let cases = [
+            ("id", "Id"),
+            ("display_name", "DisplayName"),
+            ("meta_data", "MetaData"),
+            ("total_amt", "TotalAmt"),
+            ("ap_account_ref", "APAccountRef"),
+            ("ar_account_ref", "ARAccountRef"),
+            ("po_number", "PONumber"),
+            ("po_email", "POEmail"),
+            ("uri", "URI"),
+            ("primary_email_addr", "PrimaryEmailAddr"),
+            ("bill_addr", "BillAddr"),
+            ("line1", "Line1"),
+            ("sync_token", "SyncToken"),
+            ("r#type", "Type"),
+        ];
+        for (field, expected) in cases {
+            let ident: Ident = syn::parse_str(field).unwrap();
+            assert_eq!(
+                to_camel_case(&ident).unwrap(),
+                expected,
+                "converting `{field}`"
+            );
+        }
+    }
+
+    #[test]
+    fn test_runtime_crate() {
+        let renamed = runtime_crate(Ok(FoundCrate::Name("qb_sql_renamed".into())));
+        assert_eq!(renamed.to_string(), quote! { ::qb_sql_renamed }.to_string());
+
+        let default = quote! { ::quick_oxibooks_sql }.to_string();
+        assert_eq!(runtime_crate(Ok(FoundCrate::Itself)).to_string(), default);
+        let missing = crate_name("a-crate-that-is-not-a-dependency");
+        assert!(missing.is_err());
+        assert_eq!(runtime_crate(missing).to_string(), default);
+    }
+
+    #[test]
+    fn test_to_camel_case_degenerate() {
+        let cases = [
+            ("__typename", "starts with an underscore"),
+            ("_private", "starts with an underscore"),
+            ("trailing_", "ends with an underscore"),
+            ("a__b", "contains repeated underscores"),
+        ];
+        for (field, problem) in cases {
+            let ident: Ident = syn::parse_str(field).unwrap();
+            let err = to_camel_case(&ident).unwrap_err();
+            assert_eq!(
+                err.to_string(),
+                format!(
+                    "field name `{field}` {problem}, so it can't be converted to a QuickBooks \
+                     name; give the name explicitly with `{field} as \"Name\"`"
+                )
+            );
+        }
+    }
 }
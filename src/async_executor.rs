@@ -0,0 +1,119 @@
+use std::time::Duration;
+
+use quickbooks_types::QBItem;
+use serde::de::DeserializeOwned;
+
+use crate::executor::{escalate_warnings, parse_response, status_error};
+use crate::{QbEnvironment, Query, QueryError, QueryResponse};
+
+/// An executor that sends query requests with an async `reqwest` client
+///
+/// This mirrors [`HttpExecutor`](crate::HttpExecutor) for services that can't
+/// block a thread per request.
+pub struct AsyncHttpExecutor<'a> {
+    client: &'a reqwest::Client,
+    environment: QbEnvironment,
+    company_id: String,
+    access_token: String,
+    base_url: Option<String>,
+    escalated_warnings: Vec<String>,
+}
+
+impl<'a> AsyncHttpExecutor<'a> {
+    /// Create a new executor for the given company using an OAuth access token
+    pub fn new(
+        client: &'a reqwest::Client,
+        environment: QbEnvironment,
+        company_id: impl Into<String>,
+        access_token: impl Into<String>,
+    ) -> Self {
+        Self {
+            client,
+            environment,
+            company_id: company_id.into(),
+            access_token: access_token.into(),
+            base_url: None,
+            escalated_warnings: Vec::new(),
+        }
+    }
+
+    /// Send requests to `base_url` instead of the environment's API host
+    ///
+    /// This is mainly useful for pointing the executor at a proxy or a local mock server.
+    pub fn with_base_url(mut self, base_url: impl Into<String>) -> Self {
+        self.base_url = Some(base_url.into());
+        self
+    }
+
+    /// Fail requests whose response carries a warning with one of `codes`, see
+    /// [`HttpExecutor::escalate_warnings`](crate::HttpExecutor::escalate_warnings)
+    pub fn escalate_warnings(mut self, codes: &[&str]) -> Self {
+        self.escalated_warnings = codes.iter().map(|code| code.to_string()).collect();
+        self
+    }
+
+    /// The ID of the company (realm) being queried
+    pub fn company_id(&self) -> &str {
+        &self.company_id
+    }
+
+    /// Perform a `GET` request against `url`, returning the raw response body
+    async fn get(&self, url: &str, timeout: Option<Duration>) -> Result<String, QueryError> {
+        let mut request = self
+            .client
+            .get(url)
+            .bearer_auth(&self.access_token)
+            .header("Accept", "application/json");
+        if let Some(timeout) = timeout {
+            request = request.timeout(timeout);
+        }
+        let response = request.send().await.map_err(transport_error)?;
+        let status = response.status().as_u16();
+        let body = response.text().await.map_err(transport_error)?;
+
+        match status_error(status, &body) {
+            Some(err) => Err(err),
+            None => Ok(body),
+        }
+    }
+}
+
+fn transport_error(err: reqwest::Error) -> QueryError {
+    if err.is_timeout() {
+        QueryError::Timeout
+    } else {
+        QueryError::Transport(Box::new(err))
+    }
+}
+
+impl<QB: QBItem + DeserializeOwned> Query<QB> {
+    /// Execute the query asynchronously, returning the matching entities
+    ///
+    /// The query string and response handling are the same as [`Query::execute_with`].
+    /// The query's retry policy is not applied, since retrying needs a timer from
+    /// the caller's async runtime.
+    ///
+    /// ```no_run
+    /// # use quick_oxibooks_sql::{AsyncHttpExecutor, Order, QbEnvironment, Query, QueryError, qb_sql};
+    /// # use quickbooks_types::Customer;
+    /// async fn active_customers(client: &reqwest::Client) -> Result<Vec<Customer>, QueryError> {
+    ///     let executor = AsyncHttpExecutor::new(client, QbEnvironment::Sandbox, "1234", "token");
+    ///     qb_sql!(select * from Customer order by display_name)
+    ///         .execute_async(&executor)
+    ///         .await
+    /// }
+    /// ```
+    pub async fn execute_async(
+        &self,
+        executor: &AsyncHttpExecutor<'_>,
+    ) -> Result<Vec<QB>, QueryError> {
+        let base_url = match &executor.base_url {
+            Some(base_url) => base_url.as_str(),
+            None => executor.environment.base_url(),
+        };
+        let url = self.url_from_base(base_url, &executor.company_id);
+        let response: QueryResponse<QB> = parse_response(&executor.get(&url, self.timeout).await?)?;
+        let response = escalate_warnings(response, &executor.escalated_warnings)?;
+        Ok(response.items)
+    }
+}
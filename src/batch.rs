@@ -0,0 +1,346 @@
+use std::collections::HashMap;
+use std::marker::PhantomData;
+
+use quickbooks_types::QBItem;
+use serde::de::{DeserializeOwned, Error as _};
+use serde_json::Value;
+
+use crate::executor::{batch_url, parse_value};
+use crate::{FaultCode, FaultInfo, Query, QueryError, QueryExecutor};
+
+/// Several queries sent together in one QuickBooks batch request
+///
+/// Each query added to the batch returns a [`BatchHandle`], which is later used
+/// to pick that query's result out of the parsed [`BatchResults`].
+#[derive(Debug, Default)]
+pub struct QueryBatch {
+    queries: Vec<String>,
+}
+
+/// Identifies one query in a [`QueryBatch`] and the entity type it returns
+#[derive(Debug)]
+pub struct BatchHandle<QB> {
+    id: String,
+    _phantom: PhantomData<QB>,
+}
+
+/// The per-item responses of a batch request
+#[derive(Debug)]
+pub struct BatchResults {
+    items: HashMap<String, Value>,
+    unidentified: Vec<Value>,
+}
+
+/// Counts of the successful and failed items in a batch response
+#[derive(Debug, PartialEq, Eq, Clone, Default)]
+pub struct BatchSummary {
+    /// The number of items that returned a well-formed query response
+    pub succeeded: usize,
+    /// The number of items that returned a fault
+    pub failed: usize,
+    /// The number of items with neither a fault nor a well-formed query response,
+    /// including those without a `bId`
+    pub malformed: usize,
+    /// How many failed items reported each fault code
+    pub fault_codes: HashMap<FaultCode, usize>,
+}
+
+impl QueryBatch {
+    /// Create an empty batch
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add a query to the batch, returning the handle used to read its result
+    pub fn add<QB: QBItem>(&mut self, query: &Query<QB>) -> BatchHandle<QB> {
+        BatchHandle {
+            id: self.push(query.query_string()),
+            _phantom: PhantomData,
+        }
+    }
+
+    /// Add a rendered query to the batch, returning its `bId`
+    fn push(&mut self, query: String) -> String {
+        let id = self.queries.len().to_string();
+        self.queries.push(query);
+        id
+    }
+
+    /// The number of queries in the batch
+    pub fn len(&self) -> usize {
+        self.queries.len()
+    }
+
+    /// Whether the batch has no queries
+    pub fn is_empty(&self) -> bool {
+        self.queries.is_empty()
+    }
+
+    /// The JSON body of the batch request
+    pub fn request_body(&self) -> String {
+        let items: Vec<_> = self
+            .queries
+            .iter()
+            .enumerate()
+            .map(|(id, query)| serde_json::json!({ "bId": id.to_string(), "Query": query }))
+            .collect();
+        serde_json::json!({ "BatchItemRequest": items }).to_string()
+    }
+
+    /// Send the batch with the given executor, using its default environment
+    pub fn execute_with(&self, executor: &impl QueryExecutor) -> Result<BatchResults, QueryError> {
+        let url = batch_url(
+            &executor.base_url(executor.environment()),
+            executor.company_id(),
+        );
+        self.parse_response(&executor.post(&url, &self.request_body())?)
+    }
+
+    /// Parse the body of a batch response
+    ///
+    /// Only the envelope is parsed here; each item is parsed when its result is
+    /// read, so a malformed item doesn't affect the others. Items without a
+    /// `bId` can't be matched to a query and are kept as
+    /// [`BatchResults::unidentified`].
+    pub fn parse_response(&self, body: &str) -> Result<BatchResults, QueryError> {
+        let mut response: Value = serde_json::from_str(body)?;
+        let items = match response["BatchItemResponse"].take() {
+            Value::Array(items) => items,
+            _ => return Err(serde_json::Error::custom("missing BatchItemResponse").into()),
+        };
+
+        let mut results = BatchResults {
+            items: HashMap::with_capacity(items.len()),
+            unidentified: Vec::new(),
+        };
+        for item in items {
+            match item.get("bId").and_then(Value::as_str) {
+                Some(id) => {
+                    results.items.insert(id.to_string(), item);
+                }
+                None => results.unidentified.push(item),
+            }
+        }
+        Ok(results)
+    }
+}
+
+impl<QB: QBItem + DeserializeOwned> BatchHandle<QB> {
+    /// The result of this handle's query
+    ///
+    /// Faults are returned as [`QueryError::Fault`], so they can be inspected with
+    /// the same [`FaultCode`] predicates as single queries.
+    pub fn result(&self, results: &BatchResults) -> Result<Vec<QB>, QueryError> {
+        let item = results.items.get(&self.id).ok_or_else(|| {
+            serde_json::Error::custom(format!("no response for batch item {}", self.id))
+        })?;
+        Ok(parse_value::<QB>(item.clone())?.items)
+    }
+}
+
+/// Count the entities of each of `entities` with a single batch request
+///
+/// This sends one `select count(*)` per entity instead of a request each,
+/// returning the counts in the same order as `entities`. A fault for any
+/// entity, e.g. an unknown entity name, fails the whole call.
+pub fn count_batch(
+    entities: &[&str],
+    executor: &impl QueryExecutor,
+) -> Result<Vec<(String, usize)>, QueryError> {
+    let mut batch = QueryBatch::new();
+    let ids: Vec<_> = entities
+        .iter()
+        .map(|entity| batch.push(format!("select count(*) from {entity}")))
+        .collect();
+    let results = batch.execute_with(executor)?;
+
+    entities
+        .iter()
+        .zip(ids)
+        .map(|(entity, id)| Ok((entity.to_string(), results.total_count(&id)?)))
+        .collect()
+}
+
+impl BatchResults {
+    /// The `totalCount` returned for the count query with the given `bId`
+    fn total_count(&self, id: &str) -> Result<usize, QueryError> {
+        let item = self
+            .items
+            .get(id)
+            .ok_or_else(|| serde_json::Error::custom(format!("no response for batch item {id}")))?;
+        if let Some(fault) = FaultInfo::from_response(item) {
+            return Err(QueryError::Fault(fault));
+        }
+        let count = item["QueryResponse"]["totalCount"]
+            .as_u64()
+            .ok_or_else(|| {
+                serde_json::Error::custom(format!("no totalCount for batch item {id}"))
+            })?;
+        Ok(count as usize)
+    }
+
+    /// The items of the response without a string `bId`, which no handle can read
+    pub fn unidentified(&self) -> &[Value] {
+        &self.unidentified
+    }
+
+    /// Count the successful, failed and malformed items and the fault codes reported
+    pub fn summary(&self) -> BatchSummary {
+        let mut summary = BatchSummary {
+            malformed: self.unidentified.len(),
+            ..BatchSummary::default()
+        };
+        for item in self.items.values() {
+            match FaultInfo::from_response(item) {
+                Some(fault) => {
+                    summary.failed += 1;
+                    if let Some(code) = fault.code() {
+                        *summary.fault_codes.entry(code).or_default() += 1;
+                    }
+                }
+                None if is_query_response(item) => summary.succeeded += 1,
+                None => summary.malformed += 1,
+            }
+        }
+        summary
+    }
+}
+
+/// Whether `item` has a query response whose entries are entity lists or counts
+///
+/// The entity type isn't known here, so the entities themselves aren't checked.
+fn is_query_response(item: &Value) -> bool {
+    match item.get("QueryResponse") {
+        Some(Value::Object(response)) => response
+            .values()
+            .all(|value| value.is_array() || value.is_number()),
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::qb_sql;
+    use quickbooks_types::{Customer, Invoice};
+
+    const MIXED_RESPONSE: &str = r#"{
+        "BatchItemResponse": [
+            {"bId": "0", "QueryResponse": {"Customer": [{"Id": "1"}, {"Id": "2"}]}},
+            {"bId": "1", "QueryResponse": {"Invoice": [{"Id": "10"}]}},
+            {"bId": "2", "Fault": {"Error": [{"Message": "Error parsing query", "code": "4000"}], "type": "ValidationFault"}},
+            {"bId": "3", "Fault": {"Error": [{"Message": "ThrottleExceeded", "code": "3001"}], "type": "SERVICE"}},
+            {"bId": "4", "QueryResponse": {"Customer": "not a list"}},
+            {"QueryResponse": {"Customer": [{"Id": "5"}]}}
+        ],
+        "time": "2024-01-01T00:00:00Z"
+    }"#;
+
+    #[test]
+    fn test_request_body() {
+        let mut batch = QueryBatch::new();
+        batch.add(&qb_sql!(select * from Customer where id = 1));
+        batch.add(&qb_sql!(select * from Invoice));
+
+        let body: Value = serde_json::from_str(&batch.request_body()).unwrap();
+        assert_eq!(
+            body,
+            serde_json::json!({
+                "BatchItemRequest": [
+                    {"bId": "0", "Query": "select * from Customer where Id = '1'"},
+                    {"bId": "1", "Query": "select * from Invoice"},
+                ]
+            })
+        );
+    }
+
+    #[test]
+    fn test_count_batch() {
+        use crate::executor::tests::MockExecutor;
+
+        let executor = MockExecutor::new([r#"{
+            "BatchItemResponse": [
+                {"bId": "1", "QueryResponse": {"totalCount": 0}},
+                {"bId": "0", "QueryResponse": {"totalCount": 42}},
+                {"bId": "2", "QueryResponse": {"totalCount": 1289}}
+            ],
+            "time": "2024-01-01T00:00:00Z"
+        }"#]);
+
+        let counts = count_batch(&["Customer", "Vendor", "Invoice"], &executor).unwrap();
+
+        assert_eq!(
+            counts,
+            vec![
+                ("Customer".to_string(), 42),
+                ("Vendor".to_string(), 0),
+                ("Invoice".to_string(), 1289),
+            ]
+        );
+        assert_eq!(
+            executor.urls.borrow()[0],
+            "https://quickbooks.api.intuit.com/v3/company/1234/batch"
+        );
+        let body: Value = serde_json::from_str(&executor.bodies.borrow()[0]).unwrap();
+        assert_eq!(
+            body,
+            serde_json::json!({
+                "BatchItemRequest": [
+                    {"bId": "0", "Query": "select count(*) from Customer"},
+                    {"bId": "1", "Query": "select count(*) from Vendor"},
+                    {"bId": "2", "Query": "select count(*) from Invoice"},
+                ]
+            })
+        );
+    }
+
+    #[test]
+    fn test_count_batch_fault() {
+        use crate::executor::tests::MockExecutor;
+
+        let executor = MockExecutor::new([r#"{"BatchItemResponse": [
+            {"bId": "0", "QueryResponse": {"totalCount": 42}},
+            {"bId": "1", "Fault": {"Error": [{"Message": "Error parsing query", "code": "4000"}], "type": "ValidationFault"}}
+        ]}"#]);
+
+        assert!(matches!(
+            count_batch(&["Customer", "Nonsense"], &executor),
+            Err(QueryError::Fault(ref fault)) if fault.code() == Some(FaultCode::QueryParserError)
+        ));
+    }
+
+    #[test]
+    fn test_mixed_batch_response() {
+        let mut batch = QueryBatch::new();
+        let customers = batch.add(&qb_sql!(select * from Customer));
+        let invoices = batch.add(&qb_sql!(select * from Invoice));
+        let bad_query = batch.add(&qb_sql!(select * from Customer));
+        let throttled = batch.add(&qb_sql!(select * from Invoice));
+        let malformed = batch.add(&qb_sql!(select * from Customer));
+
+        let results = batch.parse_response(MIXED_RESPONSE).unwrap();
+
+        assert_eq!(customers.result(&results).unwrap().len(), 2);
+        assert_eq!(invoices.result(&results).unwrap().len(), 1);
+        assert!(matches!(
+            bad_query.result(&results),
+            Err(QueryError::Fault(ref fault)) if fault.code() == Some(FaultCode::QueryParserError)
+        ));
+        assert!(throttled.result(&results).unwrap_err().is_retryable());
+        assert!(matches!(
+            malformed.result(&results),
+            Err(QueryError::Json(_))
+        ));
+
+        assert_eq!(results.unidentified().len(), 1);
+
+        let summary = results.summary();
+        assert_eq!(summary.succeeded, 2);
+        assert_eq!(summary.failed, 2);
+        assert_eq!(summary.malformed, 2);
+        assert_eq!(
+            summary.fault_codes,
+            HashMap::from([(FaultCode::QueryParserError, 1), (FaultCode::Throttled, 1)])
+        );
+    }
+}
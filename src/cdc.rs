@@ -0,0 +1,195 @@
+use std::marker::PhantomData;
+
+use quickbooks_types::QBItem;
+use serde::de::DeserializeOwned;
+use serde_json::Value;
+
+use crate::{FaultInfo, QbEnvironment, QueryError, QueryExecutor, QueryValue};
+
+/// A request for the entities of type `QB` changed since a point in time
+///
+/// This goes through QuickBooks' change data capture (CDC) endpoint rather than
+/// the query endpoint, so it takes no conditions: every entity of the type
+/// created, updated or deleted since `changed_since` is returned. QuickBooks
+/// only looks back 30 days and returns at most 1000 entities of each type.
+///
+/// CDC supports these entity types: Account, Bill, BillPayment, Budget, Class,
+/// CreditMemo, Customer, Department, Deposit, Employee, Estimate, Invoice,
+/// Item, JournalCode, JournalEntry, Payment, PaymentMethod, Purchase,
+/// PurchaseOrder, RefundReceipt, SalesReceipt, TaxAgency, Term, TimeActivity,
+/// Transfer, Vendor and VendorCredit. QuickBooks rejects requests for other
+/// types with a fault.
+#[derive(Debug, PartialEq, Clone)]
+pub struct CdcQuery<QB> {
+    changed_since: String,
+    _phantom: PhantomData<QB>,
+}
+
+/// The entities returned by a [`CdcQuery`]
+#[derive(Debug, PartialEq, Clone)]
+pub struct CdcResults<QB> {
+    /// Entities created or updated since the requested time
+    pub changed: Vec<QB>,
+    /// The IDs of entities deleted since the requested time, which QuickBooks
+    /// returns without their other fields
+    pub deleted: Vec<String>,
+    /// The server time of the response, to request the next set of changes from
+    pub time: Option<String>,
+}
+
+impl<QB: QBItem> CdcQuery<QB> {
+    /// Request the entities changed since `changed_since`
+    ///
+    /// The time is rendered as a query literal, so a `chrono` or `time` date
+    /// time can be given directly with the features of the same names.
+    pub fn new(changed_since: impl QueryValue) -> Self {
+        CdcQuery {
+            changed_since: changed_since.to_query_literal(),
+            _phantom: PhantomData,
+        }
+    }
+
+    /// The time changes are requested from, as sent to QuickBooks
+    pub fn changed_since(&self) -> &str {
+        &self.changed_since
+    }
+
+    /// The URL query parameters of the CDC request, e.g.
+    /// `entities=Customer&changedSince=2024-01-01T00%3A00%3A00Z`
+    pub fn cdc_request_params(&self) -> String {
+        format!(
+            "entities={}&changedSince={}",
+            QB::name(),
+            urlencoding::encode(&self.changed_since)
+        )
+    }
+
+    /// Build the full request URL for the CDC endpoint
+    pub fn to_url(&self, env: QbEnvironment, company_id: &str) -> String {
+        cdc_url(env.base_url(), company_id, &self.cdc_request_params())
+    }
+}
+
+impl<QB: QBItem + DeserializeOwned> CdcQuery<QB> {
+    /// Send the request with `executor` and parse the changed entities
+    pub fn execute_with(
+        &self,
+        executor: &impl QueryExecutor,
+    ) -> Result<CdcResults<QB>, QueryError> {
+        let env = executor.environment();
+        let url = cdc_url(
+            &executor.base_url(env),
+            executor.company_id(),
+            &self.cdc_request_params(),
+        );
+        parse_cdc_response(serde_json::from_str(&executor.get(&url)?)?)
+    }
+}
+
+/// Build the request URL for the CDC endpoint
+fn cdc_url(base_url: &str, company_id: &str, params: &str) -> String {
+    format!("{base_url}/company/{company_id}/cdc?{params}")
+}
+
+/// Split a CDC response into changed entities and the IDs of deleted ones
+fn parse_cdc_response<QB: QBItem + DeserializeOwned>(
+    response: Value,
+) -> Result<CdcResults<QB>, QueryError> {
+    if let Some(fault) = FaultInfo::from_response(&response) {
+        return Err(QueryError::Fault(fault));
+    }
+
+    let mut results = CdcResults {
+        changed: Vec::new(),
+        deleted: Vec::new(),
+        time: response["time"].as_str().map(String::from),
+    };
+    // Each CDC response holds one query response per requested entity type
+    let query_responses = response["CDCResponse"]
+        .as_array()
+        .into_iter()
+        .flatten()
+        .filter_map(|cdc| cdc["QueryResponse"].as_array())
+        .flatten();
+    for query_response in query_responses {
+        let Some(items) = query_response[QB::name()].as_array() else {
+            continue;
+        };
+        for item in items {
+            if item["status"] == "Deleted" {
+                results
+                    .deleted
+                    .extend(item["Id"].as_str().map(String::from));
+            } else {
+                results.changed.push(serde_json::from_value(item.clone())?);
+            }
+        }
+    }
+    Ok(results)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::executor::tests::MockExecutor;
+    use quickbooks_types::Customer;
+
+    #[test]
+    fn test_cdc_request_params() {
+        let query = CdcQuery::<Customer>::new("2024-01-01T00:00:00-07:00");
+
+        assert_eq!(query.changed_since(), "2024-01-01T00:00:00-07:00");
+        assert_eq!(
+            query.cdc_request_params(),
+            "entities=Customer&changedSince=2024-01-01T00%3A00%3A00-07%3A00"
+        );
+        assert_eq!(
+            query.to_url(QbEnvironment::Production, "1234"),
+            "https://quickbooks.api.intuit.com/v3/company/1234/cdc?\
+             entities=Customer&changedSince=2024-01-01T00%3A00%3A00-07%3A00"
+        );
+    }
+
+    #[test]
+    fn test_cdc_execute() {
+        let executor = MockExecutor::new([r#"{
+            "CDCResponse": [{"QueryResponse": [{
+                "Customer": [
+                    {"Id": "1", "DisplayName": "Acme"},
+                    {"domain": "QBO", "status": "Deleted", "Id": "2"},
+                    {"Id": "3", "DisplayName": "Globex"}
+                ],
+                "startPosition": 1,
+                "maxResults": 3
+            }]}],
+            "time": "2024-01-02T10:00:00-07:00"
+        }"#]);
+
+        let results = CdcQuery::<Customer>::new("2024-01-01T00:00:00Z")
+            .execute_with(&executor)
+            .unwrap();
+
+        let changed: Vec<_> = results.changed.iter().map(|c| c.id.as_deref()).collect();
+        assert_eq!(changed, vec![Some("1"), Some("3")]);
+        assert_eq!(results.deleted, vec!["2"]);
+        assert_eq!(results.time.as_deref(), Some("2024-01-02T10:00:00-07:00"));
+        assert_eq!(
+            executor.urls.borrow()[0],
+            "https://quickbooks.api.intuit.com/v3/company/1234/cdc?\
+             entities=Customer&changedSince=2024-01-01T00%3A00%3A00Z"
+        );
+    }
+
+    #[test]
+    fn test_cdc_no_changes() {
+        let executor = MockExecutor::new([r#"{"CDCResponse": [{"QueryResponse": [{}]}]}"#]);
+
+        let results = CdcQuery::<Customer>::new("2024-01-01")
+            .execute_with(&executor)
+            .unwrap();
+
+        assert!(results.changed.is_empty());
+        assert!(results.deleted.is_empty());
+        assert_eq!(results.time, None);
+    }
+}
@@ -0,0 +1,71 @@
+use quickbooks_types::{Estimate, Invoice, QBItem, SalesReceipt};
+
+use crate::{Connective, Operator, Query, WhereClause};
+
+/// A sales entity with the `EmailStatus` and `PrintStatus` delivery fields
+///
+/// Only invoices, estimates and sales receipts track whether they still need to
+/// be sent, so [`Query::needs_sending`] is only available for those.
+///
+/// ```compile_fail
+/// # use quick_oxibooks_sql::Query;
+/// # use quickbooks_types::Customer;
+/// let query = Query::<Customer>::new().needs_sending();
+/// ```
+pub trait DeliveryStatus: QBItem {}
+
+impl DeliveryStatus for Invoice {}
+impl DeliveryStatus for Estimate {}
+impl DeliveryStatus for SalesReceipt {}
+
+impl<QB: DeliveryStatus> Query<QB> {
+    /// Only match entities waiting to be emailed or printed
+    ///
+    /// Adds `(EmailStatus = 'NeedToSend' or PrintStatus = 'NeedToPrint')`, combined
+    /// with any existing conditions as in [`Query::merge`].
+    pub fn needs_sending(self) -> Self {
+        let pending = Query::new()
+            .with_condition(
+                WhereClause::new("EmailStatus", Operator::Equal).add_value("NeedToSend"),
+            )
+            .with_condition(
+                WhereClause::new("PrintStatus", Operator::Equal)
+                    .add_value("NeedToPrint")
+                    .joined_by(Connective::Or),
+            );
+        self.merge(pending)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::qb_sql;
+
+    #[test]
+    fn test_needs_sending_per_entity() {
+        assert_eq!(
+            Query::<Invoice>::new().needs_sending().query_string(),
+            "select * from Invoice where (EmailStatus = 'NeedToSend' or PrintStatus = 'NeedToPrint')"
+        );
+        assert_eq!(
+            Query::<Estimate>::new().needs_sending().query_string(),
+            "select * from Estimate where (EmailStatus = 'NeedToSend' or PrintStatus = 'NeedToPrint')"
+        );
+        assert_eq!(
+            Query::<SalesReceipt>::new().needs_sending().query_string(),
+            "select * from SalesReceipt where (EmailStatus = 'NeedToSend' or PrintStatus = 'NeedToPrint')"
+        );
+    }
+
+    #[test]
+    fn test_needs_sending_keeps_existing_conditions() {
+        let query =
+            qb_sql!(select * from Invoice where balance > 0 or total_amt > 100).needs_sending();
+        assert_eq!(
+            query.query_string(),
+            "select * from Invoice where (Balance > '0' or TotalAmt > '100') \
+             and (EmailStatus = 'NeedToSend' or PrintStatus = 'NeedToPrint')"
+        );
+    }
+}
@@ -0,0 +1,168 @@
+use std::borrow::Cow;
+use std::fmt;
+
+use crate::Limit;
+#[cfg(feature = "api")]
+use crate::{FaultInfo, ResponseWarning};
+
+/// Errors that can occur while building or executing a query
+#[derive(Debug)]
+pub enum QueryError {
+    /// The HTTP request could not be completed
+    Transport(Box<dyn std::error::Error + Send + Sync>),
+    /// The API responded with an unsuccessful status and no fault payload
+    Http { status: u16, body: String },
+    /// The response body could not be deserialized
+    #[cfg(feature = "api")]
+    Json(serde_json::Error),
+    /// The API returned a fault instead of a query response
+    #[cfg(feature = "api")]
+    Fault(FaultInfo),
+    /// The response carried a warning whose code the executor escalates to an error,
+    /// see [`QueryExecutor::escalated_warnings`](crate::QueryExecutor::escalated_warnings)
+    #[cfg(feature = "api")]
+    Warning(ResponseWarning),
+    /// The request took longer than the query's
+    /// [timeout](crate::Query::with_timeout)
+    Timeout,
+    /// A query expected to match exactly one entity matched none
+    NoResults,
+    /// A query expected to match exactly one entity matched several
+    MultipleResults { count: usize },
+    /// A query marked with [`Query::require_order`](crate::Query::require_order)
+    /// was paged without an `order by` clause
+    OrderRequired,
+    /// Results couldn't be written out, e.g. by
+    /// [`Query::execute_ndjson`](crate::Query::execute_ndjson)
+    Write(std::io::Error),
+}
+
+impl QueryError {
+    /// Whether the request may succeed if sent again later
+    pub fn is_retryable(&self) -> bool {
+        match self {
+            QueryError::Http { status, .. } => *status == 429 || *status >= 500,
+            #[cfg(feature = "api")]
+            QueryError::Fault(fault) => fault.is_retryable(),
+            _ => false,
+        }
+    }
+
+    /// Whether the request failed due to invalid or insufficient credentials
+    pub fn is_auth(&self) -> bool {
+        match self {
+            QueryError::Http { status, .. } => matches!(*status, 401 | 403),
+            #[cfg(feature = "api")]
+            QueryError::Fault(fault) => fault.is_auth(),
+            _ => false,
+        }
+    }
+
+    /// Whether the request failed because the requested object does not exist
+    pub fn is_not_found(&self) -> bool {
+        match self {
+            QueryError::Http { status, .. } => *status == 404,
+            #[cfg(feature = "api")]
+            QueryError::Fault(fault) => fault.is_not_found(),
+            _ => false,
+        }
+    }
+}
+
+impl fmt::Display for QueryError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            QueryError::Transport(err) => write!(f, "request failed: {err}"),
+            QueryError::Http { status, body } => write!(f, "HTTP {status}: {body}"),
+            #[cfg(feature = "api")]
+            QueryError::Json(err) => write!(f, "invalid response body: {err}"),
+            #[cfg(feature = "api")]
+            QueryError::Fault(fault) => write!(f, "QuickBooks fault: {fault}"),
+            #[cfg(feature = "api")]
+            QueryError::Warning(warning) => write!(f, "QuickBooks warning: {warning}"),
+            QueryError::Timeout => write!(f, "request timed out"),
+            QueryError::NoResults => write!(f, "query returned no results"),
+            QueryError::MultipleResults { count } => {
+                write!(f, "query returned {count} results, expected one")
+            }
+            QueryError::OrderRequired => {
+                write!(f, "paged query requires an explicit `order by` clause")
+            }
+            QueryError::Write(err) => write!(f, "failed to write results: {err}"),
+        }
+    }
+}
+
+impl std::error::Error for QueryError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            QueryError::Transport(err) => Some(err.as_ref()),
+            #[cfg(feature = "api")]
+            QueryError::Json(err) => Some(err),
+            QueryError::Write(err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+#[cfg(feature = "api")]
+impl From<serde_json::Error> for QueryError {
+    fn from(err: serde_json::Error) -> Self {
+        QueryError::Json(err)
+    }
+}
+
+/// A problem with a query detected before it is sent, see [`Query::validate`](crate::Query::validate)
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub enum ValidationError {
+    /// A condition that compares against a value has none
+    MissingValue { field: Cow<'static, str> },
+    /// An `IN` condition has an empty value list, which QuickBooks rejects
+    EmptyInList { field: Cow<'static, str> },
+    /// The field isn't a queryable field of the entity
+    UnknownField { field: Cow<'static, str> },
+    /// The query has no condition on the field
+    NoCondition { field: Cow<'static, str> },
+    /// Two queries combined with [`Query::merge_limits`](crate::Query::merge_limits)
+    /// are limited at different offsets
+    ///
+    /// This is found while building the query, so it's a validation error rather
+    /// than a [`QueryError`].
+    ConflictingLimits { left: Limit, right: Limit },
+    /// A query string given to [`Query::from_sql_str`](crate::Query::from_sql_str)
+    /// couldn't be parsed, at the given byte offset
+    Syntax {
+        position: usize,
+        message: Cow<'static, str>,
+    },
+}
+
+impl fmt::Display for ValidationError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ValidationError::MissingValue { field } => {
+                write!(f, "condition on `{field}` is missing a value")
+            }
+            ValidationError::EmptyInList { field } => {
+                write!(f, "IN condition on `{field}` has no values")
+            }
+            ValidationError::UnknownField { field } => {
+                write!(f, "`{field}` is not a queryable field")
+            }
+            ValidationError::NoCondition { field } => {
+                write!(f, "the query has no condition on `{field}`")
+            }
+            ValidationError::ConflictingLimits { left, right } => write!(
+                f,
+                "can't merge limits at offsets {} and {}",
+                left.offset().unwrap_or(0),
+                right.offset().unwrap_or(0)
+            ),
+            ValidationError::Syntax { position, message } => {
+                write!(f, "syntax error at byte {position}: {message}")
+            }
+        }
+    }
+}
+
+impl std::error::Error for ValidationError {}
@@ -0,0 +1,1086 @@
+use std::io::Write;
+use std::time::Duration;
+
+use quickbooks_types::QBItem;
+use serde::Serialize;
+use serde::de::{DeserializeOwned, Error as _};
+
+use crate::{
+    CountQuery, FaultCode, FaultInfo, Limit, Query, QueryError, ResponseWarning, ValidationError,
+};
+
+/// The QuickBooks Online environment a query is sent to
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum QbEnvironment {
+    Sandbox,
+    Production,
+}
+
+impl QbEnvironment {
+    /// The base URL of the QuickBooks Online accounting API for this environment
+    pub fn base_url(self) -> &'static str {
+        match self {
+            QbEnvironment::Sandbox => "https://sandbox-quickbooks.api.intuit.com/v3",
+            QbEnvironment::Production => "https://quickbooks.api.intuit.com/v3",
+        }
+    }
+}
+
+/// A backend capable of sending query requests to QuickBooks Online
+///
+/// The query itself builds the request URL and parses the response, so an
+/// executor only has to perform the authenticated `GET`. This makes it easy to
+/// swap in a canned executor for testing.
+pub trait QueryExecutor {
+    /// The environment queries are sent to unless overridden
+    fn environment(&self) -> QbEnvironment;
+
+    /// The ID of the company (realm) being queried
+    fn company_id(&self) -> &str;
+
+    /// The base URL requests for `env` are sent to
+    fn base_url(&self, env: QbEnvironment) -> String {
+        env.base_url().to_string()
+    }
+
+    /// Perform a `GET` request against `url`, returning the raw response body
+    fn get(&self, url: &str) -> Result<String, QueryError>;
+
+    /// Perform a `GET` request against `url`, failing with [`QueryError::Timeout`]
+    /// if it takes longer than `timeout`
+    ///
+    /// Executors that can't bound a request's duration send it with
+    /// [`QueryExecutor::get`], ignoring the timeout.
+    fn get_with_timeout(&self, url: &str, timeout: Duration) -> Result<String, QueryError> {
+        let _ = timeout;
+        self.get(url)
+    }
+
+    /// Perform a `POST` request with a JSON `body` against `url`, returning the raw response body
+    ///
+    /// This is only needed for batch requests, see [`count_batch`](crate::count_batch).
+    /// Executors that don't implement it fail those requests.
+    fn post(&self, url: &str, body: &str) -> Result<String, QueryError> {
+        let _ = body;
+        Err(QueryError::Transport(
+            format!("executor does not support POST requests to {url}").into(),
+        ))
+    }
+
+    /// The warning codes that fail a request with [`QueryError::Warning`] instead
+    /// of being reported in [`ResponseMetadata::warnings`]
+    fn escalated_warnings(&self) -> &[String] {
+        &[]
+    }
+}
+
+/// A parsed `QueryResponse` envelope
+#[derive(Debug, PartialEq, Clone)]
+pub struct QueryResponse<QB> {
+    /// The entities returned by the query, empty if nothing matched
+    pub items: Vec<QB>,
+    pub metadata: ResponseMetadata,
+}
+
+/// Paging and timing information returned alongside query results
+#[derive(Debug, PartialEq, Clone, Default)]
+pub struct ResponseMetadata {
+    pub start_position: Option<u32>,
+    pub max_results: Option<u32>,
+    pub total_count: Option<usize>,
+    /// The server timestamp of the response
+    pub time: Option<String>,
+    /// Warnings returned alongside the results
+    pub warnings: Vec<ResponseWarning>,
+}
+
+/// The result of running a query, distinguishing the business-level ways it can end
+#[derive(Debug, PartialEq, Clone)]
+pub enum ExecutionOutcome<QB> {
+    /// The query ran; the rows may be empty if nothing matched
+    Rows(QueryResponse<QB>),
+    /// The query failed validation and was never sent
+    SkippedByValidation(Vec<ValidationError>),
+    /// The API returned a fault instead of results
+    Faulted(FaultInfo),
+}
+
+/// Controls whether and how failed requests are retried
+///
+/// Retries are disabled by default. When enabled, a request is retried if it
+/// fails with a retryable HTTP status (429 or 5xx) or a retryable fault, waiting
+/// `backoff` before the first retry and doubling the wait after each attempt.
+#[derive(Debug, PartialEq, Clone)]
+pub struct RetryPolicy {
+    /// The maximum number of retries after the initial attempt
+    pub max_retries: u32,
+    /// How long to wait before the first retry
+    pub backoff: Duration,
+    /// The fault codes that trigger a retry, or `None` to use [`FaultCode::is_retryable`]
+    pub retry_on: Option<Vec<FaultCode>>,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_retries: 0,
+            backoff: Duration::from_secs(1),
+            retry_on: None,
+        }
+    }
+}
+
+impl RetryPolicy {
+    /// The number of retries used when fault codes are given without a retry count
+    pub const DEFAULT_RETRIES: u32 = 3;
+
+    /// Whether a request that failed with `err` should be retried
+    pub fn should_retry(&self, err: &QueryError) -> bool {
+        match (err, &self.retry_on) {
+            (QueryError::Fault(fault), Some(codes)) => {
+                fault.errors.iter().any(|error| codes.contains(&error.code))
+            }
+            (err, _) => err.is_retryable(),
+        }
+    }
+}
+
+/// A [`QueryExecutor`] that sends requests with a `ureq` agent
+pub struct HttpExecutor<'a> {
+    client: &'a ureq::Agent,
+    environment: QbEnvironment,
+    company_id: String,
+    access_token: String,
+    base_url: Option<String>,
+    escalated_warnings: Vec<String>,
+}
+
+impl<'a> HttpExecutor<'a> {
+    /// Create a new executor for the given company using an OAuth access token
+    pub fn new(
+        client: &'a ureq::Agent,
+        environment: QbEnvironment,
+        company_id: impl Into<String>,
+        access_token: impl Into<String>,
+    ) -> Self {
+        Self {
+            client,
+            environment,
+            company_id: company_id.into(),
+            access_token: access_token.into(),
+            base_url: None,
+            escalated_warnings: Vec::new(),
+        }
+    }
+
+    /// Send requests to `base_url` instead of the environment's API host
+    ///
+    /// This is mainly useful for pointing the executor at a proxy or a local mock server.
+    pub fn with_base_url(mut self, base_url: impl Into<String>) -> Self {
+        self.base_url = Some(base_url.into());
+        self
+    }
+
+    /// Fail requests whose response carries a warning with one of `codes`
+    ///
+    /// This is meant for strict pipelines that would rather stop than continue
+    /// with, e.g., truncated results.
+    pub fn escalate_warnings(mut self, codes: &[&str]) -> Self {
+        self.escalated_warnings = codes.iter().map(|code| code.to_string()).collect();
+        self
+    }
+}
+
+impl HttpExecutor<'_> {
+    fn send_get(&self, url: &str, timeout: Option<Duration>) -> Result<String, QueryError> {
+        let mut config = self.client.get(url).config().http_status_as_error(false);
+        // Only override the agent's own timeout when the query sets one
+        if timeout.is_some() {
+            config = config.timeout_global(timeout);
+        }
+        let response = config
+            .build()
+            .header("Authorization", &format!("Bearer {}", self.access_token))
+            .header("Accept", "application/json")
+            .call();
+        read_body(response)
+    }
+}
+
+impl QueryExecutor for HttpExecutor<'_> {
+    fn environment(&self) -> QbEnvironment {
+        self.environment
+    }
+
+    fn company_id(&self) -> &str {
+        &self.company_id
+    }
+
+    fn base_url(&self, env: QbEnvironment) -> String {
+        match &self.base_url {
+            Some(base_url) => base_url.clone(),
+            None => env.base_url().to_string(),
+        }
+    }
+
+    fn escalated_warnings(&self) -> &[String] {
+        &self.escalated_warnings
+    }
+
+    fn get(&self, url: &str) -> Result<String, QueryError> {
+        self.send_get(url, None)
+    }
+
+    fn get_with_timeout(&self, url: &str, timeout: Duration) -> Result<String, QueryError> {
+        self.send_get(url, Some(timeout))
+    }
+
+    fn post(&self, url: &str, body: &str) -> Result<String, QueryError> {
+        let response = self
+            .client
+            .post(url)
+            .config()
+            .http_status_as_error(false)
+            .build()
+            .header("Authorization", &format!("Bearer {}", self.access_token))
+            .header("Accept", "application/json")
+            .header("Content-Type", "application/json")
+            .send(body);
+        read_body(response)
+    }
+}
+
+/// Read the body of a `ureq` response, turning unsuccessful statuses into errors
+fn read_body(
+    response: Result<ureq::http::Response<ureq::Body>, ureq::Error>,
+) -> Result<String, QueryError> {
+    let mut response = response.map_err(transport_error)?;
+    let status = response.status().as_u16();
+    let body = response
+        .body_mut()
+        .read_to_string()
+        .map_err(transport_error)?;
+
+    match status_error(status, &body) {
+        Some(err) => Err(err),
+        None => Ok(body),
+    }
+}
+
+fn transport_error(err: ureq::Error) -> QueryError {
+    match err {
+        ureq::Error::Timeout(_) => QueryError::Timeout,
+        err => QueryError::Transport(Box::new(err)),
+    }
+}
+
+/// The error for an unsuccessful HTTP status, if the body can't be parsed as a fault
+pub(crate) fn status_error(status: u16, body: &str) -> Option<QueryError> {
+    // Faults are returned with an error status, so let the caller parse them
+    if status >= 400 && !body.contains("\"Fault\"") && !body.contains("\"fault\"") {
+        return Some(QueryError::Http {
+            status,
+            body: body.to_string(),
+        });
+    }
+    None
+}
+
+impl<QB: QBItem + DeserializeOwned> Query<QB> {
+    /// Build the full request URL for this query against the given environment and company
+    pub fn to_url(&self, env: QbEnvironment, company_id: &str) -> String {
+        self.url_from_base(env.base_url(), company_id)
+    }
+
+    pub(crate) fn url_from_base(&self, base_url: &str, company_id: &str) -> String {
+        query_url(base_url, company_id, &self.query_string())
+    }
+
+    /// Execute the query with the given executor, using its default environment
+    pub fn execute_with(&self, executor: &impl QueryExecutor) -> Result<Vec<QB>, QueryError> {
+        self.execute_in(executor.environment(), executor)
+    }
+
+    /// Execute the query with the given executor against a specific environment
+    pub fn execute_in(
+        &self,
+        env: QbEnvironment,
+        executor: &impl QueryExecutor,
+    ) -> Result<Vec<QB>, QueryError> {
+        Ok(self.fetch(env, executor)?.items)
+    }
+
+    /// Execute the query, expecting it to match exactly one entity
+    ///
+    /// Returns [`QueryError::NoResults`] if nothing matched and
+    /// [`QueryError::MultipleResults`] if more than one entity did.
+    pub fn execute_expect_one(&self, executor: &impl QueryExecutor) -> Result<QB, QueryError> {
+        let mut items = self.execute_with(executor)?;
+        match items.len() {
+            0 => Err(QueryError::NoResults),
+            1 => Ok(items.remove(0)),
+            count => Err(QueryError::MultipleResults { count }),
+        }
+    }
+
+    /// Fail each request sent for the query with [`QueryError::Timeout`] if it
+    /// takes longer than `timeout`
+    ///
+    /// This applies on top of any timeout configured on the HTTP client. Each
+    /// retry and each page of a paged query gets the full timeout.
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = Some(timeout);
+        self
+    }
+
+    /// Set the maximum number of times a failed request is retried
+    pub fn retries(mut self, max_retries: u32) -> Self {
+        self.retry.max_retries = max_retries;
+        self
+    }
+
+    /// Retry requests only when they fail with one of the given fault codes
+    ///
+    /// This overrides the default set of retryable faults. If no retry count has
+    /// been set, [`RetryPolicy::DEFAULT_RETRIES`] is used.
+    pub fn retry_on(mut self, codes: &[FaultCode]) -> Self {
+        self.retry.retry_on = Some(codes.to_vec());
+        if self.retry.max_retries == 0 {
+            self.retry.max_retries = RetryPolicy::DEFAULT_RETRIES;
+        }
+        self
+    }
+
+    /// Set how long to wait before the first retry
+    pub fn retry_backoff(mut self, backoff: Duration) -> Self {
+        self.retry.backoff = backoff;
+        self
+    }
+
+    /// Count the entities matching the query without fetching them
+    ///
+    /// This sends [`Query::count_query_string`] and reads `totalCount` from the response,
+    /// failing with [`QueryError::Json`] if the response has none.
+    pub fn count(&self, executor: &impl QueryExecutor) -> Result<usize, QueryError> {
+        let response =
+            self.fetch_rendered(&self.count_query_string(), executor.environment(), executor)?;
+        response
+            .metadata
+            .total_count
+            .ok_or_else(|| serde_json::Error::custom("no totalCount in count response").into())
+    }
+
+    /// Execute the query one page at a time, returning an iterator over the pages
+    ///
+    /// Each request asks for at most `page_size` entities, starting where the
+    /// previous page ended, until a short or empty page is returned. A limit set
+    /// on the query caps the total number of entities fetched, and its offset is
+    /// where paging starts. Iteration stops after the first error.
+    ///
+    /// # Panics
+    /// Panics if `page_size` is zero.
+    pub fn execute_paged<'a, E: QueryExecutor>(
+        &'a self,
+        executor: &'a E,
+        page_size: u32,
+    ) -> Pages<'a, QB, E> {
+        assert!(page_size > 0, "page size must be greater than zero");
+        Pages {
+            query: self,
+            executor,
+            page_size,
+            offset: self.limit.and_then(|limit| limit.offset).unwrap_or(0),
+            remaining: self.limit.map(|limit| limit.number),
+            done: false,
+        }
+    }
+
+    /// Page through the query's results, writing each entity to `writer` as a line of JSON
+    ///
+    /// Pages are fetched as in [`Query::execute_paged`] and written as they
+    /// arrive, so the whole result set is never held in memory. Returns the
+    /// number of entities written. Entities written before an error are left in
+    /// `writer`.
+    ///
+    /// # Panics
+    /// Panics if `page_size` is zero.
+    pub fn execute_ndjson(
+        &self,
+        executor: &impl QueryExecutor,
+        page_size: u32,
+        mut writer: impl Write,
+    ) -> Result<usize, QueryError>
+    where
+        QB: Serialize,
+    {
+        let mut written = 0;
+        for page in self.execute_paged(executor, page_size) {
+            for item in page? {
+                let mut line = serde_json::to_vec(&item)?;
+                line.push(b'\n');
+                writer.write_all(&line).map_err(QueryError::Write)?;
+                written += 1;
+            }
+        }
+        writer.flush().map_err(QueryError::Write)?;
+        Ok(written)
+    }
+
+    /// Send the query and parse the response, retrying according to the query's retry policy
+    fn fetch(
+        &self,
+        env: QbEnvironment,
+        executor: &impl QueryExecutor,
+    ) -> Result<QueryResponse<QB>, QueryError> {
+        self.fetch_rendered(&self.query_string(), env, executor)
+    }
+
+    /// Send an already rendered form of the query, retrying according to the query's retry policy
+    fn fetch_rendered(
+        &self,
+        query: &str,
+        env: QbEnvironment,
+        executor: &impl QueryExecutor,
+    ) -> Result<QueryResponse<QB>, QueryError> {
+        let url = query_url(&executor.base_url(env), executor.company_id(), query);
+        let mut backoff = self.retry.backoff;
+        let mut attempt = 0;
+        loop {
+            let body = match self.timeout {
+                Some(timeout) => executor.get_with_timeout(&url, timeout),
+                None => executor.get(&url),
+            };
+            let result = body
+                .and_then(|body| parse_response(&body))
+                .and_then(|response| escalate_warnings(response, executor.escalated_warnings()));
+            match result {
+                Err(err) if attempt < self.retry.max_retries && self.retry.should_retry(&err) => {
+                    std::thread::sleep(backoff);
+                    backoff *= 2;
+                    attempt += 1;
+                }
+                result => return result,
+            }
+        }
+    }
+
+    /// Execute the query, reporting validation failures and faults as outcomes instead of errors
+    ///
+    /// Only transport and deserialization problems are returned as `Err`. A query
+    /// that fails [`Query::validate`] is not sent at all.
+    pub fn execute_outcome(
+        &self,
+        executor: &impl QueryExecutor,
+    ) -> Result<ExecutionOutcome<QB>, QueryError> {
+        let problems = self.validate();
+        if !problems.is_empty() {
+            return Ok(ExecutionOutcome::SkippedByValidation(problems));
+        }
+
+        match self.fetch(executor.environment(), executor) {
+            Ok(response) => Ok(ExecutionOutcome::Rows(response)),
+            Err(QueryError::Fault(fault)) => Ok(ExecutionOutcome::Faulted(fault)),
+            Err(err) => Err(err),
+        }
+    }
+}
+
+impl<QB: QBItem + DeserializeOwned> CountQuery<QB> {
+    /// Execute the count query with the given executor, returning the number of matching entities
+    pub fn execute_with(&self, executor: &impl QueryExecutor) -> Result<usize, QueryError> {
+        self.query().count(executor)
+    }
+}
+
+/// An iterator over the pages of a query's results, created by [`Query::execute_paged`]
+pub struct Pages<'a, QB, E> {
+    query: &'a Query<QB>,
+    executor: &'a E,
+    page_size: u32,
+    offset: u32,
+    remaining: Option<u32>,
+    done: bool,
+}
+
+impl<QB: QBItem + DeserializeOwned, E: QueryExecutor> Iterator for Pages<'_, QB, E> {
+    type Item = Result<Vec<QB>, QueryError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let number = self
+            .remaining
+            .map_or(self.page_size, |remaining| remaining.min(self.page_size));
+        if self.done || number == 0 {
+            return None;
+        }
+        if self.query.order_required && self.query.order.is_empty() {
+            self.done = true;
+            return Some(Err(QueryError::OrderRequired));
+        }
+
+        let limit = Limit {
+            number,
+            offset: (self.offset > 0).then_some(self.offset),
+        };
+        let rendered = self.query.render(Some(limit));
+        let items =
+            match self
+                .query
+                .fetch_rendered(&rendered, self.executor.environment(), self.executor)
+            {
+                Ok(response) => response.items,
+                Err(err) => {
+                    self.done = true;
+                    return Some(Err(err));
+                }
+            };
+
+        let fetched = items.len() as u32;
+        self.done = fetched < number;
+        self.offset += fetched;
+        if let Some(remaining) = &mut self.remaining {
+            *remaining -= fetched.min(*remaining);
+        }
+        (!items.is_empty()).then_some(Ok(items))
+    }
+}
+
+/// Fail with the first warning in `response` whose code is in `escalated`
+pub(crate) fn escalate_warnings<QB>(
+    response: QueryResponse<QB>,
+    escalated: &[String],
+) -> Result<QueryResponse<QB>, QueryError> {
+    let warnings = &response.metadata.warnings;
+    if let Some(warning) = warnings.iter().find(|w| escalated.contains(&w.code)) {
+        return Err(QueryError::Warning(warning.clone()));
+    }
+    Ok(response)
+}
+
+/// Build the URL batch requests are posted to
+pub(crate) fn batch_url(base_url: &str, company_id: &str) -> String {
+    format!("{base_url}/company/{company_id}/batch")
+}
+
+/// Build the request URL for a rendered query
+fn query_url(base_url: &str, company_id: &str, query: &str) -> String {
+    format!(
+        "{}/company/{}/query?query={}",
+        base_url,
+        company_id,
+        urlencoding::encode(query)
+    )
+}
+
+/// Parse a raw response body into the entities of type `QB` and their metadata
+pub(crate) fn parse_response<QB: QBItem + DeserializeOwned>(
+    body: &str,
+) -> Result<QueryResponse<QB>, QueryError> {
+    parse_value(serde_json::from_str(body)?)
+}
+
+/// Parse a response that has already been decoded as JSON
+pub(crate) fn parse_value<QB: QBItem + DeserializeOwned>(
+    mut response: serde_json::Value,
+) -> Result<QueryResponse<QB>, QueryError> {
+    if let Some(fault) = FaultInfo::from_response(&response) {
+        return Err(QueryError::Fault(fault));
+    }
+
+    let time = response["time"].as_str().map(String::from);
+    let warnings = ResponseWarning::from_response(&response);
+    let envelope = &mut response["QueryResponse"];
+    let items = match envelope.get_mut(QB::name()) {
+        Some(items) => serde_json::from_value(items.take())?,
+        None => Vec::new(),
+    };
+    let number = |key: &str| envelope.get(key).and_then(serde_json::Value::as_u64);
+    let metadata = ResponseMetadata {
+        start_position: number("startPosition").map(|n| n as u32),
+        max_results: number("maxResults").map(|n| n as u32),
+        total_count: number("totalCount").map(|n| n as usize),
+        time,
+        warnings,
+    };
+
+    Ok(QueryResponse { items, metadata })
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use crate::{Order, qb_sql};
+    use quickbooks_types::Customer;
+    use std::cell::RefCell;
+
+    /// Executor returning canned responses and recording the requested URLs
+    pub(crate) struct MockExecutor {
+        responses: RefCell<Vec<String>>,
+        pub(crate) urls: RefCell<Vec<String>>,
+        pub(crate) bodies: RefCell<Vec<String>>,
+        escalated_warnings: Vec<String>,
+    }
+
+    impl MockExecutor {
+        pub(crate) fn new<'a>(responses: impl IntoIterator<Item = &'a str>) -> Self {
+            Self {
+                responses: RefCell::new(responses.into_iter().map(String::from).collect()),
+                urls: RefCell::new(Vec::new()),
+                bodies: RefCell::new(Vec::new()),
+                escalated_warnings: Vec::new(),
+            }
+        }
+    }
+
+    impl QueryExecutor for MockExecutor {
+        fn environment(&self) -> QbEnvironment {
+            QbEnvironment::Production
+        }
+
+        fn company_id(&self) -> &str {
+            "1234"
+        }
+
+        fn get(&self, url: &str) -> Result<String, QueryError> {
+            self.urls.borrow_mut().push(url.to_string());
+            Ok(self.responses.borrow_mut().remove(0))
+        }
+
+        fn post(&self, url: &str, body: &str) -> Result<String, QueryError> {
+            self.bodies.borrow_mut().push(body.to_string());
+            self.get(url)
+        }
+
+        fn escalated_warnings(&self) -> &[String] {
+            &self.escalated_warnings
+        }
+    }
+
+    #[test]
+    fn test_execute_in_sandbox() {
+        let executor = MockExecutor::new([
+            r#"{"QueryResponse":{"Customer":[{"Id":"1"},{"Id":"2"}]},"time":"2024-01-01T00:00:00Z"}"#,
+        ]);
+        let query = qb_sql!(
+            select * from Customer
+            where display_name = "John"
+        );
+
+        let customers = query.execute_in(QbEnvironment::Sandbox, &executor).unwrap();
+
+        assert_eq!(customers.len(), 2);
+        assert_eq!(
+            executor.urls.borrow()[0],
+            "https://sandbox-quickbooks.api.intuit.com/v3/company/1234/query?query=select%20%2A%20from%20Customer%20where%20DisplayName%20%3D%20%27John%27"
+        );
+    }
+
+    #[test]
+    fn test_execute_with_default_environment() {
+        let executor = MockExecutor::new([r#"{"QueryResponse":{}}"#]);
+        let query = qb_sql!(select * from Customer);
+
+        let customers = query.execute_with(&executor).unwrap();
+
+        assert!(customers.is_empty());
+        assert!(
+            executor.urls.borrow()[0]
+                .starts_with("https://quickbooks.api.intuit.com/v3/company/1234/")
+        );
+    }
+
+    #[test]
+    fn test_execute_fault() {
+        let executor = MockExecutor::new([
+            r#"{"Fault":{"Error":[{"Message":"Error parsing query","code":"4000"}],"type":"ValidationFault"}}"#,
+        ]);
+        let query = qb_sql!(select * from Customer);
+
+        let err = query.execute_with(&executor).unwrap_err();
+
+        assert!(matches!(
+            err,
+            QueryError::Fault(ref fault) if fault.code() == Some(crate::FaultCode::QueryParserError)
+        ));
+        assert!(!err.is_retryable());
+    }
+
+    #[test]
+    fn test_outcome_rows() {
+        let executor = MockExecutor::new([
+            r#"{"QueryResponse":{"Customer":[{"Id":"1"}],"startPosition":1,"maxResults":1},"time":"2024-01-01T00:00:00Z"}"#,
+        ]);
+        let query = qb_sql!(select * from Customer where display_name = "John");
+
+        let Ok(ExecutionOutcome::Rows(response)) = query.execute_outcome(&executor) else {
+            panic!("expected rows");
+        };
+        assert_eq!(response.items.len(), 1);
+        assert_eq!(
+            response.metadata,
+            ResponseMetadata {
+                start_position: Some(1),
+                max_results: Some(1),
+                total_count: None,
+                time: Some("2024-01-01T00:00:00Z".to_string()),
+                warnings: Vec::new(),
+            }
+        );
+    }
+
+    const WARNING_RESPONSE: &str = r#"{
+        "QueryResponse":{"Customer":[{"Id":"1"}],"startPosition":1,"maxResults":1},
+        "Warnings":{"Warning":[
+            {"Message":"Field is deprecated","Detail":"Use PrimaryEmailAddr","code":"2500","element":"Email"},
+            {"Message":"Result set truncated","code":"2510"}
+        ]},
+        "time":"2024-01-01T00:00:00Z"
+    }"#;
+
+    #[test]
+    fn test_response_warnings() {
+        let executor = MockExecutor::new([WARNING_RESPONSE]);
+        let query = qb_sql!(select * from Customer where display_name = "John");
+
+        let Ok(ExecutionOutcome::Rows(response)) = query.execute_outcome(&executor) else {
+            panic!("expected rows");
+        };
+        assert_eq!(response.items.len(), 1);
+        assert_eq!(
+            response.metadata.warnings,
+            vec![
+                ResponseWarning {
+                    code: "2500".to_string(),
+                    message: "Field is deprecated".to_string(),
+                    element: Some("Email".to_string()),
+                },
+                ResponseWarning {
+                    code: "2510".to_string(),
+                    message: "Result set truncated".to_string(),
+                    element: None,
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_escalated_warning_is_error() {
+        let mut executor = MockExecutor::new([WARNING_RESPONSE, WARNING_RESPONSE]);
+        let query = qb_sql!(select * from Customer where display_name = "John");
+
+        executor.escalated_warnings = vec!["9999".to_string()];
+        assert_eq!(query.execute_with(&executor).unwrap().len(), 1);
+
+        executor.escalated_warnings = vec!["2510".to_string()];
+        let Err(QueryError::Warning(warning)) = query.execute_with(&executor) else {
+            panic!("expected an escalated warning");
+        };
+        assert_eq!(warning.code, "2510");
+        assert_eq!(
+            QueryError::Warning(warning).to_string(),
+            "QuickBooks warning: Result set truncated (2510)"
+        );
+    }
+
+    #[test]
+    fn test_outcome_empty_rows() {
+        let executor = MockExecutor::new([r#"{"QueryResponse":{},"time":"2024-01-01T00:00:00Z"}"#]);
+        let query = qb_sql!(select * from Customer where display_name = "Nobody");
+
+        let Ok(ExecutionOutcome::Rows(response)) = query.execute_outcome(&executor) else {
+            panic!("expected rows");
+        };
+        assert!(response.items.is_empty());
+    }
+
+    #[test]
+    fn test_outcome_skipped_by_validation() {
+        let executor = MockExecutor::new([]);
+        let ids: Vec<u32> = Vec::new();
+        let query = qb_sql!(select * from Customer where id in (ids));
+
+        let Ok(ExecutionOutcome::SkippedByValidation(problems)) = query.execute_outcome(&executor)
+        else {
+            panic!("expected the query to be skipped");
+        };
+        assert_eq!(
+            problems,
+            vec![ValidationError::EmptyInList { field: "Id".into() }]
+        );
+        assert!(executor.urls.borrow().is_empty());
+    }
+
+    #[test]
+    fn test_outcome_faulted() {
+        let executor = MockExecutor::new([
+            r#"{"Fault":{"Error":[{"Message":"Object Not Found","code":"610"}],"type":"ValidationFault"}}"#,
+        ]);
+        let query = qb_sql!(select * from Customer where id = 1);
+
+        let Ok(ExecutionOutcome::Faulted(fault)) = query.execute_outcome(&executor) else {
+            panic!("expected a fault");
+        };
+        assert!(fault.is_not_found());
+    }
+
+    #[test]
+    fn test_outcome_invalid_body_is_error() {
+        let executor = MockExecutor::new(["not json"]);
+        let query = qb_sql!(select * from Customer where id = 1);
+
+        assert!(matches!(
+            query.execute_outcome(&executor),
+            Err(QueryError::Json(_))
+        ));
+    }
+
+    const STALE_OBJECT: &str = r#"{"Fault":{"Error":[{"Message":"Stale Object Error","code":"5010"}],"type":"ValidationFault"}}"#;
+    const THROTTLED: &str =
+        r#"{"Fault":{"Error":[{"Message":"ThrottleExceeded","code":"3001"}],"type":"SERVICE"}}"#;
+
+    #[test]
+    fn test_retry_on_custom_code() {
+        let executor = MockExecutor::new([
+            STALE_OBJECT,
+            r#"{"QueryResponse":{"Customer":[{"Id":"1"}]}}"#,
+        ]);
+        let query = qb_sql!(select * from Customer)
+            .retry_on(&[FaultCode::StaleObject])
+            .retry_backoff(Duration::ZERO);
+
+        let customers = query.execute_with(&executor).unwrap();
+
+        assert_eq!(customers.len(), 1);
+        assert_eq!(executor.urls.borrow().len(), 2);
+    }
+
+    #[test]
+    fn test_retry_on_unlisted_code_fails_immediately() {
+        let executor = MockExecutor::new([THROTTLED, r#"{"QueryResponse":{}}"#]);
+        let query = qb_sql!(select * from Customer)
+            .retry_on(&[FaultCode::StaleObject])
+            .retry_backoff(Duration::ZERO);
+
+        let err = query.execute_with(&executor).unwrap_err();
+
+        assert!(
+            matches!(err, QueryError::Fault(ref fault) if fault.code() == Some(FaultCode::Throttled))
+        );
+        assert_eq!(executor.urls.borrow().len(), 1);
+    }
+
+    #[test]
+    fn test_retries_exhausted() {
+        let executor = MockExecutor::new([THROTTLED, THROTTLED, THROTTLED]);
+        let query = qb_sql!(select * from Customer)
+            .retries(2)
+            .retry_backoff(Duration::ZERO);
+
+        assert!(query.execute_with(&executor).unwrap_err().is_retryable());
+        assert_eq!(executor.urls.borrow().len(), 3);
+    }
+
+    #[test]
+    fn test_no_retries_by_default() {
+        let executor = MockExecutor::new([THROTTLED, r#"{"QueryResponse":{}}"#]);
+        let query = qb_sql!(select * from Customer);
+
+        assert!(query.execute_with(&executor).is_err());
+        assert_eq!(executor.urls.borrow().len(), 1);
+    }
+
+    /// A response containing customers with the given IDs
+    fn customers(ids: std::ops::Range<u32>) -> String {
+        let customers: Vec<_> = ids.map(|id| format!(r#"{{"Id":"{id}"}}"#)).collect();
+        format!(
+            r#"{{"QueryResponse":{{"Customer":[{}]}}}}"#,
+            customers.join(",")
+        )
+    }
+
+    #[test]
+    fn test_execute_expect_one() {
+        let responses = [customers(1..1), customers(1..2), customers(1..4)];
+        let executor = MockExecutor::new(responses.iter().map(String::as_str));
+        let query = qb_sql!(select * from Customer where display_name = "Settings");
+
+        assert!(matches!(
+            query.execute_expect_one(&executor),
+            Err(QueryError::NoResults)
+        ));
+        let customer = query.execute_expect_one(&executor).unwrap();
+        assert_eq!(customer.id.as_deref(), Some("1"));
+        assert!(matches!(
+            query.execute_expect_one(&executor),
+            Err(QueryError::MultipleResults { count: 3 })
+        ));
+    }
+
+    /// The decoded query strings of the requests sent by `executor`
+    fn sent_queries(executor: &MockExecutor) -> Vec<String> {
+        executor
+            .urls
+            .borrow()
+            .iter()
+            .map(|url| {
+                let (_, query) = url.split_once("query=").unwrap();
+                urlencoding::decode(query).unwrap().into_owned()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_execute_paged() {
+        let responses = [customers(1..3), customers(3..5), customers(5..6)];
+        let executor = MockExecutor::new(responses.iter().map(String::as_str));
+        let query = qb_sql!(select * from Customer where active = true);
+
+        let pages: Vec<_> = query
+            .execute_paged(&executor, 2)
+            .map(|page| page.unwrap().len())
+            .collect();
+
+        assert_eq!(pages, vec![2, 2, 1]);
+        assert_eq!(
+            sent_queries(&executor),
+            vec![
+                "select * from Customer where Active = 'true' MAXRESULTS 2",
+                "select * from Customer where Active = 'true' STARTPOSITION 3 MAXRESULTS 2",
+                "select * from Customer where Active = 'true' STARTPOSITION 5 MAXRESULTS 2",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_execute_paged_require_order() {
+        let executor = MockExecutor::new([customers(1..2).as_str()]);
+        let query = qb_sql!(select * from Customer).require_order();
+
+        let mut pages = query.execute_paged(&executor, 2);
+        assert!(matches!(pages.next(), Some(Err(QueryError::OrderRequired))));
+        assert!(pages.next().is_none());
+        assert!(executor.urls.borrow().is_empty());
+
+        let query = qb_sql!(select * from Customer order by id).require_order();
+        let pages: Vec<_> = query.execute_paged(&executor, 2).collect();
+        assert_eq!(pages.len(), 1);
+        assert!(pages[0].is_ok());
+    }
+
+    #[test]
+    fn test_execute_ndjson() {
+        let responses = [customers(1..3), customers(3..4)];
+        let executor = MockExecutor::new(responses.iter().map(String::as_str));
+        let query = qb_sql!(select * from Customer order by id);
+
+        let mut output = Vec::new();
+        let written = query.execute_ndjson(&executor, 2, &mut output).unwrap();
+
+        let output = String::from_utf8(output).unwrap();
+        let ids: Vec<_> = output
+            .lines()
+            .map(|line| serde_json::from_str::<Customer>(line).unwrap().id)
+            .collect();
+        assert_eq!(written, 3);
+        assert_eq!(
+            ids,
+            vec![Some("1".into()), Some("2".into()), Some("3".into())]
+        );
+        assert!(output.ends_with('\n'));
+        assert_eq!(executor.urls.borrow().len(), 2);
+    }
+
+    #[test]
+    fn test_execute_paged_empty() {
+        let executor = MockExecutor::new([r#"{"QueryResponse":{}}"#]);
+        let query = qb_sql!(select * from Customer);
+
+        assert!(query.execute_paged(&executor, 10).next().is_none());
+        assert_eq!(executor.urls.borrow().len(), 1);
+    }
+
+    #[test]
+    fn test_execute_paged_exact_final_page() {
+        let responses = [customers(1..3), customers(3..5), customers(0..0)];
+        let executor = MockExecutor::new(responses.iter().map(String::as_str));
+        let query = qb_sql!(select * from Customer);
+
+        assert_eq!(query.execute_paged(&executor, 2).count(), 2);
+        assert_eq!(executor.urls.borrow().len(), 3);
+    }
+
+    #[test]
+    fn test_execute_paged_error_stops_iteration() {
+        let first = customers(1..3);
+        let executor = MockExecutor::new([first.as_str(), THROTTLED]);
+        let query = qb_sql!(select * from Customer);
+
+        let mut pages = query.execute_paged(&executor, 2);
+
+        assert_eq!(pages.next().unwrap().unwrap().len(), 2);
+        assert!(pages.next().unwrap().unwrap_err().is_retryable());
+        assert!(pages.next().is_none());
+        assert_eq!(executor.urls.borrow().len(), 2);
+    }
+
+    #[test]
+    fn test_execute_paged_respects_limit() {
+        let responses = [customers(2..4), customers(4..5)];
+        let executor = MockExecutor::new(responses.iter().map(String::as_str));
+        let query = qb_sql!(select * from Customer limit 3 offset 1);
+
+        let total: usize = query
+            .execute_paged(&executor, 2)
+            .map(|page| page.unwrap().len())
+            .sum();
+
+        assert_eq!(total, 3);
+        assert_eq!(
+            sent_queries(&executor),
+            vec![
+                "select * from Customer STARTPOSITION 2 MAXRESULTS 2",
+                "select * from Customer STARTPOSITION 4 MAXRESULTS 1",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_query_count() {
+        let executor = MockExecutor::new([r#"{"QueryResponse":{"totalCount":7}}"#]);
+        let query = qb_sql!(
+            select * from Customer
+            where balance > 0
+            order by balance desc
+            limit 5
+        );
+
+        assert_eq!(query.count(&executor).unwrap(), 7);
+        assert_eq!(
+            sent_queries(&executor),
+            vec!["select count(*) from Customer where Balance > '0'"]
+        );
+        // Only the count request drops the ordering
+        assert_eq!(query.ordering()[0].order(), &Order::Desc);
+    }
+
+    #[test]
+    fn test_query_count_without_total() {
+        let executor = MockExecutor::new([r#"{"QueryResponse":{}}"#]);
+        let query = qb_sql!(select * from Customer);
+
+        assert!(matches!(query.count(&executor), Err(QueryError::Json(_))));
+    }
+
+    #[test]
+    fn test_count_query() {
+        let executor = MockExecutor::new([
+            r#"{"QueryResponse":{"totalCount":42},"time":"2024-01-01T00:00:00Z"}"#,
+        ]);
+        let query = qb_sql!(select count from Customer where active = true);
+
+        assert_eq!(query.execute_with(&executor).unwrap(), 42);
+        assert_eq!(
+            sent_queries(&executor),
+            vec!["select count(*) from Customer where Active = 'true'"]
+        );
+    }
+}
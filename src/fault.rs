@@ -0,0 +1,309 @@
+use serde_json::Value;
+
+/// Documented Intuit fault codes returned by the QuickBooks Online API
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Hash)]
+pub enum FaultCode {
+    /// 500: The operation is not supported for this entity
+    UnsupportedOperation,
+    /// 610: The requested object does not exist or has been made inactive
+    ObjectNotFound,
+    /// 3001: Too many requests were sent in a short period of time
+    Throttled,
+    /// 3100: The application is not authorized to access this company
+    AuthorizationFailed,
+    /// 3200: The access token is invalid or expired
+    AuthenticationFailed,
+    /// 4000: The query could not be parsed
+    QueryParserError,
+    /// 5010: The object was modified since it was last read
+    StaleObject,
+    /// 6000: A business validation rule was violated
+    BusinessValidation,
+    /// 10000: An unexpected error occurred on Intuit's side
+    InternalServerError,
+    /// Any other fault code
+    Other(u32),
+}
+
+impl FaultCode {
+    /// Map a numeric fault code to its variant
+    pub fn from_code(code: u32) -> Self {
+        match code {
+            500 => FaultCode::UnsupportedOperation,
+            610 => FaultCode::ObjectNotFound,
+            3001 => FaultCode::Throttled,
+            3100 => FaultCode::AuthorizationFailed,
+            3200 => FaultCode::AuthenticationFailed,
+            4000 => FaultCode::QueryParserError,
+            5010 => FaultCode::StaleObject,
+            6000 => FaultCode::BusinessValidation,
+            10000 => FaultCode::InternalServerError,
+            code => FaultCode::Other(code),
+        }
+    }
+
+    /// The numeric fault code
+    pub fn code(self) -> u32 {
+        match self {
+            FaultCode::UnsupportedOperation => 500,
+            FaultCode::ObjectNotFound => 610,
+            FaultCode::Throttled => 3001,
+            FaultCode::AuthorizationFailed => 3100,
+            FaultCode::AuthenticationFailed => 3200,
+            FaultCode::QueryParserError => 4000,
+            FaultCode::StaleObject => 5010,
+            FaultCode::BusinessValidation => 6000,
+            FaultCode::InternalServerError => 10000,
+            FaultCode::Other(code) => code,
+        }
+    }
+
+    /// Whether the request may succeed if sent again later
+    pub fn is_retryable(self) -> bool {
+        matches!(self, FaultCode::Throttled | FaultCode::InternalServerError)
+    }
+
+    /// Whether the fault was caused by invalid or insufficient credentials
+    pub fn is_auth(self) -> bool {
+        matches!(
+            self,
+            FaultCode::AuthorizationFailed | FaultCode::AuthenticationFailed
+        )
+    }
+
+    /// Whether the fault indicates that the requested object does not exist
+    pub fn is_not_found(self) -> bool {
+        self == FaultCode::ObjectNotFound
+    }
+}
+
+/// A single error within a fault returned by the API
+#[derive(Debug, PartialEq, Clone)]
+pub struct FaultError {
+    pub code: FaultCode,
+    pub message: String,
+    pub detail: Option<String>,
+    pub element: Option<String>,
+}
+
+/// A fault returned by the API in place of a query response
+#[derive(Debug, PartialEq, Clone)]
+pub struct FaultInfo {
+    /// The fault type, e.g. `ValidationFault` or `AUTHENTICATION`
+    pub fault_type: String,
+    pub errors: Vec<FaultError>,
+}
+
+impl FaultInfo {
+    /// Extract the fault from a response body, if it contains one
+    ///
+    /// Intuit is inconsistent about casing (authentication faults use
+    /// lowercase keys), so both forms are accepted.
+    pub fn from_response(response: &Value) -> Option<Self> {
+        let fault = get(response, "Fault")?;
+        let errors = get(fault, "Error")
+            .and_then(Value::as_array)
+            .map(|errors| errors.iter().map(FaultError::from_json).collect())
+            .unwrap_or_default();
+
+        Some(FaultInfo {
+            fault_type: get_str(fault, "type").unwrap_or_default(),
+            errors,
+        })
+    }
+
+    /// The code of the first error in the fault
+    pub fn code(&self) -> Option<FaultCode> {
+        self.errors.first().map(|error| error.code)
+    }
+
+    /// Whether any error in the fault is retryable
+    pub fn is_retryable(&self) -> bool {
+        self.errors.iter().any(|error| error.code.is_retryable())
+    }
+
+    /// Whether any error in the fault is an authentication or authorization failure
+    pub fn is_auth(&self) -> bool {
+        self.errors.iter().any(|error| error.code.is_auth())
+    }
+
+    /// Whether any error in the fault is an object not found error
+    pub fn is_not_found(&self) -> bool {
+        self.errors.iter().any(|error| error.code.is_not_found())
+    }
+}
+
+/// A warning returned alongside the results of an otherwise successful request,
+/// e.g. for use of a deprecated field
+#[derive(Debug, PartialEq, Clone)]
+pub struct ResponseWarning {
+    pub code: String,
+    pub message: String,
+    pub element: Option<String>,
+}
+
+impl ResponseWarning {
+    /// Extract the warnings from a response body, which may have none
+    ///
+    /// The warnings are read from `Warnings.Warning`, or from `Warnings` when it
+    /// holds the list directly.
+    pub fn from_response(response: &Value) -> Vec<Self> {
+        let Some(warnings) = get(response, "Warnings") else {
+            return Vec::new();
+        };
+        get(warnings, "Warning")
+            .unwrap_or(warnings)
+            .as_array()
+            .map(|warnings| warnings.iter().map(ResponseWarning::from_json).collect())
+            .unwrap_or_default()
+    }
+
+    fn from_json(warning: &Value) -> Self {
+        let code = match get(warning, "code") {
+            Some(Value::String(code)) => code.clone(),
+            Some(Value::Number(code)) => code.to_string(),
+            _ => String::new(),
+        };
+
+        ResponseWarning {
+            code,
+            message: get_str(warning, "Message").unwrap_or_default(),
+            element: get_str(warning, "element").filter(|element| !element.is_empty()),
+        }
+    }
+}
+
+impl std::fmt::Display for ResponseWarning {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} ({})", self.message, self.code)?;
+        if let Some(element) = &self.element {
+            write!(f, " on {element}")?;
+        }
+        Ok(())
+    }
+}
+
+impl FaultError {
+    fn from_json(error: &Value) -> Self {
+        let code = match get(error, "code") {
+            Some(Value::String(code)) => code.parse().unwrap_or_default(),
+            Some(Value::Number(code)) => code.as_u64().unwrap_or_default() as u32,
+            _ => 0,
+        };
+
+        FaultError {
+            code: FaultCode::from_code(code),
+            message: get_str(error, "Message").unwrap_or_default(),
+            detail: get_str(error, "Detail"),
+            element: get_str(error, "element").filter(|element| !element.is_empty()),
+        }
+    }
+}
+
+impl std::fmt::Display for FaultInfo {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.fault_type)?;
+        for error in &self.errors {
+            write!(f, "; {} ({})", error.message, error.code.code())?;
+        }
+        Ok(())
+    }
+}
+
+/// Look up a key by its given casing, falling back to the lowercase form
+fn get<'a>(value: &'a Value, key: &str) -> Option<&'a Value> {
+    value
+        .get(key)
+        .or_else(|| value.get(key.to_ascii_lowercase()))
+}
+
+fn get_str(value: &Value, key: &str) -> Option<String> {
+    get(value, key).and_then(Value::as_str).map(String::from)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::QueryError;
+
+    fn parse(body: &str) -> FaultInfo {
+        FaultInfo::from_response(&serde_json::from_str(body).unwrap()).unwrap()
+    }
+
+    #[test]
+    fn test_object_not_found() {
+        let fault = parse(
+            r#"{"Fault":{"Error":[{"Message":"Object Not Found","Detail":"Object Not Found : Something you're trying to use has been made inactive.","code":"610","element":""}],"type":"ValidationFault"},"time":"2024-01-01T00:00:00.000-08:00"}"#,
+        );
+
+        assert_eq!(fault.fault_type, "ValidationFault");
+        assert_eq!(fault.code(), Some(FaultCode::ObjectNotFound));
+        assert_eq!(fault.errors[0].element, None);
+        assert!(fault.is_not_found());
+        assert!(!fault.is_retryable());
+        assert!(!fault.is_auth());
+    }
+
+    #[test]
+    fn test_authentication_failed() {
+        let fault = parse(
+            r#"{"fault":{"error":[{"message":"message=AuthenticationFailed; errorCode=003200; statusCode=401","detail":"Token expired","code":"3200"}],"type":"AUTHENTICATION"},"time":"2024-01-01T00:00:00.000-08:00"}"#,
+        );
+
+        assert_eq!(fault.fault_type, "AUTHENTICATION");
+        assert_eq!(fault.code(), Some(FaultCode::AuthenticationFailed));
+        assert_eq!(fault.errors[0].detail.as_deref(), Some("Token expired"));
+        assert!(fault.is_auth());
+        assert!(!fault.is_retryable());
+    }
+
+    #[test]
+    fn test_unsupported_operation() {
+        let fault = parse(
+            r#"{"Fault":{"Error":[{"Message":"Unsupported Operation","Detail":"Operation Could not find resource for relative : /v3/company/1/query is not supported.","code":"500"}],"type":"ValidationFault"}}"#,
+        );
+
+        assert_eq!(fault.code(), Some(FaultCode::UnsupportedOperation));
+        assert!(!fault.is_retryable());
+        assert!(!fault.is_not_found());
+    }
+
+    #[test]
+    fn test_throttled() {
+        let fault = parse(
+            r#"{"Fault":{"Error":[{"Message":"message=ThrottleExceeded; errorCode=003001; statusCode=429","Detail":"The request limit was reached.","code":"3001"}],"type":"SERVICE"}}"#,
+        );
+
+        assert_eq!(fault.code(), Some(FaultCode::Throttled));
+        assert!(fault.is_retryable());
+        assert!(QueryError::Fault(fault).is_retryable());
+    }
+
+    #[test]
+    fn test_query_parser_error() {
+        let fault = parse(
+            r#"{"Fault":{"Error":[{"Message":"Error parsing query","Detail":"QueryParserError: Encountered \"LIMIT\" at line 1, column 30.","code":"4000"}],"type":"ValidationFault"}}"#,
+        );
+
+        assert_eq!(fault.code(), Some(FaultCode::QueryParserError));
+        assert!(!fault.is_retryable());
+        assert!(!fault.is_auth());
+    }
+
+    #[test]
+    fn test_unknown_code() {
+        let fault = parse(
+            r#"{"Fault":{"Error":[{"Message":"Duplicate Name Exists Error","code":"6240","element":"DisplayName"}],"type":"ValidationFault"}}"#,
+        );
+
+        assert_eq!(fault.code(), Some(FaultCode::Other(6240)));
+        assert_eq!(fault.errors[0].element.as_deref(), Some("DisplayName"));
+        assert_eq!(FaultCode::from_code(6240).code(), 6240);
+    }
+
+    #[test]
+    fn test_no_fault() {
+        let response = serde_json::json!({"QueryResponse": {}});
+        assert_eq!(FaultInfo::from_response(&response), None);
+    }
+}
@@ -0,0 +1,116 @@
+use std::fmt::Display;
+
+use quickbooks_types::QBItem;
+#[cfg(feature = "api")]
+use serde::de::DeserializeOwned;
+
+use crate::{Operator, Query, WhereClause};
+#[cfg(feature = "api")]
+use crate::{QueryError, QueryExecutor};
+
+/// The most entities QuickBooks returns in one response
+#[cfg(feature = "api")]
+const MAX_PAGE_SIZE: u32 = 1000;
+
+/// A journal entry whose lines can be checked for the accounts they post to
+///
+/// QuickBooks can only filter journal entries by the fields listed in
+/// [`entity_metadata("JournalEntry")`](crate::entity_metadata); the accounts on
+/// their lines aren't queryable. Implement this for the typed entity to filter
+/// by account client side with [`Query::fetch_then_filter_by_account`].
+pub trait JournalEntryLines {
+    /// The IDs of the accounts referenced by the entry's lines
+    fn line_account_ids(&self) -> Vec<&str>;
+}
+
+impl<QB: QBItem + JournalEntryLines> Query<QB> {
+    /// Query the journal entries with a `TxnDate` between `start` and `end`, inclusive
+    pub fn journal_entries_between<T: Display>(start: T, end: T) -> Self {
+        Query::new().with_condition(
+            WhereClause::new("TxnDate", Operator::Between)
+                .add_value(start)
+                .add_value(end),
+        )
+    }
+
+    /// Query the journal entries with the given document number
+    pub fn journal_entry_by_doc_number<T: Display>(doc_number: T) -> Self {
+        Query::new()
+            .with_condition(WhereClause::new("DocNumber", Operator::Equal).add_value(doc_number))
+    }
+}
+
+#[cfg(feature = "api")]
+impl<QB: QBItem + DeserializeOwned + JournalEntryLines> Query<QB> {
+    /// Fetch every entry the query matches and keep those with a line posting to `account_id`
+    ///
+    /// All matching entries are downloaded, so the query should be narrowed to a
+    /// date window first, e.g. with [`Query::journal_entries_between`].
+    pub fn fetch_then_filter_by_account(
+        &self,
+        executor: &impl QueryExecutor,
+        account_id: &str,
+    ) -> Result<Vec<QB>, QueryError> {
+        let mut entries = Vec::new();
+        for page in self.execute_paged(executor, MAX_PAGE_SIZE) {
+            entries.extend(
+                page?
+                    .into_iter()
+                    .filter(|entry| entry.line_account_ids().contains(&account_id)),
+            );
+        }
+        Ok(entries)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use quickbooks_types::Customer;
+
+    // The typed journal entry's line layout isn't needed to test the helpers, so
+    // customers stand in for journal entries, with their display name as the
+    // comma separated account IDs of their lines.
+    impl JournalEntryLines for Customer {
+        fn line_account_ids(&self) -> Vec<&str> {
+            self.display_name
+                .as_deref()
+                .map(|accounts| accounts.split(',').collect())
+                .unwrap_or_default()
+        }
+    }
+
+    #[test]
+    fn test_journal_entry_helpers() {
+        assert_eq!(
+            Query::<Customer>::journal_entries_between("2024-01-01", "2024-01-31").query_string(),
+            "select * from Customer where TxnDate BETWEEN '2024-01-01' AND '2024-01-31'"
+        );
+        assert_eq!(
+            Query::<Customer>::journal_entry_by_doc_number("JE-1042").query_string(),
+            "select * from Customer where DocNumber = 'JE-1042'"
+        );
+    }
+
+    #[cfg(feature = "api")]
+    #[test]
+    fn test_fetch_then_filter_by_account() {
+        use crate::executor::tests::MockExecutor;
+
+        let executor = MockExecutor::new([r#"{"QueryResponse":{"Customer":[
+            {"Id":"1","DisplayName":"123,400"},
+            {"Id":"2","DisplayName":"400,500"},
+            {"Id":"3","DisplayName":"123"},
+            {"Id":"4"}
+        ]}}"#]);
+        let query = Query::<Customer>::journal_entries_between("2024-01-01", "2024-01-31");
+
+        let entries = query
+            .fetch_then_filter_by_account(&executor, "123")
+            .unwrap();
+
+        let ids: Vec<_> = entries.iter().map(|entry| entry.id.as_deref()).collect();
+        assert_eq!(ids, vec![Some("1"), Some("3")]);
+        assert_eq!(executor.urls.borrow().len(), 1);
+    }
+}
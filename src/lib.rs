@@ -1,16 +1,417 @@
-use std::fmt::Display;
+use std::borrow::Cow;
+use std::fmt::{Display, Write};
+use std::ops::Range;
+use std::sync::Arc;
 
+#[cfg(feature = "async")]
+mod async_executor;
+#[cfg(feature = "api")]
+mod batch;
+#[cfg(feature = "api")]
+mod cdc;
+mod delivery;
+mod error;
+#[cfg(feature = "api")]
+mod executor;
+#[cfg(feature = "api")]
+mod fault;
+mod journal;
+mod lint;
+mod metadata;
+mod parse;
+#[cfg(feature = "api")]
+mod realms;
+#[cfg(feature = "serde")]
+mod saved_search;
+mod sort;
+mod time;
+mod value;
+
+pub use self::time::UtcOffset;
+#[cfg(feature = "async")]
+pub use async_executor::AsyncHttpExecutor;
+#[cfg(feature = "api")]
+pub use batch::{BatchHandle, BatchResults, BatchSummary, QueryBatch, count_batch};
+#[cfg(feature = "api")]
+pub use cdc::{CdcQuery, CdcResults};
+pub use delivery::DeliveryStatus;
+pub use error::{QueryError, ValidationError};
+#[cfg(feature = "api")]
+pub use executor::{
+    ExecutionOutcome, HttpExecutor, Pages, QbEnvironment, QueryExecutor, QueryResponse,
+    ResponseMetadata, RetryPolicy,
+};
+#[cfg(feature = "api")]
+pub use fault::{FaultCode, FaultError, FaultInfo, ResponseWarning};
+pub use journal::JournalEntryLines;
+pub use lint::Lint;
+pub use metadata::{EntityMeta, FieldMeta, FieldType, all_entities, entity_metadata};
+#[cfg(feature = "api")]
+pub use realms::{RealmResults, RealmSummary};
+#[cfg(feature = "serde")]
+pub use saved_search::SavedSearch;
+pub use sort::sort_results;
+pub use value::{QueryValue, Values};
 // Re-export the procedural macro
 pub use quick_oxibooks_sql_macro::qb_sql;
 use quickbooks_types::QBItem;
 
+// Lets the code generated by `qb_sql!` refer to this crate by name from within it
+extern crate self as quick_oxibooks_sql;
+
+/// Items used by the code generated by [`qb_sql!`], not part of the public API
+#[doc(hidden)]
+pub mod __private {
+    use std::marker::PhantomData;
+
+    /// Wraps a field in the compile-time check of a nested field path
+    ///
+    /// `(&Nested(&field)).field()` resolves to [`ThroughOption::field`] when the
+    /// field is an `Option`, and to [`Direct::field`] otherwise, so the next
+    /// segment of the path is looked up on the value inside.
+    pub struct Nested<'a, T>(pub &'a T);
+
+    pub trait ThroughOption {
+        type Inner;
+        fn field(&self) -> &Self::Inner;
+    }
+
+    impl<T> ThroughOption for Nested<'_, Option<T>> {
+        type Inner = T;
+        fn field(&self) -> &T {
+            self.0.as_ref().expect("only used for type checking")
+        }
+    }
+
+    pub trait Direct {
+        type Inner;
+        fn field(&self) -> &Self::Inner;
+    }
+
+    impl<T> Direct for &Nested<'_, T> {
+        type Inner = T;
+        fn field(&self) -> &T {
+            self.0
+        }
+    }
+
+    /// Wraps a field or condition value to check that they can be compared
+    ///
+    /// `(&Classify(&x))` resolves [`KnownKind`] methods for types with a
+    /// [`Classified`] kind, and [`UnknownKind`] methods, which accept everything,
+    /// otherwise. Values are passed through [`KnownKind::checked`] against the kind
+    /// of their field, found with [`field_kind`].
+    pub struct Classify<'a, T: ?Sized>(pub &'a T);
+
+    /// The kinds of values that fields and conditions are compared by
+    pub mod kind {
+        pub struct Text;
+        pub struct Number;
+        pub struct Bool;
+        /// An entity ID or document number, which are strings often written as numbers
+        pub struct Id;
+        /// A type whose kind isn't known, e.g. a reference type or a custom value
+        pub struct Any;
+    }
+
+    /// A type with a known [`kind`] of value
+    pub trait Classified {
+        type Kind;
+    }
+
+    macro_rules! classified {
+        ($kind:ident: $($ty:ty),*) => {
+            $(impl Classified for $ty {
+                type Kind = kind::$kind;
+            })*
+        };
+    }
+
+    classified!(Text: str, String, char, std::borrow::Cow<'_, str>);
+    classified!(Number: i8, i16, i32, i64, i128, isize, u8, u16, u32, u64, u128, usize, f32, f64);
+    classified!(Bool: bool);
+
+    impl<T: Classified + ?Sized> Classified for &T {
+        type Kind = T::Kind;
+    }
+
+    impl<T: Classified> Classified for Option<T> {
+        type Kind = T::Kind;
+    }
+
+    pub trait KnownKind<'a, T: Classified + ?Sized> {
+        fn kind(&self) -> PhantomData<T::Kind> {
+            PhantomData
+        }
+
+        fn checked<F>(&self, field: PhantomData<F>) -> &'a T
+        where
+            T::Kind: ComparableTo<F>;
+    }
+
+    impl<'a, T: Classified + ?Sized> KnownKind<'a, T> for Classify<'a, T> {
+        fn checked<F>(&self, _field: PhantomData<F>) -> &'a T
+        where
+            T::Kind: ComparableTo<F>,
+        {
+            self.0
+        }
+    }
+
+    pub trait UnknownKind<'a, T: ?Sized> {
+        fn kind(&self) -> PhantomData<kind::Any> {
+            PhantomData
+        }
+
+        fn checked<F>(&self, field: PhantomData<F>) -> &'a T;
+    }
+
+    impl<'a, T: ?Sized> UnknownKind<'a, T> for &Classify<'a, T> {
+        fn checked<F>(&self, _field: PhantomData<F>) -> &'a T {
+            self.0
+        }
+    }
+
+    /// The kind of the field returned by `field`, which is only type checked
+    ///
+    /// The closure lets the kind be found without a value of the entity type.
+    pub fn field_kind<E, K>(_field: impl FnOnce(&E) -> PhantomData<K>) -> PhantomData<K> {
+        PhantomData
+    }
+
+    /// The kind of an ID or document number field, whatever the kind of its type
+    pub fn as_id<K>(_field: PhantomData<K>) -> PhantomData<kind::Id> {
+        PhantomData
+    }
+
+    /// Whether a value of this kind can be compared against a field of kind `F`
+    #[diagnostic::on_unimplemented(
+        message = "the value can't be compared against the field it's used with",
+        label = "this value doesn't match the field's type",
+        note = "the field is of kind `{F}` and the value of kind `{Self}`"
+    )]
+    pub trait ComparableTo<F> {}
+
+    macro_rules! comparable {
+        ($($field:ident => $($value:ident),*;)*) => {
+            $($(impl ComparableTo<kind::$field> for kind::$value {})*)*
+        };
+    }
+
+    comparable! {
+        Text => Text, Any;
+        Number => Number, Any;
+        Bool => Bool, Any;
+        Id => Text, Number, Any;
+        Any => Text, Number, Bool, Any;
+    }
+
+    /// Fail compilation with a suggestion if `field` looks like a typo of a
+    /// queryable field of the entity
+    ///
+    /// `field` is the QuickBooks name of the path `written` in the macro. This is
+    /// evaluated in a const item alongside the field type check, which still
+    /// reports fields that are missing from the entity.
+    pub const fn suggest_field(entity: &str, field: &str, written: &str) {
+        if let Some(closest) = crate::metadata::closest_field(entity, field) {
+            let mut message = ConstMessage::new();
+            message.push("unknown field `");
+            message.push(written);
+            message.push("` on ");
+            message.push(entity);
+            message.push("; did you mean `");
+            message.push_snake_case(closest);
+            message.push("`?");
+            panic!("{}", message.as_str());
+        }
+    }
+
+    /// A fixed-size buffer for building panic messages in const contexts
+    ///
+    /// Text past the end of the buffer is dropped.
+    struct ConstMessage {
+        buf: [u8; 256],
+        len: usize,
+    }
+
+    impl ConstMessage {
+        const fn new() -> Self {
+            Self {
+                buf: [0; 256],
+                len: 0,
+            }
+        }
+
+        const fn push_byte(&mut self, byte: u8) {
+            if self.len < self.buf.len() {
+                self.buf[self.len] = byte;
+                self.len += 1;
+            }
+        }
+
+        const fn push(&mut self, text: &str) {
+            let bytes = text.as_bytes();
+            let mut i = 0;
+            while i < bytes.len() {
+                self.push_byte(bytes[i]);
+                i += 1;
+            }
+        }
+
+        /// Push a camel-cased QuickBooks name as the snake-cased Rust field path
+        ///
+        /// A run of uppercase letters is one word, so `APAccountRef` becomes
+        /// `ap_account_ref`.
+        const fn push_snake_case(&mut self, name: &str) {
+            let bytes = name.as_bytes();
+            let mut i = 0;
+            while i < bytes.len() {
+                let byte = bytes[i];
+                if byte.is_ascii_uppercase() {
+                    let starts_word = i > 0
+                        && bytes[i - 1] != b'.'
+                        && (!bytes[i - 1].is_ascii_uppercase()
+                            || (i + 1 < bytes.len() && bytes[i + 1].is_ascii_lowercase()));
+                    if starts_word {
+                        self.push_byte(b'_');
+                    }
+                    self.push_byte(byte.to_ascii_lowercase());
+                } else {
+                    self.push_byte(byte);
+                }
+                i += 1;
+            }
+        }
+
+        const fn as_str(&self) -> &str {
+            let (text, _) = self.buf.split_at(self.len);
+            match std::str::from_utf8(text) {
+                Ok(text) => text,
+                // Truncation split a multi-byte character
+                Err(_) => "unknown field",
+            }
+        }
+    }
+}
+
 /// Struct representing a SQL-like query for QuickBooks entities
-#[derive(Debug, PartialEq, Clone)]
+///
+/// The clauses are stored behind [`Arc`]s and copied on write, so cloning a
+/// query is cheap until one of the clones is modified.
+///
+/// Queries built with [`qb_sql!`] are checked at compile time: every selected,
+/// filtered, grouped and ordered field must exist on the entity type.
+///
+/// ```
+/// # use quick_oxibooks_sql::{Operator, Order, Query, WhereClause, qb_sql};
+/// # use quickbooks_types::Customer;
+/// let query = qb_sql!(select display_name from Customer where id in (1, 2) order by balance);
+/// ```
+///
+/// ```compile_fail
+/// # use quick_oxibooks_sql::{Operator, Order, Query, WhereClause, qb_sql};
+/// # use quickbooks_types::Customer;
+/// let query = qb_sql!(select * from Customer where nonexistent_field = 1);
+/// ```
+///
+/// ```compile_fail
+/// # use quick_oxibooks_sql::{Operator, Order, Query, WhereClause, qb_sql};
+/// # use quickbooks_types::Customer;
+/// let query = qb_sql!(select * from Customer where nonexistent_field in (1, 2));
+/// ```
+///
+/// ```compile_fail
+/// # use quick_oxibooks_sql::{Operator, Order, Query, WhereClause, qb_sql};
+/// # use quickbooks_types::Customer;
+/// let query = qb_sql!(select * from Customer order by nonexistent_field desc);
+/// ```
+///
+/// ```compile_fail
+/// # use quick_oxibooks_sql::{Operator, Order, Query, WhereClause, qb_sql};
+/// # use quickbooks_types::Customer;
+/// let query = qb_sql!(select count from Customer group by nonexistent_field);
+/// ```
+///
+/// ```compile_fail
+/// # use quick_oxibooks_sql::{Operator, Order, Query, WhereClause, qb_sql};
+/// # use quickbooks_types::Customer;
+/// let query = qb_sql!(select nonexistent_field from Customer);
+/// ```
+///
+/// Selecting or ordering by the same field twice is rejected as well:
+///
+/// ```compile_fail
+/// # use quick_oxibooks_sql::{Operator, Order, Query, WhereClause, qb_sql};
+/// # use quickbooks_types::Customer;
+/// let query = qb_sql!(select display_name, balance, display_name from Customer);
+/// ```
+///
+/// ```compile_fail
+/// # use quick_oxibooks_sql::{Operator, Order, Query, WhereClause, qb_sql};
+/// # use quickbooks_types::Customer;
+/// let query = qb_sql!(select * from Customer order by balance desc, balance asc);
+/// ```
+///
+/// Nested paths in conditions are checked in full, looking through optional fields:
+///
+/// ```compile_fail
+/// # use quick_oxibooks_sql::{Operator, Order, Query, WhereClause, qb_sql};
+/// # use quickbooks_types::Customer;
+/// let query = qb_sql!(select * from Customer where meta_data.last_updated_tiem > "2024-01-01");
+/// ```
+///
+/// Marking one field with `?` to skip its check doesn't skip the others:
+///
+/// ```compile_fail
+/// # use quick_oxibooks_sql::{Operator, Order, Query, WhereClause, qb_sql};
+/// # use quickbooks_types::Customer;
+/// let query = qb_sql!(select * from Customer where custom_column? = 1 and nonexistent_field = 2);
+/// ```
+///
+/// Values must be comparable with the type of their field:
+///
+/// ```compile_fail
+/// # use quick_oxibooks_sql::{Operator, Order, Query, WhereClause, qb_sql};
+/// # use quickbooks_types::Customer;
+/// let query = qb_sql!(select * from Customer where balance like "foo");
+/// ```
+///
+/// A value that's a bare identifier is a Rust variable, even if it's named like
+/// a field, since QuickBooks can't compare two fields. Without such a variable
+/// in scope, the query fails with rustc's usual missing variable error; filter
+/// on one field client side instead:
+///
+/// ```
+/// # use quick_oxibooks_sql::qb_sql;
+/// # use quickbooks_types::Customer;
+/// let display_name = "Oak & Co";
+/// let query = qb_sql!(select * from Customer where display_name = display_name);
+/// ```
+#[derive(Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(bound = ""))]
 pub struct Query<QB> {
-    fields: Vec<&'static str>,
-    condition: Vec<WhereClause>,
-    order: Vec<OrderClause>,
+    distinct: bool,
+    fields: Arc<Vec<SelectField>>,
+    condition: Arc<Vec<WhereClause>>,
+    groups: Arc<Vec<Range<usize>>>,
+    group_by: Arc<Vec<Cow<'static, str>>>,
+    order: Arc<Vec<OrderClause>>,
     limit: Option<Limit>,
+    dialect: Dialect,
+    null_style: NullStyle,
+    incremental: bool,
+    order_required: bool,
+    true_base: bool,
+    allowed_lints: Vec<Lint>,
+    time_offset: Option<UtcOffset>,
+    #[cfg(feature = "api")]
+    #[cfg_attr(feature = "serde", serde(skip))]
+    retry: RetryPolicy,
+    #[cfg(feature = "api")]
+    #[cfg_attr(feature = "serde", serde(skip))]
+    timeout: Option<std::time::Duration>,
+    #[cfg_attr(feature = "serde", serde(skip))]
     _phantom: std::marker::PhantomData<QB>,
 }
 
@@ -18,379 +419,3339 @@ impl<QB: QBItem> Query<QB> {
     /// Create a new empty query
     pub fn new() -> Self {
         Query {
-            fields: Vec::new(),
-            condition: Vec::new(),
-            order: Vec::new(),
+            distinct: false,
+            fields: Arc::default(),
+            condition: Arc::default(),
+            groups: Arc::default(),
+            group_by: Arc::default(),
+            order: Arc::default(),
             limit: None,
+            dialect: Dialect::default(),
+            null_style: NullStyle::default(),
+            incremental: false,
+            order_required: false,
+            true_base: false,
+            allowed_lints: Vec::new(),
+            time_offset: None,
+            #[cfg(feature = "api")]
+            retry: RetryPolicy::default(),
+            #[cfg(feature = "api")]
+            timeout: None,
             _phantom: std::marker::PhantomData,
         }
     }
 
+    /// Only return entities with distinct values for the selected fields
+    ///
+    /// In [`qb_sql!`] this is written as `select distinct`, with `*` or a field
+    /// list. It can't be combined with `select count`:
+    ///
+    /// ```compile_fail
+    /// # use quick_oxibooks_sql::{Query, qb_sql};
+    /// # use quickbooks_types::Customer;
+    /// let query = qb_sql!(select distinct count from Customer);
+    /// ```
+    pub fn distinct(mut self) -> Self {
+        self.distinct = true;
+        self
+    }
+
+    /// Start a query to be built with the chained builder methods
+    ///
+    /// The same as [`Query::new`], for code that reads better as
+    /// `Query::<Customer>::builder().select_field(..)`. To configure a query
+    /// through a mutable reference instead, see [`Query::build`].
+    pub fn builder() -> Self {
+        Self::new()
+    }
+
+    /// Build a query by configuring a [`QueryBuilder`] in a closure
+    ///
+    /// This reads well when parts of the query are conditional:
+    ///
+    /// ```ignore
+    /// let query = Query::<Customer>::build(|q| {
+    ///     q.select("DisplayName");
+    ///     if let Some(name) = name {
+    ///         q.filter(WhereClause::new("DisplayName", Operator::Like).add_value(name));
+    ///     }
+    /// });
+    /// ```
+    pub fn build(configure: impl FnOnce(&mut QueryBuilder<QB>)) -> Self {
+        let mut builder = QueryBuilder {
+            query: Query::new(),
+        };
+        configure(&mut builder);
+        builder.query
+    }
+
     /// Add a field to select in the query
     ///
-    /// # Safety
-    /// This function is unsafe because it accepts a raw string slice as the field name.
-    /// The caller must ensure that the field name is valid and corresponds to a field in the QuickBooks entity.
-    pub unsafe fn field(mut self, field: &'static str) -> Self {
-        self.fields.push(field);
+    /// The field name is not checked against the QuickBooks entity; an unknown
+    /// name produces a query that QuickBooks rejects when it is executed. The
+    /// name may be decided at runtime, e.g. from user input.
+    pub fn select_field(mut self, field: impl Into<Cow<'static, str>>) -> Self {
+        Arc::make_mut(&mut self.fields).push(SelectField {
+            field: field.into(),
+            alias: None,
+            aggregate: None,
+        });
         self
     }
 
-    /// Add a condition to the query
+    /// Add a field to select in the query under a different name
     ///
-    /// # Safety
-    /// This function is unsafe because it accepts a raw `WhereClause`.
-    /// The caller must ensure that the `WhereClause` is valid and corresponds to the QuickBooks entity.
-    pub unsafe fn condition(mut self, condition: WhereClause) -> Self {
-        self.condition.push(condition);
+    /// Aliases are only rendered in the [`Dialect::Standard`] dialect, since
+    /// QuickBooks doesn't support them; the field is selected unaliased otherwise.
+    pub fn select_field_as(
+        mut self,
+        field: impl Into<Cow<'static, str>>,
+        alias: impl Into<Cow<'static, str>>,
+    ) -> Self {
+        Arc::make_mut(&mut self.fields).push(SelectField {
+            field: field.into(),
+            alias: Some(alias.into()),
+            aggregate: None,
+        });
         self
     }
 
-    /// Add an order clause to the query
+    /// Select an aggregate of a field, e.g. `count(Id)`, for use with [`Query::group_by`]
     ///
-    /// # Safety
-    /// This function is unsafe because it accepts a raw string slice as the field name.
-    /// The caller must ensure that the field name is valid and corresponds to a field in the QuickBooks entity.
-    pub unsafe fn order(mut self, field: &'static str, order: Order) -> Self {
-        self.order.push(OrderClause { field, order });
+    /// In [`qb_sql!`] this is written as a function call in the field list, such
+    /// as `select type, count(id) from Customer group by type`. Like
+    /// [`Query::select_field`], the field name isn't checked.
+    pub fn select_aggregate(
+        mut self,
+        aggregate: Aggregate,
+        field: impl Into<Cow<'static, str>>,
+    ) -> Self {
+        Arc::make_mut(&mut self.fields).push(SelectField {
+            field: field.into(),
+            alias: None,
+            aggregate: Some(aggregate),
+        });
         self
     }
 
-    /// Set a limit on the number of results returned by the query
-    pub fn limit(mut self, number: u32, offset: Option<u32>) -> Self {
-        self.limit = Some(Limit { number, offset });
+    /// Select an aggregate of a field under a different name
+    ///
+    /// As with [`Query::select_field_as`], the alias is only rendered in the
+    /// [`Dialect::Standard`] and [`Dialect::Postgres`] dialects.
+    pub fn select_aggregate_as(
+        mut self,
+        aggregate: Aggregate,
+        field: impl Into<Cow<'static, str>>,
+        alias: impl Into<Cow<'static, str>>,
+    ) -> Self {
+        Arc::make_mut(&mut self.fields).push(SelectField {
+            field: field.into(),
+            alias: Some(alias.into()),
+            aggregate: Some(aggregate),
+        });
         self
     }
 
-    /// Generate the query string
-    pub fn query_string(&self) -> String {
-        let mut query = String::new();
+    /// Add a condition to the query
+    ///
+    /// The clause's field name is not checked against the QuickBooks entity; an
+    /// unknown name produces a query that QuickBooks rejects when it is executed.
+    /// See [`Query::try_with_condition`] for a checked version.
+    pub fn with_condition(mut self, condition: WhereClause) -> Self {
+        Arc::make_mut(&mut self.condition).push(condition);
+        self
+    }
 
-        match &self.fields[..] {
-            [] => query.push_str("select *"),
-            fields => {
-                query.push_str("select ");
-                for (i, field) in fields.iter().enumerate() {
-                    if i > 0 {
-                        query.push_str(", ");
-                    }
-                    query.push_str(field);
+    /// Add a condition to the query, checking its field against the entity's metadata
+    ///
+    /// This is meant for queries assembled at runtime, e.g. from user input. Entities
+    /// without [metadata](crate::entity_metadata) can't be checked, so any field is
+    /// accepted for them.
+    pub fn try_with_condition(self, condition: WhereClause) -> Result<Self, ValidationError> {
+        self.check_field(&condition.field)?;
+        Ok(self.with_condition(condition))
+    }
+
+    /// Group the results by a field, for use with aggregate selections
+    ///
+    /// In [`qb_sql!`] this is written as `group by field1, field2` after the
+    /// `where` clause. Like [`Query::order_by`], the field name isn't checked.
+    pub fn group_by(mut self, field: impl Into<Cow<'static, str>>) -> Self {
+        Arc::make_mut(&mut self.group_by).push(field.into());
+        self
+    }
+
+    /// Rewrite the values of every condition on `field`, including each value of an `IN` list
+    ///
+    /// This is meant for adjusting a base query after it's built, e.g. prefixing
+    /// document numbers with a tenant code. Conditions on other fields are left
+    /// untouched. Returns [`ValidationError::NoCondition`] if no condition is on `field`.
+    pub fn map_values<T: Display>(
+        mut self,
+        field: &str,
+        mut f: impl FnMut(&str) -> T,
+    ) -> Result<Self, ValidationError> {
+        if !self.condition.iter().any(|c| c.field == field) {
+            return Err(ValidationError::NoCondition {
+                field: Cow::Owned(field.to_string()),
+            });
+        }
+        for cond in Arc::make_mut(&mut self.condition) {
+            if cond.field == field {
+                for value in cond.values.iter_mut() {
+                    *value = f(value).to_string();
                 }
             }
         }
+        Ok(self)
+    }
+
+    /// Add an order clause to the query
+    ///
+    /// The field name is not checked against the QuickBooks entity; an unknown
+    /// name produces a query that QuickBooks rejects when it is executed.
+    /// See [`Query::try_order_by`] for a checked version.
+    pub fn order_by(mut self, field: impl Into<Cow<'static, str>>, order: Order) -> Self {
+        Arc::make_mut(&mut self.order).push(OrderClause {
+            field: field.into(),
+            order,
+            nulls: None,
+        });
+        self
+    }
+
+    /// Add an order clause to the query, placing entities without the field
+    /// first or last
+    ///
+    /// QuickBooks doesn't support `NULLS FIRST`/`NULLS LAST`, so the null
+    /// placement is only rendered in the [`Dialect::Standard`] and
+    /// [`Dialect::Postgres`] dialects and the field is ordered as in
+    /// [`Query::order_by`] otherwise.
+    pub fn order_by_nulls(
+        mut self,
+        field: impl Into<Cow<'static, str>>,
+        order: Order,
+        nulls: Nulls,
+    ) -> Self {
+        Arc::make_mut(&mut self.order).push(OrderClause {
+            field: field.into(),
+            order,
+            nulls: Some(nulls),
+        });
+        self
+    }
+
+    /// Add an order clause to the query, checking its field against the entity's metadata
+    ///
+    /// Like [`Query::try_with_condition`], any field is accepted for entities
+    /// without metadata.
+    pub fn try_order_by(
+        self,
+        field: impl Into<Cow<'static, str>>,
+        order: Order,
+    ) -> Result<Self, ValidationError> {
+        let field = field.into();
+        self.check_field(&field)?;
+        Ok(self.order_by(field, order))
+    }
+
+    /// Check that `field` is a queryable field of the entity, if its metadata is known
+    fn check_field(&self, field: &str) -> Result<(), ValidationError> {
+        match entity_metadata(QB::name()) {
+            Some(entity) if entity.field(field).is_none() => Err(ValidationError::UnknownField {
+                field: Cow::Owned(field.to_string()),
+            }),
+            _ => Ok(()),
+        }
+    }
+
+    /// Add a field to select in the query
+    ///
+    /// Prefer [`Query::select_field`], which does the same thing.
+    ///
+    /// # Safety
+    /// There is no memory-safety requirement. The caller must ensure that the field
+    /// name corresponds to a field in the QuickBooks entity, otherwise the query
+    /// is rejected by QuickBooks.
+    pub unsafe fn field(self, field: &'static str) -> Self {
+        self.select_field(field)
+    }
 
-        query.push_str(&format!(" from {}", QB::name()));
+    /// Add a condition to the query
+    ///
+    /// Prefer [`Query::with_condition`], which does the same thing.
+    ///
+    /// # Safety
+    /// There is no memory-safety requirement. The caller must ensure that the
+    /// clause's field name corresponds to a field in the QuickBooks entity,
+    /// otherwise the query is rejected by QuickBooks.
+    pub unsafe fn condition(self, condition: WhereClause) -> Self {
+        self.with_condition(condition)
+    }
 
-        if !self.condition.is_empty() {
-            query.push_str(" where");
-            for (i, cond) in self.condition.iter().enumerate() {
-                if i > 0 {
-                    query.push_str(" and");
-                }
-                cond.extend_query(&mut query);
+    /// Add a condition whose value is inserted into the query verbatim
+    ///
+    /// Unlike values added through [`WhereClause::add_value`], `raw_value` is not
+    /// quoted or escaped, so it must already be a complete, well-formed literal
+    /// such as `'2024-01-01'`.
+    ///
+    /// # Safety
+    /// There is no memory-safety requirement. The caller must ensure that
+    /// `raw_value` is a complete literal, quoted and escaped for the QuickBooks
+    /// query language, otherwise the query is malformed or its meaning changes.
+    /// The field name must also correspond to a field in the QuickBooks entity.
+    pub unsafe fn and_where_raw(&mut self, field: &'static str, op: Operator, raw_value: &str) {
+        let mut clause = WhereClause::new(field, op).add_value(raw_value);
+        clause.raw = true;
+        Arc::make_mut(&mut self.condition).push(clause);
+    }
+
+    /// Add a case-insensitive equality condition
+    ///
+    /// QuickBooks compares some fields case-sensitively with `=` but matches `LIKE`
+    /// patterns case-insensitively, so this renders `Field LIKE 'value'` with any
+    /// wildcard characters in `value` escaped, keeping it an exact match.
+    /// `field_path` may be a nested path such as `PrimaryEmailAddr.Address`.
+    pub fn where_ci<T: Display>(self, field_path: impl Into<Cow<'static, str>>, value: T) -> Self {
+        self.with_condition(
+            WhereClause::new(field_path, Operator::Like).add_value(escape_like(&value.to_string())),
+        )
+    }
+
+    /// Add a condition matching entities where `field op value` doesn't hold
+    ///
+    /// The condition is rendered with the opposite operator, e.g. `Like` becomes
+    /// `NOT LIKE` and `Less` becomes `>=`, see [`Operator::negated`].
+    ///
+    /// # Panics
+    /// Panics if the operator has no opposite, which is the case for `Between`.
+    pub fn and_not<T: Display>(
+        self,
+        field: impl Into<Cow<'static, str>>,
+        operator: Operator,
+        value: T,
+    ) -> Self {
+        let Some(negated) = operator.negated() else {
+            panic!("`{operator:?}` has no negated operator for `and_not`");
+        };
+        let clause = WhereClause::new(field, negated);
+        let clause = if clause.operator.takes_value() {
+            clause.add_value(value)
+        } else {
+            clause
+        };
+        self.with_condition(clause)
+    }
+
+    /// Wrap a range of the conditions added so far in parentheses
+    ///
+    /// The range indexes into the conditions in the order they were added, so
+    /// `group(0..2)` groups the first two conditions. Groups may be nested but
+    /// must not partially overlap.
+    ///
+    /// # Panics
+    /// Panics if the range is empty or extends past the conditions added so far.
+    pub fn group(mut self, clauses: Range<usize>) -> Self {
+        assert!(
+            clauses.start < clauses.end && clauses.end <= self.condition.len(),
+            "invalid condition group {clauses:?} for {} conditions",
+            self.condition.len()
+        );
+        Arc::make_mut(&mut self.groups).push(clauses);
+        self
+    }
+
+    /// Combine the conditions of two queries with `and`
+    ///
+    /// The conditions of `other` are appended to the receiver's, and either side
+    /// that joins conditions with `or` is grouped first so it keeps its meaning.
+    /// Selected fields are combined, skipping any the receiver already selects,
+    /// and `other`'s ordering is appended after the receiver's for fields it
+    /// doesn't already order by. The receiver's limit wins if it has one, and its
+    /// dialect and other settings are kept. To combine both limits instead, call
+    /// [`Query::merge_limits`] first.
+    pub fn merge(mut self, other: Query<QB>) -> Self {
+        // Whether the query's conditions need grouping to be combined with `and`
+        let needs_group = |query: &Query<QB>| {
+            let all = 0..query.condition.len();
+            query
+                .condition
+                .iter()
+                .skip(1)
+                .any(|c| c.join == Connective::Or)
+                && !query.groups.contains(&all)
+        };
+        let offset = self.condition.len();
+        if needs_group(&self) {
+            Arc::make_mut(&mut self.groups).push(0..offset);
+        }
+        if needs_group(&other) {
+            Arc::make_mut(&mut self.groups).push(offset..offset + other.condition.len());
+        }
+        Arc::make_mut(&mut self.groups).extend(
+            other
+                .groups
+                .iter()
+                .map(|group| group.start + offset..group.end + offset),
+        );
+        let conditions = Arc::make_mut(&mut self.condition);
+        for (i, cond) in other.condition.iter().enumerate() {
+            let mut cond = cond.clone();
+            if i == 0 {
+                cond.join = Connective::And;
             }
+            conditions.push(cond);
         }
 
-        if !self.order.is_empty() {
-            query.push_str(" order by");
-            for (i, ord) in self.order.iter().enumerate() {
-                if i > 0 {
-                    query.push_str(",");
-                }
-                ord.extend_query(&mut query);
+        for field in other.fields.iter() {
+            if !self.fields.contains(field) {
+                Arc::make_mut(&mut self.fields).push(field.clone());
             }
         }
-
-        if let Some(limit) = &self.limit {
-            limit.extend_query(&mut query);
+        for field in other.group_by.iter() {
+            if !self.group_by.contains(field) {
+                Arc::make_mut(&mut self.group_by).push(field.clone());
+            }
+        }
+        for ord in other.order.iter() {
+            if !self
+                .order
+                .iter()
+                .any(|existing| existing.field == ord.field)
+            {
+                Arc::make_mut(&mut self.order).push(ord.clone());
+            }
         }
 
-        query
+        self.distinct |= other.distinct;
+        self.limit = self.limit.or(other.limit);
+        self
     }
 
-    #[cfg(feature = "api")]
-    /// Execute the query against the QuickBooks API, returning a vector of results or an error
-    pub fn execute(
-        &self,
-        qb: &quick_oxibooks::QBContext,
-        client: &ureq::Agent,
-    ) -> Result<Vec<QB>, quick_oxibooks::error::APIError> {
-        // Safety: The query has been constructed using the provided methods,
-        // ensuring that it is valid for the QuickBooks entity QB.
-        unsafe { quick_oxibooks::functions::query::qb_query_raw::<QB>(self, qb, client) }
+    /// Combine the limit of `other` with the receiver's
+    ///
+    /// If only one query has a limit, it's kept. If both do, the smaller number
+    /// of results wins, and their offsets must match since no single limit
+    /// covers both pages; a missing offset counts as 0.
+    ///
+    /// Conflicting offsets fail with [`ValidationError::ConflictingLimits`]
+    /// rather than a [`QueryError`], since like the other problems found while
+    /// building a query, it's known before anything is sent.
+    ///
+    /// Only the limit is changed, so this is usually followed by [`Query::merge`]:
+    ///
+    /// ```ignore
+    /// let merged = first.merge_limits(&second)?.merge(second);
+    /// ```
+    pub fn merge_limits(mut self, other: &Query<QB>) -> Result<Self, ValidationError> {
+        self.limit = match (self.limit, other.limit) {
+            (Some(left), Some(right)) => {
+                if left.offset.unwrap_or(0) != right.offset.unwrap_or(0) {
+                    return Err(ValidationError::ConflictingLimits { left, right });
+                }
+                Some(if right.number < left.number {
+                    right
+                } else {
+                    left
+                })
+            }
+            (left, right) => left.or(right),
+        };
+        Ok(self)
     }
-}
 
-impl<QB: QBItem> std::fmt::Display for Query<QB> {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(f, "{}", self.query_string())
+    /// Add an order clause to the query
+    ///
+    /// Prefer [`Query::order_by`], which does the same thing.
+    ///
+    /// # Safety
+    /// There is no memory-safety requirement. The caller must ensure that the field
+    /// name corresponds to a field in the QuickBooks entity, otherwise the query
+    /// is rejected by QuickBooks.
+    pub unsafe fn order(self, field: &'static str, order: Order) -> Self {
+        self.order_by(field, order)
     }
-}
 
-#[derive(Debug, PartialEq, Clone, Copy)]
-struct Limit {
-    number: u32,
-    offset: Option<u32>,
-}
+    /// Set a limit on the number of results returned by the query
+    ///
+    /// `offset` is the 0-based number of results to skip. In [`qb_sql!`] this is
+    /// written as `limit n offset m`, or as `maxresults n startposition m` where
+    /// `m` is 1-based. The two forms can't be mixed:
+    ///
+    /// ```compile_fail
+    /// # use quick_oxibooks_sql::{Query, qb_sql};
+    /// # use quickbooks_types::Customer;
+    /// let query = qb_sql!(select * from Customer limit 10 startposition 5);
+    /// ```
+    pub fn limit(mut self, number: u32, offset: Option<u32>) -> Self {
+        self.limit = Some(Limit { number, offset });
+        self
+    }
 
-impl Limit {
-    fn extend_query(&self, query: &mut String) {
-        query.push_str(&format!(" LIMIT {}", self.number));
-        if let Some(offset) = self.offset {
-            query.push_str(&format!(" OFFSET {}", offset));
+    /// A copy of the query with its limit replaced, leaving `self` untouched
+    ///
+    /// This is for deriving variants of a base query, such as one per page.
+    /// The copy is cheap since the query's clauses are shared until modified.
+    pub fn with_limit(&self, number: u32, offset: Option<u32>) -> Self {
+        self.clone().limit(number, offset)
+    }
+
+    /// A copy of the query without its limit, leaving `self` untouched
+    pub fn without_limit(&self) -> Self {
+        Query {
+            limit: None,
+            ..self.clone()
         }
     }
-}
 
-/// Struct representing an order clause in a query
-#[derive(Debug, PartialEq, Clone)]
-struct OrderClause {
-    field: &'static str,
-    order: Order,
-}
+    /// A copy of the query with another condition, leaving `self` untouched
+    ///
+    /// See [`Query::with_condition`].
+    pub fn with_additional_condition(&self, condition: WhereClause) -> Self {
+        self.clone().with_condition(condition)
+    }
 
-impl OrderClause {
-    fn extend_query(&self, query: &mut String) {
-        query.push_str(&format!(
-            " {} {}",
-            self.field,
-            match self.order {
-                Order::Asc => "ASC",
-                Order::Desc => "DESC",
-            }
-        ));
+    /// Check the query for problems that would make QuickBooks reject it
+    ///
+    /// Returns every problem found, or an empty vector if the query is valid.
+    pub fn validate(&self) -> Vec<ValidationError> {
+        self.condition
+            .iter()
+            .filter_map(|c| {
+                let field = c.field.clone();
+                match c.operator {
+                    Operator::IsNull | Operator::IsNotNull => None,
+                    Operator::In | Operator::NotIn if c.values.is_empty() => {
+                        Some(ValidationError::EmptyInList { field })
+                    }
+                    Operator::Between if c.values.len() < 2 => {
+                        Some(ValidationError::MissingValue { field })
+                    }
+                    _ if c.values.is_empty() => Some(ValidationError::MissingValue { field }),
+                    _ => None,
+                }
+            })
+            .collect()
     }
-}
 
-/// Enum representing the order direction in a query
-#[derive(Debug, PartialEq, Clone)]
-pub enum Order {
-    Asc,
-    Desc,
-}
+    /// The selected fields, empty when the query selects `*`
+    pub fn fields(&self) -> &[SelectField] {
+        &self.fields
+    }
+
+    /// The query's conditions, in the order they were added
+    ///
+    /// Parenthesized groups aren't included; each condition's
+    /// [`join`](WhereClause::join) is the connective before it.
+    pub fn conditions(&self) -> &[WhereClause] {
+        &self.condition
+    }
+
+    /// The order clauses, in the order they're applied
+    ///
+    /// Named so it doesn't clash with the unchecked [`Query::order`] builder.
+    pub fn ordering(&self) -> &[OrderClause] {
+        &self.order
+    }
+
+    /// The query's limit and offset, if it has one
+    ///
+    /// Named so it doesn't clash with the [`Query::limit`] builder method.
+    pub fn limit_clause(&self) -> Option<Limit> {
+        self.limit
+    }
+
+    /// Whether the query has any conditions
+    pub fn has_conditions(&self) -> bool {
+        !self.condition.is_empty()
+    }
+
+    /// Whether the query is a plain `select * from Entity`, with no fields,
+    /// conditions, grouping, ordering or limit
+    pub fn is_empty(&self) -> bool {
+        !self.distinct
+            && self.fields.is_empty()
+            && self.condition.is_empty()
+            && self.group_by.is_empty()
+            && self.order.is_empty()
+            && self.limit.is_none()
+    }
+
+    /// Get every condition on `field`, in the order they were added
+    pub fn conditions_for(&self, field: &str) -> Vec<&WhereClause> {
+        self.condition
+            .iter()
+            .filter(|clause| clause.field == field)
+            .collect()
+    }
+
+    /// Mark the query as an incremental sync, which enables sync-specific lints
+    ///
+    /// Paginated queries are treated as syncs without being marked.
+    pub fn mark_incremental(mut self) -> Self {
+        self.incremental = true;
+        self
+    }
+
+    /// Require the query to have an `order by` clause when it's paged
+    ///
+    /// Without an explicit order QuickBooks may return entities in a different
+    /// order for each page, so entities can be skipped or repeated. Paging a
+    /// query that requires an order but has none fails with
+    /// [`QueryError::OrderRequired`] before any request is sent.
+    pub fn require_order(mut self) -> Self {
+        self.order_required = true;
+        self
+    }
+
+    /// Always render a `where` clause, starting from `1=1`
+    ///
+    /// Conditions are then appended with `and`, so SQL assembled around the
+    /// query string can add its own conditions without checking whether the
+    /// query has any. QuickBooks may reject `1=1`, so it's only rendered in the
+    /// [`Dialect::Standard`] and [`Dialect::Postgres`] dialects and the `where`
+    /// clause is left out as usual when there are no conditions otherwise.
+    pub fn with_true_base(mut self) -> Self {
+        self.true_base = true;
+        self
+    }
+
+    /// Stop `lint` from being reported for this query
+    pub fn allow_lint(mut self, lint: Lint) -> Self {
+        self.allowed_lints.push(lint);
+        self
+    }
+
+    /// Check the query for patterns that are valid but likely mistakes
+    ///
+    /// Unlike [`Query::validate`], these are only warnings and are never enforced.
+    pub fn lints(&self) -> Vec<Lint> {
+        let mut lints = Vec::new();
+        let is_sync = self.incremental || self.limit.is_some();
+        if is_sync && !self.conditions_for("MetaData.CreateTime").is_empty() {
+            lints.push(Lint::CreateTimeInSync);
+        }
+        lints.retain(|lint| !self.allowed_lints.contains(lint));
+        lints
+    }
+
+    /// Summarize the clauses making up the query, e.g. for emitting metrics
+    pub fn summary(&self) -> QuerySummary {
+        QuerySummary {
+            num_fields: self.fields.len(),
+            num_conditions: self.condition.len(),
+            num_in_values: self
+                .condition
+                .iter()
+                .filter(|c| c.operator.takes_list())
+                .map(|c| c.values.len())
+                .sum(),
+            num_groups: self.groups.len(),
+            has_like: self
+                .condition
+                .iter()
+                .any(|c| matches!(c.operator, Operator::Like | Operator::NotLike)),
+            has_or: self.condition.iter().any(|c| c.join == Connective::Or),
+            has_order: !self.order.is_empty(),
+            is_paginated: self.limit.is_some(),
+        }
+    }
+
+    /// Describe how many entities the query will fetch and from where, to help
+    /// diagnose truncated results
+    ///
+    /// The report covers the effective `MAXRESULTS` and `STARTPOSITION` and
+    /// whether the result set is fetched in one request or paged. It's meant to
+    /// be read by people, so its wording may change between versions.
+    pub fn pagination_report(&self) -> String {
+        // QuickBooks returns this many entities when MAXRESULTS is omitted, and
+        // won't return more than the maximum in one response
+        const DEFAULT_PAGE_SIZE: u32 = 100;
+        const MAX_PAGE_SIZE: u32 = 1000;
+
+        let mut lines = Vec::new();
+        match self.limit {
+            Some(limit) if limit.number > MAX_PAGE_SIZE => lines.push(format!(
+                "MAXRESULTS {}, but QuickBooks returns at most {MAX_PAGE_SIZE} entities per request",
+                limit.number
+            )),
+            Some(limit) => lines.push(format!("MAXRESULTS {}", limit.number)),
+            None => lines.push(format!(
+                "MAXRESULTS not set, so QuickBooks returns at most {DEFAULT_PAGE_SIZE} entities"
+            )),
+        }
+        let offset = self.limit.and_then(|limit| limit.offset).unwrap_or(0);
+        lines.push(format!("STARTPOSITION {}", offset + 1));
+        lines.push(
+            "`execute` sends a single request, so results past the page size are not fetched; \
+             `execute_paged` requests further pages until the limit or the last entity is reached"
+                .to_string(),
+        );
+        lines.join("\n")
+    }
+
+    /// Set the dialect used when rendering the query string
+    pub fn dialect(mut self, dialect: Dialect) -> Self {
+        self.dialect = dialect;
+        self
+    }
+
+    /// Set how null checks are rendered in the query string
+    pub fn null_style(mut self, null_style: NullStyle) -> Self {
+        self.null_style = null_style;
+        self
+    }
+
+    /// Render timestamps in conditions at the given offset from UTC
+    ///
+    /// QuickBooks compares timestamps in the company's time zone, so a UTC
+    /// timestamp such as `2024-01-01T00:00:00Z` is rewritten as the same instant
+    /// in the company's zone, e.g. `2023-12-31T16:00:00-08:00`. Only values with
+    /// an explicit offset are converted; dates and timestamps without an offset
+    /// are already interpreted in the company's time zone and are left alone.
+    ///
+    /// The offset should be the one of the company being queried, which is
+    /// available from its `Preferences`.
+    pub fn normalize_times_to(mut self, offset: UtcOffset) -> Self {
+        self.time_offset = Some(offset);
+        self
+    }
+
+    /// Whether a condition group is rendered with parentheses in the query's dialect
+    fn renders_group(&self, group: &Range<usize>) -> bool {
+        match self.dialect {
+            Dialect::Standard | Dialect::Postgres => true,
+            // Groups that only join their terms with `and` don't change the meaning of
+            // the query, so they're flattened. Joins inside nested groups belong to
+            // those groups and are ignored here.
+            Dialect::Quickbooks => (group.start + 1..group.end).any(|i| {
+                self.condition[i].join == Connective::Or
+                    && !self.groups.iter().any(|inner| {
+                        inner != group
+                            && group.start <= inner.start
+                            && inner.end <= group.end
+                            && inner.start < i
+                            && i < inner.end
+                    })
+            }),
+        }
+    }
+
+    /// Generate the query string
+    ///
+    /// # Panics
+    /// Panics if a comparison has no value, which the macro never produces but
+    /// the runtime builders can. An `IN` condition without values is rendered as
+    /// `IN ()`, which QuickBooks rejects. See [`Query::try_query_string`] to
+    /// check for both first.
+    pub fn query_string(&self) -> String {
+        self.render(self.limit)
+    }
+
+    /// Generate the query string with each clause on its own line, for logging
+    ///
+    /// Conditions after the first are indented on lines of their own. The
+    /// layout is only meant to be read by people; use [`Query::query_string`]
+    /// for requests.
+    pub fn query_string_pretty(&self) -> String {
+        self.render_layout(self.limit, true)
+    }
+
+    /// Generate the query string, or the first problem [`Query::validate`] finds
+    pub fn try_query_string(&self) -> Result<String, ValidationError> {
+        match self.validate().into_iter().next() {
+            Some(problem) => Err(problem),
+            None => Ok(self.query_string()),
+        }
+    }
+
+    /// Generate the query string with `limit` in place of the query's own limit
+    fn render(&self, limit: Option<Limit>) -> String {
+        self.render_layout(limit, false)
+    }
+
+    /// Render the query, breaking lines between clauses and conditions if `pretty`
+    fn render_layout(&self, limit: Option<Limit>, pretty: bool) -> String {
+        let separator = if pretty { "\n" } else { " " };
+        let mut query = String::from("select ");
+        if self.distinct {
+            query.push_str("distinct ");
+        }
+
+        match &self.fields[..] {
+            [] => query.push('*'),
+            fields => {
+                for (i, field) in fields.iter().enumerate() {
+                    if i > 0 {
+                        query.push_str(", ");
+                    }
+                    match field.aggregate {
+                        Some(aggregate) => {
+                            query.push_str(aggregate.as_str());
+                            query.push('(');
+                            self.dialect.push_identifier(&mut query, &field.field);
+                            query.push(')');
+                        }
+                        None => self.dialect.push_identifier(&mut query, &field.field),
+                    }
+                    if let (Some(alias), Dialect::Standard | Dialect::Postgres) =
+                        (&field.alias, self.dialect)
+                    {
+                        query.push_str(" AS ");
+                        self.dialect.push_identifier(&mut query, alias);
+                    }
+                }
+            }
+        }
+
+        self.push_from_where(&mut query, pretty);
+        self.push_group_by(&mut query, pretty);
+
+        if !self.order.is_empty() {
+            query.push_str(separator);
+            query.push_str(match self.dialect {
+                Dialect::Quickbooks => "ORDERBY",
+                Dialect::Standard | Dialect::Postgres => "order by",
+            });
+            for (i, ord) in self.order.iter().enumerate() {
+                if i > 0 {
+                    query.push(',');
+                }
+                ord.extend_query(&mut query, self.dialect);
+            }
+        }
+
+        if let Some(limit) = limit {
+            let mut clause = String::new();
+            limit.extend_query(&mut clause, self.dialect);
+            query.push_str(separator);
+            query.push_str(clause.trim_start());
+        }
+
+        query
+    }
+
+    /// Generate the query string counting the entities the query matches
+    ///
+    /// The selected fields are replaced with `count(*)`, and the order and limit
+    /// are left out since they don't affect the count. A `group by` clause is
+    /// kept, counting each group separately.
+    pub fn count_query_string(&self) -> String {
+        let mut query = String::from("select count(*)");
+        self.push_from_where(&mut query, false);
+        self.push_group_by(&mut query, false);
+        query
+    }
+
+    /// Append the `group by` clause to `query`, if the query has one, on its own line if `pretty`
+    fn push_group_by(&self, query: &mut String, pretty: bool) {
+        if !self.group_by.is_empty() {
+            query.push_str(if pretty { "\ngroup by " } else { " group by " });
+            for (i, field) in self.group_by.iter().enumerate() {
+                if i > 0 {
+                    query.push_str(", ");
+                }
+                self.dialect.push_identifier(query, field);
+            }
+        }
+    }
+
+    /// Append the `from` and `where` clauses to `query`, on lines of their own if `pretty`
+    fn push_from_where(&self, query: &mut String, pretty: bool) {
+        query.push_str(if pretty { "\nfrom " } else { " from " });
+        self.dialect.push_identifier(query, QB::name());
+
+        let true_base = self.true_base && self.dialect != Dialect::Quickbooks;
+        if true_base || !self.condition.is_empty() {
+            query.push_str(if pretty { "\nwhere" } else { " where" });
+            if true_base {
+                query.push_str(" 1=1");
+            }
+            for (i, cond) in self.condition.iter().enumerate() {
+                if i > 0 {
+                    query.push_str(if pretty { "\n  " } else { " " });
+                    query.push_str(match cond.join {
+                        Connective::And => "and",
+                        Connective::Or => "or",
+                    });
+                } else if true_base {
+                    // The first condition's join is ignored, since `1=1 or ...` matches everything
+                    query.push_str(" and");
+                }
+                query.push(' ');
+                let rendered = |g: &&Range<usize>| self.renders_group(g);
+                for _ in self.groups.iter().filter(|g| g.start == i).filter(rendered) {
+                    query.push('(');
+                }
+                let normalized = self.time_offset.and_then(|o| cond.normalize_times(o));
+                normalized.as_ref().unwrap_or(cond).extend_query(
+                    query,
+                    self.dialect,
+                    self.null_style,
+                );
+                for _ in self
+                    .groups
+                    .iter()
+                    .filter(|g| g.end == i + 1)
+                    .filter(rendered)
+                {
+                    query.push(')');
+                }
+            }
+        }
+    }
+
+    /// Turn the query into a [`CountQuery`] counting the entities it matches
+    pub fn into_count(self) -> CountQuery<QB> {
+        CountQuery { query: self }
+    }
+
+    #[cfg(feature = "api")]
+    /// Execute the query against the QuickBooks API, returning a vector of results or an error
+    pub fn execute(
+        &self,
+        qb: &quick_oxibooks::QBContext,
+        client: &ureq::Agent,
+    ) -> Result<Vec<QB>, quick_oxibooks::error::APIError> {
+        // Safety: The query has been constructed using the provided methods,
+        // ensuring that it is valid for the QuickBooks entity QB.
+        unsafe { quick_oxibooks::functions::query::qb_query_raw::<QB>(self, qb, client) }
+    }
+}
+
+/// Counts of each kind of clause in a [`Query`], as returned by [`Query::summary`]
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub struct QuerySummary {
+    /// The number of explicitly selected fields (zero for `select *`)
+    pub num_fields: usize,
+    pub num_conditions: usize,
+    /// The total number of values across all `IN` conditions
+    pub num_in_values: usize,
+    pub num_groups: usize,
+    pub has_like: bool,
+    pub has_or: bool,
+    pub has_order: bool,
+    /// Whether the query sets a limit on the number of results
+    pub is_paginated: bool,
+}
+
+impl<QB: QBItem> Default for Query<QB> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+// Implemented by hand so that cloning doesn't require `QB: Clone`
+impl<QB> Clone for Query<QB> {
+    fn clone(&self) -> Self {
+        Query {
+            distinct: self.distinct,
+            fields: Arc::clone(&self.fields),
+            condition: Arc::clone(&self.condition),
+            groups: Arc::clone(&self.groups),
+            group_by: Arc::clone(&self.group_by),
+            order: Arc::clone(&self.order),
+            limit: self.limit,
+            dialect: self.dialect,
+            null_style: self.null_style,
+            incremental: self.incremental,
+            order_required: self.order_required,
+            true_base: self.true_base,
+            allowed_lints: self.allowed_lints.clone(),
+            time_offset: self.time_offset,
+            #[cfg(feature = "api")]
+            retry: self.retry.clone(),
+            #[cfg(feature = "api")]
+            timeout: self.timeout,
+            _phantom: std::marker::PhantomData,
+        }
+    }
+}
+
+impl<QB: QBItem> std::fmt::Display for Query<QB> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.query_string())
+    }
+}
+
+/// A mutable builder for a [`Query`], passed to the closure given to [`Query::build`]
+#[derive(Debug)]
+pub struct QueryBuilder<QB> {
+    query: Query<QB>,
+}
+
+impl<QB: QBItem> QueryBuilder<QB> {
+    /// Add a field to select, see [`Query::select_field`]
+    pub fn select(&mut self, field: impl Into<Cow<'static, str>>) -> &mut Self {
+        Arc::make_mut(&mut self.query.fields).push(SelectField {
+            field: field.into(),
+            alias: None,
+            aggregate: None,
+        });
+        self
+    }
+
+    /// Add a condition, see [`Query::with_condition`]
+    pub fn filter(&mut self, condition: WhereClause) -> &mut Self {
+        Arc::make_mut(&mut self.query.condition).push(condition);
+        self
+    }
+
+    /// Add an order clause, see [`Query::order_by`]
+    pub fn order_by(&mut self, field: impl Into<Cow<'static, str>>, order: Order) -> &mut Self {
+        Arc::make_mut(&mut self.query.order).push(OrderClause {
+            field: field.into(),
+            order,
+            nulls: None,
+        });
+        self
+    }
+
+    /// Set a limit on the number of results, see [`Query::limit`]
+    pub fn limit(&mut self, number: u32, offset: Option<u32>) -> &mut Self {
+        self.query.limit = Some(Limit { number, offset });
+        self
+    }
+}
+
+/// A query counting the entities matched by a [`Query`] instead of returning them
+///
+/// The order and limit of the underlying query are ignored, since they don't
+/// affect the count.
+#[derive(Debug, PartialEq, Clone)]
+pub struct CountQuery<QB> {
+    query: Query<QB>,
+}
+
+impl<QB: QBItem> CountQuery<QB> {
+    /// The query whose matches are counted
+    pub fn query(&self) -> &Query<QB> {
+        &self.query
+    }
+
+    /// Generate the query string
+    pub fn query_string(&self) -> String {
+        self.query.count_query_string()
+    }
+}
+
+impl<QB: QBItem> std::fmt::Display for CountQuery<QB> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.query_string())
+    }
+}
+
+/// The limit and offset of a query, see [`Query::limit`]
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Limit {
+    number: u32,
+    offset: Option<u32>,
+}
+
+impl Limit {
+    /// The most entities the query returns
+    pub fn number(&self) -> u32 {
+        self.number
+    }
+
+    /// The number of entities skipped before the first one returned
+    ///
+    /// This is 0-based, while QuickBooks' `STARTPOSITION` is 1-based.
+    pub fn offset(&self) -> Option<u32> {
+        self.offset
+    }
+
+    fn extend_query(&self, query: &mut String, dialect: Dialect) {
+        match dialect {
+            Dialect::Quickbooks => {
+                // STARTPOSITION is 1-based, while the offset counts results to skip
+                if let Some(offset) = self.offset {
+                    write!(query, " STARTPOSITION {}", offset + 1).unwrap();
+                }
+                write!(query, " MAXRESULTS {}", self.number).unwrap();
+            }
+            Dialect::Standard | Dialect::Postgres => {
+                write!(query, " LIMIT {}", self.number).unwrap();
+                if let Some(offset) = self.offset {
+                    write!(query, " OFFSET {}", offset).unwrap();
+                }
+            }
+        }
+    }
+}
+
+/// Struct representing a selected field in a query
+#[derive(Debug, PartialEq, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct SelectField {
+    field: Cow<'static, str>,
+    alias: Option<Cow<'static, str>>,
+    aggregate: Option<Aggregate>,
+}
+
+impl SelectField {
+    /// The QuickBooks name of the field
+    pub fn field(&self) -> &str {
+        &self.field
+    }
+
+    /// The alias the field is selected as, if any
+    pub fn alias(&self) -> Option<&str> {
+        self.alias.as_deref()
+    }
+
+    /// The aggregate function applied to the field, if any
+    pub fn aggregate(&self) -> Option<Aggregate> {
+        self.aggregate
+    }
+}
+
+/// Struct representing an order clause in a query
+#[derive(Debug, PartialEq, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct OrderClause {
+    field: Cow<'static, str>,
+    order: Order,
+    nulls: Option<Nulls>,
+}
+
+impl OrderClause {
+    /// The QuickBooks name of the field ordered by
+    pub fn field(&self) -> &str {
+        &self.field
+    }
+
+    /// The direction of the ordering
+    pub fn order(&self) -> &Order {
+        &self.order
+    }
+
+    /// Where entities without the field are placed, if given
+    pub fn nulls(&self) -> Option<Nulls> {
+        self.nulls
+    }
+
+    fn extend_query(&self, query: &mut String, dialect: Dialect) {
+        query.push(' ');
+        dialect.push_identifier(query, &self.field);
+        query.push_str(match self.order {
+            Order::Asc => " ASC",
+            Order::Desc => " DESC",
+        });
+        // Not supported by QuickBooks, see `Query::order_by_nulls`
+        match (dialect, self.nulls) {
+            (Dialect::Quickbooks, _) | (_, None) => {}
+            (Dialect::Standard | Dialect::Postgres, Some(Nulls::First)) => {
+                query.push_str(" NULLS FIRST");
+            }
+            (Dialect::Standard | Dialect::Postgres, Some(Nulls::Last)) => {
+                query.push_str(" NULLS LAST");
+            }
+        }
+    }
+}
+
+/// Enum representing the order direction in a query
+#[derive(Debug, PartialEq, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Order {
+    Asc,
+    Desc,
+}
+
+/// Where entities without the ordered field are placed, see [`Query::order_by_nulls`]
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Nulls {
+    First,
+    Last,
+}
+
+/// An aggregate function applied to a selected field, see [`Query::select_aggregate`]
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Aggregate {
+    Count,
+    Max,
+    Min,
+    Sum,
+}
+
+impl Aggregate {
+    /// The name of the function as written in the query
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Aggregate::Count => "count",
+            Aggregate::Max => "max",
+            Aggregate::Min => "min",
+            Aggregate::Sum => "sum",
+        }
+    }
+}
 
 /// Struct representing a where clause in a query
 #[derive(Debug, PartialEq, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct WhereClause {
-    pub field: &'static str,
+    pub field: Cow<'static, str>,
     pub operator: Operator,
-    pub values: Vec<String>,
+    pub values: Values,
+    pub negated: bool,
+    pub join: Connective,
+    /// Whether the values are inserted verbatim instead of being quoted
+    pub raw: bool,
 }
 
 impl WhereClause {
     /// Create a new where clause
-    pub fn new(field: &'static str, operator: Operator) -> Self {
+    ///
+    /// The field name may be decided at runtime, e.g. from user input.
+    pub fn new(field: impl Into<Cow<'static, str>>, operator: Operator) -> Self {
         Self {
-            field,
+            field: field.into(),
             operator,
-            values: Vec::new(),
+            values: Values::default(),
+            negated: false,
+            join: Connective::And,
+            raw: false,
         }
     }
 
-    /// Add a value to the where clause
-    pub fn add_value<T: Display>(mut self, value: T) -> Self {
-        self.values.push(value.to_string());
-        self
-    }
+    /// Create a `LIKE` clause matching values that contain `term` literally
+    ///
+    /// Any `%` or `_` in `term` is escaped, so user input is never treated as a
+    /// wildcard. Use [`WhereClause::new`] with [`Operator::Like`] for raw patterns.
+    pub fn contains<T: Display>(field: impl Into<Cow<'static, str>>, term: T) -> Self {
+        Self::like_pattern(field, "%", term, "%")
+    }
+
+    /// Create a `LIKE` clause matching values that start with `term` literally
+    ///
+    /// See [`WhereClause::contains`].
+    pub fn starts_with<T: Display>(field: impl Into<Cow<'static, str>>, term: T) -> Self {
+        Self::like_pattern(field, "", term, "%")
+    }
+
+    /// Create a `LIKE` clause matching values that end with `term` literally
+    ///
+    /// See [`WhereClause::contains`].
+    pub fn ends_with<T: Display>(field: impl Into<Cow<'static, str>>, term: T) -> Self {
+        Self::like_pattern(field, "%", term, "")
+    }
+
+    fn like_pattern<T: Display>(
+        field: impl Into<Cow<'static, str>>,
+        prefix: &str,
+        term: T,
+        suffix: &str,
+    ) -> Self {
+        let term = escape_like(&term.to_string());
+        Self::new(field, Operator::Like).add_value(format!("{prefix}{term}{suffix}"))
+    }
+
+    /// Create an `IN` clause whose values come from an iterator
+    ///
+    /// The iterator isn't consumed until the values are first read, e.g. when
+    /// the query is rendered or validated, and is consumed exactly once. See
+    /// [`Values`].
+    pub fn in_iter<I>(field: impl Into<Cow<'static, str>>, values: I) -> Self
+    where
+        I: IntoIterator + Send + 'static,
+        I::Item: QueryValue,
+    {
+        Self::new(field, Operator::In).with_deferred_values(values)
+    }
+
+    /// Create a `NOT IN` clause whose values come from an iterator
+    ///
+    /// See [`WhereClause::in_iter`].
+    pub fn not_in_iter<I>(field: impl Into<Cow<'static, str>>, values: I) -> Self
+    where
+        I: IntoIterator + Send + 'static,
+        I::Item: QueryValue,
+    {
+        Self::new(field, Operator::NotIn).with_deferred_values(values)
+    }
+
+    /// Replace the clause's values with ones rendered from `values` when first read
+    ///
+    /// See [`Values`]. Use [`WhereClause::add_query_values`] for iterators that borrow.
+    pub fn with_deferred_values<I>(mut self, values: I) -> Self
+    where
+        I: IntoIterator + Send + 'static,
+        I::Item: QueryValue,
+    {
+        self.values = Values::deferred(values);
+        self
+    }
+
+    /// Negate the where clause, rendering it as `NOT Field ...`
+    pub fn negate(mut self) -> Self {
+        self.negated = !self.negated;
+        self
+    }
+
+    /// Set the connective joining this clause to the one before it
+    pub fn joined_by(mut self, join: Connective) -> Self {
+        self.join = join;
+        self
+    }
+
+    /// Add a value to the where clause
+    pub fn add_value<T: Display>(mut self, value: T) -> Self {
+        self.values.push(value.to_string());
+        self
+    }
+
+    /// Add a value to the where clause, rendered with [`QueryValue`]
+    ///
+    /// This is what [`qb_sql!`] uses for condition values.
+    pub fn add_query_value<T: QueryValue + ?Sized>(mut self, value: &T) -> Self {
+        self.values.push(value.to_query_literal());
+        self
+    }
+
+    /// Add multiple values to the where clause from an iterator
+    pub fn add_values<I, T>(mut self, values: I) -> Self
+    where
+        I: Iterator<Item = T>,
+        T: Display,
+    {
+        self.values.extend(values.map(|v| v.to_string()));
+        self
+    }
+
+    /// Add multiple values to the where clause, rendered with [`QueryValue`]
+    ///
+    /// This is what [`qb_sql!`] uses for `in (iterator)`. Unlike
+    /// [`WhereClause::in_iter`], the iterator is consumed right away, so it may borrow.
+    pub fn add_query_values<I>(mut self, values: I) -> Self
+    where
+        I: IntoIterator,
+        I::Item: QueryValue,
+    {
+        let values = values.into_iter();
+        self.values.reserve(values.size_hint().0);
+        self.values
+            .extend(values.map(|value| value.to_query_literal()));
+        self
+    }
+}
+
+impl WhereClause {
+    fn extend_query(&self, query: &mut String, dialect: Dialect, null_style: NullStyle) {
+        let op_str = match self.operator {
+            Operator::In => "IN",
+            Operator::NotIn => "NOT IN",
+            // QuickBooks matches LIKE patterns case-insensitively, which Postgres spells ILIKE
+            Operator::Like if dialect == Dialect::Postgres => "ILIKE",
+            Operator::NotLike if dialect == Dialect::Postgres => "NOT ILIKE",
+            Operator::Like => "LIKE",
+            Operator::NotLike => "NOT LIKE",
+            Operator::Equal => "=",
+            Operator::NotEqual => "!=",
+            Operator::Less => "<",
+            Operator::Greater => ">",
+            Operator::LessEqual => "<=",
+            Operator::GreaterEqual => ">=",
+            Operator::IsNull => match null_style {
+                NullStyle::IsNull => "IS NULL",
+                NullStyle::EqualsNull => "= NULL",
+            },
+            Operator::IsNotNull => match null_style {
+                NullStyle::IsNull => "IS NOT NULL",
+                NullStyle::EqualsNull => "!= NULL",
+            },
+            Operator::Between => "BETWEEN",
+        };
+
+        if self.negated {
+            query.push_str("NOT ");
+        }
+
+        dialect.push_identifier(query, &self.field);
+        query.push(' ');
+        query.push_str(op_str);
+
+        if !self.operator.takes_value() {
+            return;
+        }
+
+        query.push(' ');
+        if self.operator == Operator::Between {
+            self.push_value(query, &self.values[0], dialect);
+            query.push_str(" AND ");
+            self.push_value(query, &self.values[1], dialect);
+        } else if self.operator.takes_list() {
+            query.push('(');
+            for (i, value) in self.values.iter().enumerate() {
+                if i > 0 {
+                    query.push_str(", ");
+                }
+                self.push_value(query, value, dialect);
+            }
+            query.push(')');
+        } else {
+            self.push_value(query, &self.values[0], dialect);
+        }
+    }
+
+    /// A copy of the clause with its timestamps converted to `offset`, if it has any
+    fn normalize_times(&self, offset: UtcOffset) -> Option<WhereClause> {
+        if self.raw {
+            return None;
+        }
+        let values: Vec<_> = self
+            .values
+            .iter()
+            .map(|value| time::convert_timestamp(value, offset))
+            .collect();
+        if values.iter().all(Option::is_none) {
+            return None;
+        }
+        Some(WhereClause {
+            values: values
+                .into_iter()
+                .zip(self.values.iter())
+                .map(|(converted, value)| converted.unwrap_or_else(|| value.clone()))
+                .collect::<Vec<_>>()
+                .into(),
+            ..self.clone()
+        })
+    }
+
+    fn push_value(&self, query: &mut String, value: &str, dialect: Dialect) {
+        if self.raw {
+            query.push_str(value);
+        } else {
+            // Each value is quoted independently, so only backslashes and quotes need
+            // escaping; delimiters such as commas are safe inside the quotes.
+            // LIKE patterns keep their backslashes, which escape the wildcards.
+            // Postgres strings treat backslashes literally and double their quotes.
+            let postgres = dialect == Dialect::Postgres;
+            let escape_backslashes =
+                !postgres && !matches!(self.operator, Operator::Like | Operator::NotLike);
+            query.reserve(value.len() + 2);
+            query.push('\'');
+            for c in value.chars() {
+                match c {
+                    '\'' if postgres => query.push_str("''"),
+                    '\'' => query.push_str("\\'"),
+                    '\\' if escape_backslashes => query.push_str("\\\\"),
+                    c => query.push(c),
+                }
+            }
+            query.push('\'');
+        }
+    }
+}
+
+/// The query language a [`Query`] is rendered for
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Dialect {
+    /// The QuickBooks Online query language
+    ///
+    /// QuickBooks doesn't support parenthesized conditions, so groups that only
+    /// contain `and` are flattened.
+    #[default]
+    Quickbooks,
+    /// Standard SQL, rendering every condition group as written
+    Standard,
+    /// PostgreSQL, for QuickBooks data mirrored into a database
+    ///
+    /// Renders like [`Dialect::Standard`], with identifiers double-quoted to keep
+    /// their case, `ILIKE` in place of `LIKE` to match QuickBooks'
+    /// case-insensitive patterns, and quotes in values doubled.
+    Postgres,
+}
+
+impl Dialect {
+    /// Append a field or entity name to `query`, quoting each segment of a
+    /// dotted path separately where the dialect needs it
+    fn push_identifier(self, query: &mut String, name: &str) {
+        match self {
+            Dialect::Quickbooks | Dialect::Standard => query.push_str(name),
+            Dialect::Postgres => {
+                for (i, segment) in name.split('.').enumerate() {
+                    if i > 0 {
+                        query.push('.');
+                    }
+                    query.push('"');
+                    query.push_str(&segment.replace('"', "\"\""));
+                    query.push('"');
+                }
+            }
+        }
+    }
+}
+
+/// How null checks are rendered in a query string
+///
+/// Support for `IS NULL` varies between QuickBooks entities and minor versions,
+/// while some contexts only accept `Field = NULL`.
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum NullStyle {
+    /// `Field IS NULL` / `Field IS NOT NULL`
+    #[default]
+    IsNull,
+    /// `Field = NULL` / `Field != NULL`
+    EqualsNull,
+}
+
+/// Enum representing how a where clause is joined to the clause before it
+///
+/// Note that the QuickBooks Online query language has limited support for `OR`;
+/// queries using it are rendered as written but may be rejected by the API.
+#[derive(Debug, PartialEq, Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Connective {
+    And,
+    Or,
+}
+
+/// Escape a literal term so it matches itself exactly in a `LIKE` pattern
+///
+/// Backslash is the pattern escape character, so it is escaped along with the
+/// `%` and `_` wildcards.
+fn escape_like(term: &str) -> String {
+    let mut escaped = String::with_capacity(term.len());
+    for c in term.chars() {
+        if matches!(c, '\\' | '%' | '_') {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+    }
+    escaped
+}
+
+/// Enum representing the operators used in where clauses
+#[derive(Debug, PartialEq, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Operator {
+    In,
+    NotIn,
+    Like,
+    NotLike,
+    Equal,
+    NotEqual,
+    Less,
+    Greater,
+    LessEqual,
+    GreaterEqual,
+    IsNull,
+    IsNotNull,
+    Between,
+}
+
+impl Operator {
+    /// Whether the operator compares against a parenthesized list of values
+    fn takes_list(&self) -> bool {
+        matches!(self, Operator::In | Operator::NotIn)
+    }
+
+    /// Whether the operator compares against any value
+    fn takes_value(&self) -> bool {
+        !matches!(self, Operator::IsNull | Operator::IsNotNull)
+    }
+
+    /// The operator matching exactly the values this one doesn't
+    ///
+    /// Returns `None` for `Between`, which QuickBooks has no negated form of.
+    pub fn negated(&self) -> Option<Operator> {
+        Some(match self {
+            Operator::In => Operator::NotIn,
+            Operator::NotIn => Operator::In,
+            Operator::Like => Operator::NotLike,
+            Operator::NotLike => Operator::Like,
+            Operator::Equal => Operator::NotEqual,
+            Operator::NotEqual => Operator::Equal,
+            Operator::Less => Operator::GreaterEqual,
+            Operator::GreaterEqual => Operator::Less,
+            Operator::Greater => Operator::LessEqual,
+            Operator::LessEqual => Operator::Greater,
+            Operator::IsNull => Operator::IsNotNull,
+            Operator::IsNotNull => Operator::IsNull,
+            Operator::Between => return None,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use quickbooks_types::Customer;
+
+    #[test]
+    fn test_empty_query() {
+        let query = qb_sql!(select * from Customer);
+        assert_eq!(query.fields.len(), 0);
+        assert_eq!(query.condition.len(), 0);
+        assert_eq!(query.groups.len(), 0);
+        assert_eq!(query.order.len(), 0);
+        assert!(query.limit.is_none());
+    }
+
+    #[test]
+    fn test_accessors() {
+        let query = qb_sql!(
+            select display_name as name, balance from Customer
+            where balance > 100 or display_name like "A%"
+            order by balance desc
+            limit 10 offset 20
+        );
+
+        let conditions: Vec<_> = query
+            .conditions()
+            .iter()
+            .map(|c| (c.field.as_ref(), &c.operator, c.values.as_slice(), &c.join))
+            .collect();
+        assert_eq!(
+            conditions,
+            vec![
+                (
+                    "Balance",
+                    &Operator::Greater,
+                    &["100".to_string()][..],
+                    &Connective::And
+                ),
+                (
+                    "DisplayName",
+                    &Operator::Like,
+                    &["A%".to_string()][..],
+                    &Connective::Or
+                ),
+            ]
+        );
+        assert!(query.has_conditions());
+        assert!(!query.is_empty());
+
+        let fields: Vec<_> = query
+            .fields()
+            .iter()
+            .map(|f| (f.field(), f.alias()))
+            .collect();
+        assert_eq!(
+            fields,
+            vec![("DisplayName", Some("name")), ("Balance", None)]
+        );
+        assert_eq!(query.ordering()[0].field(), "Balance");
+        assert_eq!(query.ordering()[0].order(), &Order::Desc);
+
+        let limit = query.limit_clause().unwrap();
+        assert_eq!((limit.number(), limit.offset()), (10, Some(20)));
+
+        let empty = qb_sql!(select * from Customer);
+        assert!(empty.is_empty());
+        assert!(!empty.has_conditions());
+        assert_eq!(empty.limit_clause(), None);
+    }
+
+    #[test]
+    fn test_and_not() {
+        let query = Query::<Customer>::new()
+            .and_not("DisplayName", Operator::Like, "Test%")
+            .and_not("Balance", Operator::Less, 100)
+            .and_not("Title", Operator::IsNull, "");
+        assert_eq!(
+            query.query_string(),
+            "select * from Customer where DisplayName NOT LIKE 'Test%' \
+             and Balance >= '100' and Title IS NOT NULL"
+        );
+        assert_eq!(Operator::NotIn.negated(), Some(Operator::In));
+        assert_eq!(Operator::Between.negated(), None);
+    }
+
+    #[test]
+    #[should_panic(expected = "`Between` has no negated operator")]
+    fn test_and_not_between() {
+        let _ = Query::<Customer>::new().and_not("Balance", Operator::Between, 0);
+    }
+
+    #[test]
+    fn test_query_without_where() {
+        let query = qb_sql!(select * from Customer order by display_name asc limit 100);
+        assert!(query.condition.is_empty());
+        assert_eq!(
+            query.query_string(),
+            "select * from Customer ORDERBY DisplayName ASC MAXRESULTS 100"
+        );
+
+        let query = qb_sql!(select display_name from Customer order by display_name desc);
+        assert_eq!(
+            query.query_string(),
+            "select DisplayName from Customer ORDERBY DisplayName DESC"
+        );
+
+        let query = qb_sql!(select * from Customer limit 10 offset 20);
+        assert_eq!(
+            query.query_string(),
+            "select * from Customer STARTPOSITION 21 MAXRESULTS 10"
+        );
+    }
+
+    #[test]
+    fn test_basic_query() {
+        let query = qb_sql!(
+            select * from Customer
+            where display_name like "John%"
+        );
+
+        assert_eq!(query.condition.len(), 1);
+        assert_eq!(query.condition[0].field, "DisplayName");
+    }
+
+    #[test]
+    fn test_multiple_conditions() {
+        let balance_min = 1000.0;
+        let query = qb_sql!(
+            select * from Customer
+            where display_name like "John%"
+            and balance >= balance_min
+        );
+
+        assert_eq!(query.condition.len(), 2);
+    }
+
+    #[test]
+    fn test_field_selection() {
+        let query = qb_sql!(
+            select display_name, balance from Customer
+            where display_name like "John%"
+        );
+
+        assert_eq!(query.fields.len(), 2);
+        assert_eq!(query.fields[0].field, "DisplayName");
+        assert_eq!(query.fields[1].field, "Balance");
+    }
+
+    #[test]
+    fn test_order_by() {
+        let query = qb_sql!(
+            select * from Customer
+            where display_name like "John%"
+            order by display_name asc, balance desc
+        );
+
+        assert_eq!(query.order.len(), 2);
+        assert_eq!(query.order[0].field, "DisplayName");
+        assert_eq!(query.order[0].order, Order::Asc);
+    }
+
+    #[test]
+    fn test_order_by_nulls() {
+        let query =
+            qb_sql!(select * from Customer order by balance desc nulls last, id NULLS FIRST);
+
+        assert_eq!(query.ordering()[0].nulls(), Some(Nulls::Last));
+        assert_eq!(query.ordering()[1].nulls(), Some(Nulls::First));
+        assert_eq!(
+            query.clone().dialect(Dialect::Standard).query_string(),
+            "select * from Customer order by Balance DESC NULLS LAST, Id ASC NULLS FIRST"
+        );
+        assert_eq!(
+            query.clone().dialect(Dialect::Postgres).query_string(),
+            "select * from \"Customer\" order by \"Balance\" DESC NULLS LAST, \"Id\" ASC NULLS FIRST"
+        );
+        // QuickBooks can't place nulls, so the placement is left out
+        assert_eq!(
+            query.query_string(),
+            "select * from Customer ORDERBY Balance DESC, Id ASC"
+        );
+    }
+
+    #[test]
+    fn test_currency_condition() {
+        let amount = 1000;
+        let query = qb_sql!(
+            select * from quickbooks_types::Bill
+            where balance >= amount in "USD" or total_amt < 5
+        );
+
+        assert_eq!(query.conditions()[1].field, "CurrencyRef");
+        assert_eq!(
+            query.query_string(),
+            "select * from Bill where Balance >= '1000' and CurrencyRef = 'USD' or TotalAmt < '5'"
+        );
+        assert_eq!(
+            query.dialect(Dialect::Standard).query_string(),
+            "select * from Bill where (Balance >= '1000' and CurrencyRef = 'USD') or TotalAmt < '5'"
+        );
+    }
+
+    #[test]
+    fn test_limit_and_offset() {
+        let offset_val = 5;
+        let query = qb_sql!(
+            select * from Customer
+            where display_name like "John%"
+            limit 10 offset offset_val
+        );
+
+        assert!(query.limit.is_some());
+        let limit = query.limit.unwrap();
+        assert_eq!(limit.number, 10);
+        assert_eq!(limit.offset, Some(5));
+        assert_eq!(
+            query.query_string(),
+            "select * from Customer where DisplayName LIKE 'John%' STARTPOSITION 6 MAXRESULTS 10"
+        );
+        assert_eq!(
+            query.dialect(Dialect::Standard).query_string(),
+            "select * from Customer where DisplayName LIKE 'John%' LIMIT 10 OFFSET 5"
+        );
+    }
+
+    #[test]
+    fn test_limit_expressions() {
+        const PAGE_SIZE: u32 = 50;
+        fn page_size() -> u32 {
+            25
+        }
+        let count = 10;
+
+        let query = qb_sql!(select * from Customer limit count);
+        assert_eq!(query.query_string(), "select * from Customer MAXRESULTS 10");
+
+        let query = qb_sql!(select * from Customer limit PAGE_SIZE offset PAGE_SIZE * 2);
+        assert_eq!(
+            query.query_string(),
+            "select * from Customer STARTPOSITION 101 MAXRESULTS 50"
+        );
+
+        let query = qb_sql!(select * from Customer maxresults page_size() startposition 1);
+        assert_eq!(
+            query.query_string(),
+            "select * from Customer STARTPOSITION 1 MAXRESULTS 25"
+        );
+    }
+
+    #[test]
+    fn test_like_concatenation() {
+        let query = qb_sql!(select * from Customer where display_name like "Jo" + "hn" + "%");
+        assert_eq!(
+            query.query_string(),
+            "select * from Customer where DisplayName LIKE 'John%'"
+        );
+
+        let suffix = String::from("n's");
+        let query = qb_sql!(select * from Customer where display_name like "Jo" + suffix + "%");
+        assert_eq!(
+            query.query_string(),
+            "select * from Customer where DisplayName LIKE 'Jon\\'s%'"
+        );
+        assert_eq!(suffix, "n's");
+    }
+
+    #[test]
+    fn test_nested_where_paths() {
+        let since = "2024-01-01T00:00:00Z";
+        let query = qb_sql!(
+            select * from Customer
+            where meta_data.last_updated_time > since
+            and bill_addr.city = "Springfield"
+        );
+        assert_eq!(
+            query.query_string(),
+            "select * from Customer where MetaData.LastUpdatedTime > '2024-01-01T00:00:00Z' \
+             and BillAddr.City = 'Springfield'"
+        );
+    }
+
+    #[test]
+    fn test_unchecked_field() {
+        let value = 5;
+        let query = qb_sql!(
+            select display_name, sub_type? from Customer
+            where custom_column? = value and meta_data.job_status? = "Open"
+            and balance > 0
+            order by level? desc
+        );
+        assert_eq!(
+            query.query_string(),
+            "select DisplayName, SubType from Customer where CustomColumn = '5' \
+             and MetaData.JobStatus = 'Open' and Balance > '0' ORDERBY Level DESC"
+        );
+    }
+
+    #[test]
+    fn test_value_kinds() {
+        let low: Option<f64> = Some(10.5);
+        let name = String::from("Acme");
+        let query = qb_sql!(
+            select * from Customer
+            where id in (1, "2") and balance between low and 500
+            and display_name = name and active = true
+        );
+        assert_eq!(
+            query.query_string(),
+            "select * from Customer where Id IN ('1', '2') and Balance BETWEEN '10.5' AND '500' \
+             and DisplayName = 'Acme' and Active = 'true'"
+        );
+
+        // Document numbers are strings, but often written as numbers
+        let query = qb_sql!(select * from quickbooks_types::Invoice where doc_number = 1001 or doc_number = "1002-A");
+        assert_eq!(
+            query.query_string(),
+            "select * from Invoice where DocNumber = '1001' or DocNumber = '1002-A'"
+        );
+    }
+
+    #[test]
+    fn test_custom_query_values() {
+        struct Cents(u64);
+
+        impl QueryValue for Cents {
+            fn to_query_literal(&self) -> String {
+                format!("{}.{:02}", self.0 / 100, self.0 % 100)
+            }
+        }
+
+        let min = Cents(100_050);
+        let ids = vec![Some(1), Some(2)];
+        let query = qb_sql!(select * from Customer where balance >= min and id in (ids));
+        assert_eq!(
+            query.query_string(),
+            "select * from Customer where Balance >= '1000.50' and Id IN ('1', '2')"
+        );
+    }
+
+    #[cfg(feature = "chrono")]
+    #[test]
+    fn test_date_condition_literal() {
+        use chrono::{FixedOffset, TimeZone};
+
+        let since = FixedOffset::west_opt(7 * 3600)
+            .unwrap()
+            .with_ymd_and_hms(2015, 7, 25, 10, 33, 0)
+            .unwrap();
+        let query = qb_sql!(select * from Customer where meta_data.last_updated_time >= since);
+        assert_eq!(
+            query.query_string(),
+            "select * from Customer where MetaData.LastUpdatedTime >= '2015-07-25T10:33:00-07:00'"
+        );
+    }
+
+    #[test]
+    fn test_nested_order_by() {
+        let query = qb_sql!(
+            select * from Customer
+            order by meta_data.last_updated_time desc, display_name
+        );
+        assert_eq!(
+            query.query_string(),
+            "select * from Customer ORDERBY MetaData.LastUpdatedTime DESC, DisplayName ASC"
+        );
+    }
+
+    #[test]
+    fn test_rename_function() {
+        /// Name fields with their acronyms uppercased
+        fn acronyms(field: &str) -> &str {
+            match field {
+                "id" => "ID",
+                "display_name" => "DisplayName",
+                "primary_email_addr.address" => "PrimaryEmailADDR.Address",
+                "meta_data.create_time" => "MetaData.CreateTime",
+                other => other,
+            }
+        }
+
+        let query = qb_sql!(
+            #[rename = acronyms]
+            select id, display_name from Customer
+            where primary_email_addr.address like "%@example.com" and id in (1, 2)
+            group by display_name
+            order by meta_data.create_time desc
+        );
+        assert_eq!(
+            query.query_string(),
+            "select ID, DisplayName from Customer where PrimaryEmailADDR.Address LIKE '%@example.com' \
+             and ID IN ('1', '2') group by DisplayName ORDERBY MetaData.CreateTime DESC"
+        );
+    }
+
+    #[test]
+    fn test_field_name_override() {
+        let email = "jo@example.com";
+        let query = qb_sql!(
+            select display_name, primary_email_addr.address as "PrimaryEmailAddr" as email
+            from Customer
+            where primary_email_addr.address as "PrimaryEmailAddr" = email
+            order by id as "ID" desc
+        );
+        assert_eq!(query.conditions()[0].field, "PrimaryEmailAddr");
+        assert_eq!(query.fields()[1].alias(), Some("email"));
+        assert_eq!(
+            query.query_string(),
+            "select DisplayName, PrimaryEmailAddr from Customer \
+             where PrimaryEmailAddr = 'jo@example.com' ORDERBY ID DESC"
+        );
+
+        fn lowercase(field: &str) -> String {
+            field.to_lowercase()
+        }
+        let query = qb_sql!(
+            #[rename = lowercase]
+            select display_name, id as "Id" from Customer
+        );
+        assert_eq!(
+            query.query_string(),
+            "select display_name, Id from Customer"
+        );
+    }
+
+    #[test]
+    fn test_uppercase_keywords() {
+        let query = qb_sql!(
+            SELECT DISTINCT display_name FROM Customer
+            WHERE balance BETWEEN 0 AND 100 AND display_name LIKE "A%"
+            OR id NOT IN (1, 2) AND title IS NOT NULL
+            ORDER BY display_name DESC
+            LIMIT 5 OFFSET 10
+        );
+        let lowercase = qb_sql!(
+            select distinct display_name from Customer
+            where balance between 0 and 100 and display_name like "A%"
+            or id not in (1, 2) and title is not null
+            order by display_name desc
+            limit 5 offset 10
+        );
+        assert_eq!(query.query_string(), lowercase.query_string());
+
+        let query = qb_sql!(Select Count From Customer Where title Is Null);
+        assert_eq!(
+            query.query_string(),
+            "select count(*) from Customer where Title IS NULL"
+        );
+    }
+
+    #[test]
+    fn test_raw_identifier_fields() {
+        // Fields named after keywords such as `r#type` are written the same way
+        let query = qb_sql!(
+            select r#display_name as r#name from Customer
+            where r#display_name = "Acme" and r#meta_data.r#create_time > "2024-01-01"
+            order by r#balance desc
+        );
+        assert_eq!(query.fields[0].alias.as_deref(), Some("name"));
+        assert_eq!(
+            query.query_string(),
+            "select DisplayName from Customer where DisplayName = 'Acme' \
+             and MetaData.CreateTime > '2024-01-01' ORDERBY Balance DESC"
+        );
+    }
+
+    #[test]
+    fn test_local_variable_value_is_not_a_field_comparison() {
+        let min_balance = 100;
+        let query = qb_sql!(select * from Customer where balance > min_balance);
+        assert_eq!(
+            query.query_string(),
+            "select * from Customer where Balance > '100'"
+        );
+
+        let display_name = "Oak & Co";
+        let query = qb_sql!(select * from Customer where display_name = display_name);
+        assert_eq!(
+            query.query_string(),
+            "select * from Customer where DisplayName = 'Oak & Co'"
+        );
+
+        // Parenthesized values are expressions like any other
+        let query = qb_sql!(select * from Customer where display_name = (display_name));
+        assert_eq!(
+            query.query_string(),
+            "select * from Customer where DisplayName = 'Oak & Co'"
+        );
+    }
+
+    #[test]
+    fn test_merge() {
+        let by_balance = qb_sql!(
+            select display_name from Customer
+            where balance > 100 or active = true
+            order by display_name
+        );
+        let by_id = qb_sql!(
+            select display_name, balance from Customer
+            where id in (1, 2)
+            order by balance desc, display_name desc
+            limit 5
+        );
+
+        assert_eq!(
+            by_balance.merge(by_id).query_string(),
+            "select DisplayName, Balance from Customer \
+             where (Balance > '100' or Active = 'true') and Id IN ('1', '2') \
+             ORDERBY DisplayName ASC, Balance DESC MAXRESULTS 5"
+        );
+
+        let first = qb_sql!(select * from Customer where active = true limit 10);
+        let second = qb_sql!(select * from Customer where id = 1 or id = 2 limit 20);
+        assert_eq!(
+            first.merge(second).query_string(),
+            "select * from Customer where Active = 'true' and (Id = '1' or Id = '2') MAXRESULTS 10"
+        );
+    }
+
+    #[test]
+    fn test_merge_limits() {
+        let first = qb_sql!(select * from Customer limit 20 offset 10);
+        let second = qb_sql!(select * from Customer where active = true limit 5 offset 10);
+        let merged = first.clone().merge_limits(&second).unwrap();
+        assert_eq!(merged.limit_clause(), second.limit_clause());
+        assert_eq!(
+            merged.merge(second).query_string(),
+            "select * from Customer where Active = 'true' STARTPOSITION 11 MAXRESULTS 5"
+        );
+
+        // A missing offset matches an offset of 0, and a single limit is kept
+        let unpaged = qb_sql!(select * from Customer limit 50);
+        let first_page = qb_sql!(select * from Customer limit 100 offset 0);
+        let merged = unpaged.clone().merge_limits(&first_page).unwrap();
+        assert_eq!(merged.limit_clause(), unpaged.limit_clause());
+        let merged = Query::<Customer>::new().merge_limits(&unpaged).unwrap();
+        assert_eq!(merged.limit_clause(), unpaged.limit_clause());
+
+        let err = first.merge_limits(&unpaged).unwrap_err();
+        assert_eq!(
+            err,
+            ValidationError::ConflictingLimits {
+                left: Limit {
+                    number: 20,
+                    offset: Some(10)
+                },
+                right: Limit {
+                    number: 50,
+                    offset: None
+                },
+            }
+        );
+        assert_eq!(err.to_string(), "can't merge limits at offsets 10 and 0");
+    }
+
+    #[test]
+    fn test_nested_field_paths() {
+        let query = qb_sql!(
+            select display_name, bill_addr.city from Customer
+            where meta_data.last_updated_time > "2020-01-01"
+        );
+        assert_eq!(
+            query.query_string(),
+            "select DisplayName, BillAddr.City from Customer where MetaData.LastUpdatedTime > '2020-01-01'"
+        );
+    }
+
+    #[test]
+    fn test_query_string_generation() {
+        let query = qb_sql!(
+            select display_name, balance from Customer
+            where display_name like "John%"
+            and id in (1, 2, 3)
+            and balance >= 1000.0
+            order by display_name asc, balance desc
+            limit 10 offset 5
+        );
+
+        let query_string = query.query_string();
+        let expected = "select DisplayName, Balance from Customer where DisplayName LIKE 'John%' and Id IN ('1', '2', '3') and Balance >= '1000' ORDERBY DisplayName ASC, Balance DESC STARTPOSITION 6 MAXRESULTS 10";
+        assert_eq!(query_string, expected);
+    }
+
+    #[test]
+    fn test_in_operator() {
+        let query = qb_sql!(
+            select * from Customer
+            where id in (1, 2, 3, 4, 5)
+        );
+
+        assert_eq!(query.condition.len(), 1);
+        assert_eq!(query.condition[0].field, "Id");
+        assert_eq!(query.condition[0].operator, Operator::In);
+        assert_eq!(query.condition[0].values.len(), 5);
+
+        let query_string = query.query_string();
+        assert_eq!(
+            query_string,
+            "select * from Customer where Id IN ('1', '2', '3', '4', '5')"
+        );
+    }
+
+    #[test]
+    fn test_in_operator_with_strings() {
+        let title1 = "Mr";
+        let title2 = "Mrs";
+        let query = qb_sql!(
+            select display_name from Customer
+            where title in (title1, title2, "Dr")
+        );
+
+        assert_eq!(query.condition.len(), 1);
+        assert_eq!(query.condition[0].values.len(), 3);
+
+        let query_string = query.query_string();
+        assert_eq!(
+            query_string,
+            "select DisplayName from Customer where Title IN ('Mr', 'Mrs', 'Dr')"
+        );
+    }
+
+    #[test]
+    fn test_in_iterator() {
+        let ids = vec![1, 2, 3, 4, 5];
+        let query = qb_sql!(
+            select * from Customer
+            where id in (ids)
+        );
+
+        assert_eq!(query.condition.len(), 1);
+        assert_eq!(query.condition[0].field, "Id");
+        assert_eq!(query.condition[0].operator, Operator::In);
+        assert_eq!(query.condition[0].values.len(), 5);
+
+        let query_string = query.query_string();
+        assert_eq!(
+            query_string,
+            "select * from Customer where Id IN ('1', '2', '3', '4', '5')"
+        );
+    }
+
+    #[test]
+    fn test_grouped_conditions() {
+        let query = qb_sql!(
+            select * from Customer
+            where (display_name like "John%" and balance >= 1000.0)
+            and id in (1, 2)
+        );
+
+        assert_eq!(query.condition.len(), 3);
+        assert_eq!(*query.groups, vec![0..2]);
+        assert_eq!(
+            query.query_string(),
+            "select * from Customer where DisplayName LIKE 'John%' and Balance >= '1000' and Id IN ('1', '2')"
+        );
+        assert_eq!(
+            query.dialect(Dialect::Standard).query_string(),
+            "select * from Customer where (DisplayName LIKE 'John%' and Balance >= '1000') and Id IN ('1', '2')"
+        );
+    }
+
+    #[test]
+    fn test_nested_groups() {
+        let query = qb_sql!(
+            select * from Customer
+            where title = "Mr"
+            and ((display_name like "John%" and balance >= 1000.0) and id = 1)
+        );
+
+        assert_eq!(query.condition.len(), 4);
+        assert_eq!(*query.groups, vec![1..3, 1..4]);
+        assert_eq!(
+            query.query_string(),
+            "select * from Customer where Title = 'Mr' and DisplayName LIKE 'John%' and Balance >= '1000' and Id = '1'"
+        );
+        assert_eq!(
+            query.dialect(Dialect::Standard).query_string(),
+            "select * from Customer where Title = 'Mr' and ((DisplayName LIKE 'John%' and Balance >= '1000') and Id = '1')"
+        );
+    }
+
+    #[test]
+    fn test_flattening_keeps_or_groups() {
+        let query = qb_sql!(
+            select * from Customer
+            where ((title = "Mr" or title = "Mrs") and balance > 0)
+            and id = 1
+        );
+
+        assert_eq!(
+            query.query_string(),
+            "select * from Customer where (Title = 'Mr' or Title = 'Mrs') and Balance > '0' and Id = '1'"
+        );
+        assert_eq!(
+            query.dialect(Dialect::Standard).query_string(),
+            "select * from Customer where ((Title = 'Mr' or Title = 'Mrs') and Balance > '0') and Id = '1'"
+        );
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_group_out_of_range() {
+        let _ = Query::<Customer>::new().group(0..1);
+    }
+
+    #[test]
+    fn test_in_iterator_evaluated_once() {
+        use std::sync::atomic::{AtomicUsize, Ordering};
+
+        let evaluated = Arc::new(AtomicUsize::new(0));
+        let counter = Arc::clone(&evaluated);
+        let ids = (1..=3).inspect(move |_| {
+            counter.fetch_add(1, Ordering::SeqCst);
+        });
+        let query = Query::<Customer>::new().with_condition(WhereClause::in_iter("Id", ids));
+        let copy = query.clone();
+
+        // Nothing is rendered until the query is
+        assert_eq!(evaluated.load(Ordering::SeqCst), 0);
+        assert_eq!(
+            query.query_string(),
+            "select * from Customer where Id IN ('1', '2', '3')"
+        );
+        assert_eq!(query.query_string(), query.to_string());
+        assert_eq!(copy.query_string(), query.query_string());
+        assert!(copy.validate().is_empty());
+        assert_eq!(evaluated.load(Ordering::SeqCst), 3);
+    }
+
+    #[test]
+    fn test_in_borrowed_iterator() {
+        let ids = vec![1, 2, 3];
+        let slice = ids.as_slice();
+        let query = qb_sql!(
+            select * from Customer
+            where id in (ids.iter()) or id in (&ids) or id not in (slice.iter().copied())
+        );
+
+        assert_eq!(
+            query.query_string(),
+            "select * from Customer where Id IN ('1', '2', '3') or Id IN ('1', '2', '3') \
+             or Id NOT IN ('1', '2', '3')"
+        );
+        drop(ids);
+    }
+
+    #[test]
+    fn test_not_condition() {
+        let query = qb_sql!(
+            select * from Customer
+            where not display_name like "test%"
+            and balance >= 1000.0
+        );
+
+        assert!(query.condition[0].negated);
+        assert!(!query.condition[1].negated);
+        assert_eq!(
+            query.query_string(),
+            "select * from Customer where NOT DisplayName LIKE 'test%' and Balance >= '1000'"
+        );
+    }
+
+    #[test]
+    fn test_not_in() {
+        let ids = vec![4, 5];
+        let query = qb_sql!(
+            select * from Customer
+            where not id in (1, 2, 3)
+            and not id in (ids)
+        );
+
+        assert_eq!(
+            query.query_string(),
+            "select * from Customer where NOT Id IN ('1', '2', '3') and NOT Id IN ('4', '5')"
+        );
+    }
+
+    #[test]
+    fn test_or_conditions() {
+        let query = qb_sql!(
+            select * from Customer
+            where title = "Mr"
+            or title = "Mrs"
+            and balance >= 1000.0
+        );
+
+        assert_eq!(query.condition[1].join, Connective::Or);
+        assert_eq!(query.condition[2].join, Connective::And);
+        assert_eq!(
+            query.query_string(),
+            "select * from Customer where Title = 'Mr' or Title = 'Mrs' and Balance >= '1000'"
+        );
+    }
+
+    #[test]
+    fn test_or_in_groups() {
+        let query = qb_sql!(
+            select * from Customer
+            where (title = "Mr" or title = "Mrs")
+            and (balance >= 1000.0 or id in (1, 2))
+            or display_name like "John%"
+        );
+
+        assert_eq!(query.condition[2].join, Connective::And);
+        assert_eq!(
+            query.query_string(),
+            "select * from Customer where (Title = 'Mr' or Title = 'Mrs') and (Balance >= '1000' or Id IN ('1', '2')) or DisplayName LIKE 'John%'"
+        );
+    }
+
+    #[test]
+    fn test_group_precedence() {
+        let ungrouped = qb_sql!(
+            select * from Customer
+            where title = "Mr" or title = "Mrs" and balance > 0
+        );
+        let grouped = qb_sql!(
+            select * from Customer
+            where (title = "Mr" or title = "Mrs") and balance > 0
+        );
+        assert_eq!(
+            ungrouped.query_string(),
+            "select * from Customer where Title = 'Mr' or Title = 'Mrs' and Balance > '0'"
+        );
+        assert_eq!(
+            grouped.query_string(),
+            "select * from Customer where (Title = 'Mr' or Title = 'Mrs') and Balance > '0'"
+        );
+
+        let query = qb_sql!(
+            select * from Customer
+            where (active = true and (title = "Mr" or (balance > 0 and id = 1)))
+            or display_name = "Acme"
+        );
+        assert_eq!(*query.groups, vec![2..4, 1..4, 0..4]);
+        assert_eq!(
+            query.query_string(),
+            "select * from Customer where Active = 'true' and (Title = 'Mr' or Balance > '0' and Id = '1') \
+             or DisplayName = 'Acme'"
+        );
+        assert_eq!(
+            query.dialect(Dialect::Standard).query_string(),
+            "select * from Customer where (Active = 'true' and (Title = 'Mr' or (Balance > '0' and Id = '1'))) \
+             or DisplayName = 'Acme'"
+        );
+    }
+
+    #[test]
+    fn test_true_base() {
+        let empty = Query::<Customer>::new().with_true_base();
+        assert_eq!(
+            empty.clone().dialect(Dialect::Standard).query_string(),
+            "select * from Customer where 1=1"
+        );
+        assert_eq!(
+            empty.clone().dialect(Dialect::Postgres).query_string(),
+            "select * from \"Customer\" where 1=1"
+        );
+        assert_eq!(empty.query_string(), "select * from Customer");
+
+        let query =
+            qb_sql!(select * from Customer where active = true or balance > 0).with_true_base();
+        assert_eq!(
+            query.clone().dialect(Dialect::Standard).query_string(),
+            "select * from Customer where 1=1 and Active = 'true' or Balance > '0'"
+        );
+        assert_eq!(
+            query.clone().dialect(Dialect::Postgres).query_string(),
+            "select * from \"Customer\" where 1=1 and \"Active\" = 'true' or \"Balance\" > '0'"
+        );
+        assert_eq!(
+            query.query_string(),
+            "select * from Customer where Active = 'true' or Balance > '0'"
+        );
+    }
+
+    #[test]
+    fn test_not_equal() {
+        let query = qb_sql!(
+            select * from Customer
+            where display_name != "John"
+            and balance != 0
+        );
+
+        assert_eq!(query.condition[0].operator, Operator::NotEqual);
+        assert_eq!(
+            query.query_string(),
+            "select * from Customer where DisplayName != 'John' and Balance != '0'"
+        );
+    }
+
+    #[test]
+    fn test_and_where_raw() {
+        let mut query = qb_sql!(
+            select * from Customer
+            where balance >= 1000.0
+        );
+        unsafe {
+            query.and_where_raw("DisplayName", Operator::Equal, r"'O\'Brien'");
+        }
+
+        assert!(query.condition[1].raw);
+        assert_eq!(
+            query.query_string(),
+            r"select * from Customer where Balance >= '1000' and DisplayName = 'O\'Brien'"
+        );
+    }
+
+    #[test]
+    fn test_not_equal_alternate_syntax() {
+        let query = qb_sql!(
+            select * from Customer
+            where active != true
+            and display_name <> "John"
+            and balance <= 100
+            and balance < 50
+        );
+
+        assert_eq!(query.condition[1].operator, Operator::NotEqual);
+        assert_eq!(query.condition[2].operator, Operator::LessEqual);
+        assert_eq!(query.condition[3].operator, Operator::Less);
+        assert_eq!(
+            query.query_string(),
+            "select * from Customer where Active != 'true' and DisplayName != 'John' and Balance <= '100' and Balance < '50'"
+        );
+    }
+
+    #[test]
+    fn test_null_checks() {
+        let query = qb_sql!(
+            select * from Customer
+            where primary_email_addr is null
+            and title is not null
+            and balance > 0
+        );
+
+        assert_eq!(query.condition[0].operator, Operator::IsNull);
+        assert!(query.condition[0].values.is_empty());
+        assert_eq!(query.condition[1].operator, Operator::IsNotNull);
+        assert_eq!(
+            query.query_string(),
+            "select * from Customer where PrimaryEmailAddr IS NULL and Title IS NOT NULL and Balance > '0'"
+        );
+    }
+
+    #[test]
+    fn test_between() {
+        let low = 100.0;
+        let query = qb_sql!(
+            select * from Customer
+            where balance between low and 500.0
+            and display_name like "John%"
+        );
+
+        assert_eq!(query.condition.len(), 2);
+        assert_eq!(query.condition[0].operator, Operator::Between);
+        assert_eq!(query.condition[0].values, vec!["100", "500"]);
+        assert_eq!(
+            query.query_string(),
+            "select * from Customer where Balance BETWEEN '100' AND '500' and DisplayName LIKE 'John%'"
+        );
+    }
+
+    #[test]
+    fn test_summary() {
+        let ids = vec![1, 2, 3];
+        let query = qb_sql!(
+            select display_name, balance from Customer
+            where display_name like "John%"
+            and (id in (ids) or id in (7, 8))
+            order by display_name asc
+            limit 10
+        );
+
+        assert_eq!(
+            query.summary(),
+            QuerySummary {
+                num_fields: 2,
+                num_conditions: 3,
+                num_in_values: 5,
+                num_groups: 1,
+                has_like: true,
+                has_or: true,
+                has_order: true,
+                is_paginated: true,
+            }
+        );
+        assert_eq!(
+            Query::<Customer>::new().summary(),
+            QuerySummary {
+                num_fields: 0,
+                num_conditions: 0,
+                num_in_values: 0,
+                num_groups: 0,
+                has_like: false,
+                has_or: false,
+                has_order: false,
+                is_paginated: false,
+            }
+        );
+    }
+
+    #[test]
+    fn test_query_string_pretty() {
+        let query = qb_sql!(
+            select display_name, balance from Customer
+            where balance > 100 and (title = "Mr" or title = "Dr")
+            group by title
+            order by balance desc, display_name asc
+            maxresults 10 startposition 21
+        );
+        assert_eq!(
+            query.query_string_pretty(),
+            "select DisplayName, Balance\n\
+             from Customer\n\
+             where Balance > '100'\n  \
+             and (Title = 'Mr'\n  \
+             or Title = 'Dr')\n\
+             group by Title\n\
+             ORDERBY Balance DESC, DisplayName ASC\n\
+             STARTPOSITION 21 MAXRESULTS 10"
+        );
+        assert_eq!(
+            query.query_string(),
+            "select DisplayName, Balance from Customer where Balance > '100' \
+             and (Title = 'Mr' or Title = 'Dr') group by Title \
+             ORDERBY Balance DESC, DisplayName ASC STARTPOSITION 21 MAXRESULTS 10"
+        );
+        assert_eq!(
+            Query::<Customer>::new().query_string_pretty(),
+            "select *\nfrom Customer"
+        );
+    }
+
+    #[test]
+    fn test_immutable_modifiers() {
+        let base = qb_sql!(select * from Customer where balance > 0 order by display_name asc);
+
+        let first = base.with_limit(50, None);
+        let second = base.with_limit(50, Some(50));
+        assert_eq!(
+            first.query_string(),
+            "select * from Customer where Balance > '0' ORDERBY DisplayName ASC MAXRESULTS 50"
+        );
+        assert_eq!(
+            second.query_string(),
+            "select * from Customer where Balance > '0' ORDERBY DisplayName ASC \
+             STARTPOSITION 51 MAXRESULTS 50"
+        );
+        assert_eq!(second.without_limit().query_string(), base.query_string());
+
+        let active = base
+            .with_additional_condition(WhereClause::new("Active", Operator::Equal).add_value(true));
+        assert_eq!(
+            active.query_string(),
+            "select * from Customer where Balance > '0' and Active = 'true' ORDERBY DisplayName ASC"
+        );
+        assert_eq!(
+            base.query_string(),
+            "select * from Customer where Balance > '0' ORDERBY DisplayName ASC"
+        );
+    }
+
+    #[test]
+    fn test_pagination_report() {
+        let query = Query::<Customer>::new().limit(25, Some(50));
+        let report = query.pagination_report();
+        assert!(report.contains("MAXRESULTS 25"), "{report}");
+        assert!(report.contains("STARTPOSITION 51"), "{report}");
+
+        let report = Query::<Customer>::new().pagination_report();
+        assert!(report.contains("MAXRESULTS not set"), "{report}");
+        assert!(report.contains("STARTPOSITION 1"), "{report}");
+
+        let report = Query::<Customer>::new()
+            .limit(5000, None)
+            .pagination_report();
+        assert!(report.contains("at most 1000"), "{report}");
+    }
+
+    #[test]
+    fn test_null_style() {
+        let query = qb_sql!(
+            select * from Customer
+            where primary_email_addr = null
+            and title != null
+        );
+
+        assert_eq!(query.condition[0].operator, Operator::IsNull);
+        assert_eq!(query.condition[1].operator, Operator::IsNotNull);
+        assert_eq!(
+            query.query_string(),
+            "select * from Customer where PrimaryEmailAddr IS NULL and Title IS NOT NULL"
+        );
+        assert_eq!(
+            query.null_style(NullStyle::EqualsNull).query_string(),
+            "select * from Customer where PrimaryEmailAddr = NULL and Title != NULL"
+        );
+    }
+
+    #[test]
+    fn test_validate() {
+        let query = qb_sql!(
+            select * from Customer
+            where display_name like "John%"
+            and title is null
+        );
+        assert!(query.validate().is_empty());
+
+        let query = Query::<Customer>::new()
+            .with_condition(WhereClause::in_iter("Id", Vec::<u32>::new()))
+            .with_condition(WhereClause::new("Balance", Operator::Greater));
+        assert_eq!(
+            query.validate(),
+            vec![
+                ValidationError::EmptyInList { field: "Id".into() },
+                ValidationError::MissingValue {
+                    field: "Balance".into()
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_try_query_string() {
+        let ids: Vec<u32> = Vec::new();
+        let query = qb_sql!(select * from Customer where id in (ids));
+        assert_eq!(
+            query.try_query_string(),
+            Err(ValidationError::EmptyInList { field: "Id".into() })
+        );
+        assert_eq!(
+            query.query_string(),
+            "select * from Customer where Id IN ()"
+        );
+
+        let query = Query::<Customer>::new()
+            .with_condition(WhereClause::new("Balance", Operator::Between).add_value(5));
+        assert_eq!(
+            query.try_query_string(),
+            Err(ValidationError::MissingValue {
+                field: "Balance".into()
+            })
+        );
+
+        let query = qb_sql!(select * from Customer where id in (1, 2));
+        assert_eq!(
+            query.try_query_string().unwrap(),
+            "select * from Customer where Id IN ('1', '2')"
+        );
+    }
+
+    #[test]
+    fn test_not_in_operator() {
+        let query = qb_sql!(
+            select * from Customer
+            where id not in (1, 2, 3, 4, 5)
+        );
+
+        assert_eq!(query.condition.len(), 1);
+        assert_eq!(query.condition[0].field, "Id");
+        assert_eq!(query.condition[0].operator, Operator::NotIn);
+        assert_eq!(query.condition[0].values.len(), 5);
+
+        let query_string = query.query_string();
+        assert_eq!(
+            query_string,
+            "select * from Customer where Id NOT IN ('1', '2', '3', '4', '5')"
+        );
+    }
+
+    #[test]
+    fn test_not_in_operator_with_strings() {
+        let title1 = "Mr";
+        let title2 = "Mrs";
+        let query = qb_sql!(
+            select display_name from Customer
+            where title not in (title1, title2, "Dr")
+        );
+
+        assert_eq!(query.condition.len(), 1);
+        assert_eq!(query.condition[0].values.len(), 3);
+
+        let query_string = query.query_string();
+        assert_eq!(
+            query_string,
+            "select DisplayName from Customer where Title NOT IN ('Mr', 'Mrs', 'Dr')"
+        );
+    }
+
+    #[test]
+    fn test_not_in_iterator() {
+        let ids = vec![1, 2, 3];
+        let query = qb_sql!(
+            select * from Customer
+            where id not in (ids)
+        );
+
+        assert_eq!(query.condition[0].operator, Operator::NotIn);
+        assert_eq!(
+            query.query_string(),
+            "select * from Customer where Id NOT IN ('1', '2', '3')"
+        );
+    }
+
+    #[test]
+    fn test_not_like_operator() {
+        let query = qb_sql!(
+            select * from Customer
+            where display_name not like "ARCHIVED%"
+        );
+
+        assert_eq!(query.condition[0].operator, Operator::NotLike);
+        assert!(query.summary().has_like);
+        assert_eq!(
+            query.query_string(),
+            "select * from Customer where DisplayName NOT LIKE 'ARCHIVED%'"
+        );
+    }
+
+    #[test]
+    fn test_not_like_differs_from_negation() {
+        let operator = qb_sql!(select * from Customer where display_name not like "ARCHIVED%");
+        let prefix = qb_sql!(select * from Customer where not display_name like "ARCHIVED%");
+
+        assert!(!operator.condition[0].negated);
+        assert_eq!(prefix.condition[0].operator, Operator::Like);
+        assert_ne!(operator.query_string(), prefix.query_string());
+        assert_eq!(
+            prefix.query_string(),
+            "select * from Customer where NOT DisplayName LIKE 'ARCHIVED%'"
+        );
+    }
+
+    #[test]
+    fn test_in_values_escaped() {
+        let query = qb_sql!(
+            select * from Customer
+            where display_name in ("Smith, John", "O'Brien", "Plain")
+            and title = "Ma'am"
+        );
+
+        assert_eq!(
+            query.query_string(),
+            r"select * from Customer where DisplayName IN ('Smith, John', 'O\'Brien', 'Plain') and Title = 'Ma\'am'"
+        );
+    }
+
+    #[test]
+    fn test_where_ci() {
+        let query =
+            Query::<Customer>::new().where_ci("PrimaryEmailAddr.Address", "John.Smith@Example.com");
+
+        assert_eq!(query.condition[0].operator, Operator::Like);
+        assert_eq!(
+            query.query_string(),
+            "select * from Customer where PrimaryEmailAddr.Address LIKE 'John.Smith@Example.com'"
+        );
+    }
+
+    #[test]
+    fn test_where_ci_escapes_wildcards() {
+        let query =
+            Query::<Customer>::new().where_ci("PrimaryEmailAddr.Address", "50%_off@example.com");
 
-    /// Add multiple values to the where clause from an iterator
-    pub fn add_values<I, T>(mut self, values: I) -> Self
-    where
-        I: Iterator<Item = T>,
-        T: Display,
-    {
-        self.values.extend(values.map(|v| v.to_string()));
-        self
+        assert_eq!(
+            query.query_string(),
+            r"select * from Customer where PrimaryEmailAddr.Address LIKE '50\%\_off@example.com'"
+        );
     }
-}
 
-impl WhereClause {
-    fn extend_query(&self, query: &mut String) {
-        let op_str = match self.operator {
-            Operator::In => "IN",
-            Operator::Like => "LIKE",
-            Operator::Equal => "=",
-            Operator::Less => "<",
-            Operator::Greater => ">",
-            Operator::LessEqual => "<=",
-            Operator::GreaterEqual => ">=",
-        };
+    #[test]
+    fn test_like_helpers_escape_wildcards() {
+        let query = Query::<Customer>::new()
+            .with_condition(WhereClause::contains("DisplayName", "50%_off"))
+            .with_condition(WhereClause::starts_with("Notes", r"C:\temp"))
+            .with_condition(WhereClause::ends_with(
+                "PrimaryEmailAddr.Address",
+                "@example.com",
+            ));
 
-        if self.operator == Operator::In {
-            query.push_str(&format!(" {} IN (", self.field));
-            for (i, value) in self.values.iter().enumerate() {
-                if i > 0 {
-                    query.push_str(", ");
-                }
-                query.push_str(&format!("'{}'", value));
-            }
-            query.push(')');
-        } else {
-            query.push_str(&format!(" {} {} '{}'", self.field, op_str, self.values[0]));
-        }
+        assert_eq!(query.condition[0].values, vec![r"%50\%\_off%"]);
+        assert_eq!(
+            query.query_string(),
+            r"select * from Customer where DisplayName LIKE '%50\%\_off%' and Notes LIKE 'C:\\temp%' and PrimaryEmailAddr.Address LIKE '%@example.com'"
+        );
     }
-}
 
-/// Enum representing the operators used in where clauses
-#[derive(Debug, PartialEq, Clone)]
-pub enum Operator {
-    In,
-    Like,
-    Equal,
-    Less,
-    Greater,
-    LessEqual,
-    GreaterEqual,
-}
+    #[test]
+    fn test_safe_builder() {
+        let query = Query::<Customer>::new()
+            .select_field("DisplayName")
+            .with_condition(WhereClause::new("Balance", Operator::Greater).add_value(100))
+            .order_by("DisplayName", Order::Desc)
+            .limit(10, None);
 
-#[cfg(test)]
-mod tests {
-    use super::*;
-    use quickbooks_types::Customer;
+        assert_eq!(
+            query.query_string(),
+            qb_sql!(
+                select display_name from Customer
+                where balance > 100
+                order by display_name desc
+                limit 10
+            )
+            .query_string()
+        );
+    }
 
     #[test]
-    fn test_empty_query() {
-        let query = qb_sql!(select * from Customer);
-        assert_eq!(query.fields.len(), 0);
-        assert_eq!(query.condition.len(), 0);
-        assert_eq!(query.order.len(), 0);
-        assert!(query.limit.is_none());
+    fn test_default_and_builder() {
+        assert_eq!(
+            Query::<Customer>::default().query_string(),
+            "select * from Customer"
+        );
+        assert_eq!(
+            Query::<Customer>::builder()
+                .select_field("DisplayName")
+                .query_string(),
+            "select DisplayName from Customer"
+        );
     }
 
     #[test]
-    fn test_basic_query() {
+    fn test_backslashes_escaped() {
         let query = qb_sql!(
             select * from Customer
-            where display_name like "John%"
+            where notes = r"C:\path\to"
+            or display_name in (r"O'Brien\", "Plain")
         );
 
-        assert_eq!(query.condition.len(), 1);
-        assert_eq!(query.condition[0].field, "DisplayName");
+        assert_eq!(
+            query.query_string(),
+            r"select * from Customer where Notes = 'C:\\path\\to' or DisplayName IN ('O\'Brien\\', 'Plain')"
+        );
     }
 
     #[test]
-    fn test_multiple_conditions() {
-        let balance_min = 1000.0;
+    fn test_conditions_for() {
         let query = qb_sql!(
             select * from Customer
-            where display_name like "John%"
-            and balance >= balance_min
+            where balance >= 100
+            and display_name like "A%"
+            and balance < 1000
         );
 
-        assert_eq!(query.condition.len(), 2);
+        let balance = query.conditions_for("Balance");
+        assert_eq!(balance.len(), 2);
+        assert_eq!(balance[0].operator, Operator::GreaterEqual);
+        assert_eq!(balance[1].operator, Operator::Less);
+        assert!(query.conditions_for("Id").is_empty());
     }
 
     #[test]
-    fn test_field_selection() {
+    fn test_count_query() {
         let query = qb_sql!(
-            select display_name, balance from Customer
-            where display_name like "John%"
+            select count from Customer
+            where balance > 0
+            and active = true
         );
 
-        assert_eq!(query.fields.len(), 2);
-        assert_eq!(query.fields[0], "DisplayName");
-        assert_eq!(query.fields[1], "Balance");
+        assert_eq!(query.query().condition.len(), 2);
+        assert_eq!(
+            query.query_string(),
+            "select count(*) from Customer where Balance > '0' and Active = 'true'"
+        );
+        assert_eq!(
+            qb_sql!(select count from Customer).query_string(),
+            "select count(*) from Customer"
+        );
     }
 
     #[test]
-    fn test_order_by() {
+    fn test_group_by() {
         let query = qb_sql!(
-            select * from Customer
-            where display_name like "John%"
-            order by display_name asc, balance desc
+            select count from Customer
+            where balance > 0
+            group by active, meta_data.create_time
+        );
+        assert_eq!(
+            query.query_string(),
+            "select count(*) from Customer where Balance > '0' group by Active, MetaData.CreateTime"
         );
 
-        assert_eq!(query.order.len(), 2);
-        assert_eq!(query.order[0].field, "DisplayName");
-        assert_eq!(query.order[0].order, Order::Asc);
+        let query = Query::<Customer>::new()
+            .select_field("Active")
+            .group_by("Active")
+            .order_by("Active", Order::Asc)
+            .limit(10, None);
+        assert_eq!(
+            query.query_string(),
+            "select Active from Customer group by Active ORDERBY Active ASC MAXRESULTS 10"
+        );
+        assert_eq!(
+            query
+                .merge(Query::new().group_by("Active").group_by("Title"))
+                .query_string(),
+            "select Active from Customer group by Active, Title ORDERBY Active ASC MAXRESULTS 10"
+        );
     }
 
     #[test]
-    fn test_limit_and_offset() {
-        let offset_val = 5;
+    fn test_aggregate_fields() {
+        let query = qb_sql!(select count(id) from Customer group by r#type?);
+        assert_eq!(query.fields()[0].aggregate(), Some(Aggregate::Count));
+        assert_eq!(
+            query.query_string(),
+            "select count(Id) from Customer group by Type"
+        );
+
         let query = qb_sql!(
+            select active, COUNT(id), max(balance) as largest, min(balance), sum(balance)
+            from Customer
+            group by active
+        );
+        assert_eq!(
+            query.query_string(),
+            "select Active, count(Id), max(Balance), min(Balance), sum(Balance) \
+             from Customer group by Active"
+        );
+        assert_eq!(
+            query.dialect(Dialect::Standard).query_string(),
+            "select Active, count(Id), max(Balance) AS largest, min(Balance), sum(Balance) \
+             from Customer group by Active"
+        );
+
+        let query = Query::<Customer>::new()
+            .select_field("Active")
+            .select_aggregate(Aggregate::Sum, "Balance")
+            .group_by("Active");
+        assert_eq!(
+            query.query_string(),
+            "select Active, sum(Balance) from Customer group by Active"
+        );
+    }
+
+    #[test]
+    fn test_map_values() {
+        let base = qb_sql!(
             select * from Customer
-            where display_name like "John%"
-            limit 10 offset offset_val
+            where display_name = "1037" and id in (1, 2) and balance > 0
         );
 
-        assert!(query.limit.is_some());
-        let limit = query.limit.unwrap();
-        assert_eq!(limit.number, 10);
-        assert_eq!(limit.offset, Some(5));
+        let query = base
+            .clone()
+            .map_values("DisplayName", |value| format!("ACME-{value}"))
+            .unwrap()
+            .map_values("Id", |value| value.parse::<u32>().unwrap() + 100)
+            .unwrap();
+        assert_eq!(
+            query.query_string(),
+            "select * from Customer where DisplayName = 'ACME-1037' and Id IN ('101', '102') and Balance > '0'"
+        );
+        // The original query shares its conditions and is unchanged
+        assert_eq!(
+            base.query_string(),
+            "select * from Customer where DisplayName = '1037' and Id IN ('1', '2') and Balance > '0'"
+        );
+
+        assert_eq!(
+            base.map_values("Title", |value| value.to_string()).err(),
+            Some(ValidationError::NoCondition {
+                field: Cow::Borrowed("Title")
+            })
+        );
     }
 
     #[test]
-    fn test_query_string_generation() {
+    fn test_count_ignores_order_and_limit() {
         let query = qb_sql!(
-            select display_name, balance from Customer
-            where display_name like "John%"
-            and id in (1, 2, 3)
-            and balance >= 1000.0
-            order by display_name asc, balance desc
-            limit 10 offset 5
+            select display_name from Customer
+            where display_name like "A%"
+            order by display_name
+            limit 10
         );
 
-        let query_string = query.query_string();
-        let expected = "select DisplayName, Balance from Customer where DisplayName LIKE 'John%' and Id IN ('1', '2', '3') and Balance >= '1000' order by DisplayName ASC, Balance DESC LIMIT 10 OFFSET 5";
-        assert_eq!(query_string, expected);
+        assert_eq!(
+            query.count_query_string(),
+            "select count(*) from Customer where DisplayName LIKE 'A%'"
+        );
+        assert_eq!(
+            query.into_count().query_string(),
+            "select count(*) from Customer where DisplayName LIKE 'A%'"
+        );
     }
 
     #[test]
-    fn test_in_operator() {
+    fn test_distinct() {
         let query = qb_sql!(
-            select * from Customer
-            where id in (1, 2, 3, 4, 5)
+            select distinct display_name, balance from Customer
+            where balance > 0
         );
 
-        assert_eq!(query.condition.len(), 1);
-        assert_eq!(query.condition[0].field, "Id");
-        assert_eq!(query.condition[0].operator, Operator::In);
-        assert_eq!(query.condition[0].values.len(), 5);
+        assert!(query.distinct);
+        assert_eq!(
+            query.query_string(),
+            "select distinct DisplayName, Balance from Customer where Balance > '0'"
+        );
+        assert!(!qb_sql!(select display_name from Customer).distinct);
 
-        let query_string = query.query_string();
+        let query = qb_sql!(select distinct display_name from Customer);
         assert_eq!(
-            query_string,
-            "select * from Customer where Id IN ('1', '2', '3', '4', '5')"
+            query.query_string(),
+            "select distinct DisplayName from Customer"
+        );
+
+        let query = qb_sql!(select distinct * from Customer where balance > 0);
+        assert_eq!(
+            query.query_string(),
+            "select distinct * from Customer where Balance > '0'"
+        );
+        assert_eq!(
+            Query::<Customer>::from_sql_str(&query.query_string()).unwrap(),
+            query
         );
     }
 
     #[test]
-    fn test_in_operator_with_strings() {
-        let title1 = "Mr";
-        let title2 = "Mrs";
+    fn test_create_time_lint() {
+        let created_after = || {
+            Query::<Customer>::new().with_condition(
+                WhereClause::new("MetaData.CreateTime", Operator::Greater).add_value("2024-01-01"),
+            )
+        };
+
+        assert!(created_after().lints().is_empty());
+        assert_eq!(
+            created_after().mark_incremental().lints(),
+            vec![Lint::CreateTimeInSync]
+        );
+        assert_eq!(
+            created_after().limit(100, None).lints(),
+            vec![Lint::CreateTimeInSync]
+        );
+        assert!(
+            created_after()
+                .mark_incremental()
+                .allow_lint(Lint::CreateTimeInSync)
+                .lints()
+                .is_empty()
+        );
+        assert_eq!(Lint::CreateTimeInSync.code(), "create_time_in_sync");
+    }
+
+    #[test]
+    fn test_last_updated_time_sync_has_no_lints() {
+        let query = Query::<Customer>::new()
+            .with_condition(
+                WhereClause::new("MetaData.LastUpdatedTime", Operator::Greater)
+                    .add_value("2024-01-01"),
+            )
+            .mark_incremental();
+
+        assert!(query.lints().is_empty());
+    }
+
+    #[test]
+    fn test_field_aliases() {
         let query = qb_sql!(
-            select display_name from Customer
-            where title in (title1, title2, "Dr")
+            select display_name as name, balance from Customer
+            where balance > 0
         );
 
-        assert_eq!(query.condition.len(), 1);
-        assert_eq!(query.condition[0].values.len(), 3);
+        assert_eq!(query.fields[0].alias.as_deref(), Some("name"));
+        assert_eq!(
+            query.query_string(),
+            "select DisplayName, Balance from Customer where Balance > '0'"
+        );
 
-        let query_string = query.query_string();
+        let query =
+            qb_sql!(select display_name as name, balance from Customer).dialect(Dialect::Standard);
         assert_eq!(
-            query_string,
-            "select DisplayName from Customer where Title IN ('Mr', 'Mrs', 'Dr')"
+            query.query_string(),
+            "select DisplayName AS name, Balance from Customer"
         );
     }
 
     #[test]
-    fn test_in_iterator() {
-        let ids = vec![1, 2, 3, 4, 5];
+    fn test_postgres_dialect() {
         let query = qb_sql!(
-            select * from Customer
-            where id in (ids)
+            select display_name, meta_data.create_time as created from Customer
+            where (display_name like "O'B%" or balance > 100) and bill_addr.city = "C:\\Temp"
+            order by meta_data.create_time desc
+            limit 10 offset 20
+        )
+        .dialect(Dialect::Postgres);
+
+        assert_eq!(
+            query.query_string(),
+            "select \"DisplayName\", \"MetaData\".\"CreateTime\" AS \"created\" from \"Customer\" \
+             where (\"DisplayName\" ILIKE 'O''B%' or \"Balance\" > '100') \
+             and \"BillAddr\".\"City\" = 'C:\\Temp' \
+             order by \"MetaData\".\"CreateTime\" DESC LIMIT 10 OFFSET 20"
+        );
+        assert_eq!(
+            query.count_query_string(),
+            "select count(*) from \"Customer\" \
+             where (\"DisplayName\" ILIKE 'O''B%' or \"Balance\" > '100') \
+             and \"BillAddr\".\"City\" = 'C:\\Temp'"
+        );
+    }
+
+    #[test]
+    fn test_runtime_query() {
+        // Field names and operators as a reporting UI might pick them at runtime
+        let selected = vec![String::from("DisplayName"), String::from("Balance")];
+        let filters = vec![
+            (
+                String::from("Balance"),
+                Operator::GreaterEqual,
+                String::from("100"),
+            ),
+            (
+                String::from("Active"),
+                Operator::Equal,
+                String::from("true"),
+            ),
+        ];
+        let sort = String::from("Balance");
+
+        let mut query = Query::<Customer>::new();
+        for field in selected {
+            query = query.select_field(field);
+        }
+        for (field, operator, value) in filters {
+            query = query
+                .try_with_condition(WhereClause::new(field, operator).add_value(value))
+                .unwrap();
+        }
+        let query = query.try_order_by(sort, Order::Desc).unwrap();
+
+        assert_eq!(
+            query.query_string(),
+            "select DisplayName, Balance from Customer where Balance >= '100' and Active = 'true' \
+             ORDERBY Balance DESC"
+        );
+    }
+
+    #[test]
+    fn test_try_rejects_unknown_fields() {
+        let field = String::from("Balanse");
+        let err = Query::<Customer>::new()
+            .try_with_condition(WhereClause::new(field.clone(), Operator::Equal).add_value(1))
+            .unwrap_err();
+        assert_eq!(
+            err,
+            ValidationError::UnknownField {
+                field: Cow::Owned(field.clone())
+            }
+        );
+        assert!(
+            Query::<Customer>::new()
+                .try_order_by(field, Order::Asc)
+                .is_err()
+        );
+    }
+
+    #[test]
+    fn test_build() {
+        let name: Option<&str> = Some("John%");
+        let query = Query::<Customer>::build(|q| {
+            q.select("DisplayName").select("Balance");
+            if let Some(name) = name {
+                q.filter(WhereClause::new("DisplayName", Operator::Like).add_value(name));
+            }
+            q.order_by("Balance", Order::Desc).limit(10, None);
+        });
+
+        assert_eq!(
+            query.query_string(),
+            "select DisplayName, Balance from Customer where DisplayName LIKE 'John%' ORDERBY Balance DESC MAXRESULTS 10"
+        );
+        assert_eq!(
+            Query::<Customer>::build(|_| {}).query_string(),
+            "select * from Customer"
+        );
+    }
+
+    #[test]
+    fn test_maxresults_startposition() {
+        let query = qb_sql!(select * from Customer maxresults 100 startposition 5);
+        assert_eq!(
+            query.limit,
+            Some(Limit {
+                number: 100,
+                offset: Some(4),
+            })
+        );
+
+        let start = 11;
+        let query = qb_sql!(select * from Customer startposition start maxresults 10);
+        assert_eq!(
+            query.limit,
+            Some(Limit {
+                number: 10,
+                offset: Some(10),
+            })
+        );
+
+        let query = qb_sql!(select * from Customer maxresults 10);
+        assert_eq!(
+            query.limit,
+            Some(Limit {
+                number: 10,
+                offset: None,
+            })
+        );
+    }
+
+    #[test]
+    fn test_clone_shares_until_modified() {
+        let query = qb_sql!(
+            select display_name from Customer
+            where balance > 0
+            order by display_name
         );
+        let clone = query.clone();
+        assert!(Arc::ptr_eq(&query.condition, &clone.condition));
+
+        let modified = clone
+            .with_condition(WhereClause::new("Active", Operator::Equal).add_value(true))
+            .select_field("Balance");
 
         assert_eq!(query.condition.len(), 1);
-        assert_eq!(query.condition[0].field, "Id");
-        assert_eq!(query.condition[0].operator, Operator::In);
-        assert_eq!(query.condition[0].values.len(), 5);
+        assert_eq!(query.fields.len(), 1);
+        assert_eq!(modified.condition.len(), 2);
+        assert_eq!(modified.fields.len(), 2);
+        assert!(Arc::ptr_eq(&query.order, &modified.order));
+        assert_eq!(
+            query.query_string(),
+            "select DisplayName from Customer where Balance > '0' ORDERBY DisplayName ASC"
+        );
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_serde_round_trip() {
+        let ids = vec![1, 2];
+        let query = qb_sql!(
+            select display_name as name, balance from Customer
+            where (balance > 100 or not display_name like "A%")
+            and id in (ids)
+            and title is null
+            order by display_name desc
+            limit 10 offset 5
+        )
+        .dialect(Dialect::Standard)
+        .mark_incremental();
+
+        let json = serde_json::to_string(&query).unwrap();
+        let restored: Query<Customer> = serde_json::from_str(&json).unwrap();
+
+        assert_eq!(restored.query_string(), query.query_string());
+        assert_eq!(restored.dialect, Dialect::Standard);
+        assert!(restored.incremental);
+    }
+
+    #[test]
+    fn test_normalize_times_to() {
+        let query = Query::<Customer>::new()
+            .with_condition(
+                WhereClause::new("MetaData.LastUpdatedTime", Operator::GreaterEqual)
+                    .add_value("2024-01-01T03:00:00Z"),
+            )
+            .with_condition(
+                WhereClause::new("MetaData.CreateTime", Operator::Between)
+                    .add_value("2024-01-01")
+                    .add_value("2024-02-01T00:00:00Z"),
+            )
+            .normalize_times_to(UtcOffset::hours(-8));
 
-        let query_string = query.query_string();
         assert_eq!(
-            query_string,
-            "select * from Customer where Id IN ('1', '2', '3', '4', '5')"
+            query.query_string(),
+            "select * from Customer where MetaData.LastUpdatedTime >= '2023-12-31T19:00:00-08:00' \
+             and MetaData.CreateTime BETWEEN '2024-01-01' AND '2024-01-31T16:00:00-08:00'"
         );
     }
 }
@@ -0,0 +1,36 @@
+use std::fmt;
+
+/// A warning about a query that is valid but likely not what was intended
+///
+/// Lints are reported by [`Query::lints`](crate::Query::lints) and never stop a
+/// query from running. Each lint can be allowed on a query with
+/// [`Query::allow_lint`](crate::Query::allow_lint).
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Lint {
+    /// A paginated or incremental query filters on `MetaData.CreateTime`, so it
+    /// misses edits to records created before the cutoff
+    CreateTimeInSync,
+}
+
+impl Lint {
+    /// A stable identifier for the lint, suitable for allow-lists
+    pub fn code(self) -> &'static str {
+        match self {
+            Lint::CreateTimeInSync => "create_time_in_sync",
+        }
+    }
+}
+
+impl fmt::Display for Lint {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Lint::CreateTimeInSync => write!(
+                f,
+                "[{}] sync query filters on `MetaData.CreateTime`; \
+                 use `MetaData.LastUpdatedTime` to also pick up edited records",
+                self.code()
+            ),
+        }
+    }
+}
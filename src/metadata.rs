@@ -0,0 +1,510 @@
+use crate::Operator;
+
+/// Queryable metadata about a QuickBooks entity
+#[derive(Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct EntityMeta {
+    /// The entity name as used in `select * from <name>`
+    pub name: &'static str,
+    /// The fields that can be used in `where` and `order by` clauses
+    pub fields: &'static [FieldMeta],
+    /// Whether the company has exactly one instance of the entity
+    pub singleton: bool,
+    /// The minimum API minor version the entity requires, if any
+    pub min_minor_version: Option<u32>,
+}
+
+impl EntityMeta {
+    /// Look up a queryable field by its QuickBooks name
+    pub fn field(&self, name: &str) -> Option<&FieldMeta> {
+        self.fields.iter().find(|field| field.name == name)
+    }
+}
+
+/// Metadata about a single queryable field
+#[derive(Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct FieldMeta {
+    /// The field name, using dots for nested paths such as `MetaData.CreateTime`
+    pub name: &'static str,
+    pub field_type: FieldType,
+    /// Whether the field can appear in an `order by` clause
+    pub sortable: bool,
+}
+
+/// The value type of a queryable field, which determines the operators it supports
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub enum FieldType {
+    Id,
+    String,
+    Number,
+    Boolean,
+    DateTime,
+    /// A string field that only accepts the listed values, matched case-sensitively
+    Enum(&'static [&'static str]),
+}
+
+impl FieldType {
+    /// The operators that can be applied to fields of this type
+    pub fn allowed_operators(self) -> &'static [Operator] {
+        match self {
+            FieldType::Id => &[
+                Operator::Equal,
+                Operator::NotEqual,
+                Operator::In,
+                Operator::NotIn,
+            ],
+            FieldType::String => &[
+                Operator::Equal,
+                Operator::NotEqual,
+                Operator::In,
+                Operator::NotIn,
+                Operator::Like,
+                Operator::NotLike,
+            ],
+            FieldType::Number | FieldType::DateTime => &[
+                Operator::Equal,
+                Operator::NotEqual,
+                Operator::Less,
+                Operator::Greater,
+                Operator::LessEqual,
+                Operator::GreaterEqual,
+                Operator::In,
+                Operator::NotIn,
+                Operator::Between,
+            ],
+            FieldType::Boolean => &[Operator::Equal, Operator::NotEqual],
+            FieldType::Enum(_) => &[
+                Operator::Equal,
+                Operator::NotEqual,
+                Operator::In,
+                Operator::NotIn,
+            ],
+        }
+    }
+
+    /// The values a field of this type accepts, if they're restricted
+    pub fn allowed_values(self) -> Option<&'static [&'static str]> {
+        match self {
+            FieldType::Enum(values) => Some(values),
+            _ => None,
+        }
+    }
+}
+
+/// Look up the metadata for an entity by name, e.g. `"Customer"`
+pub fn entity_metadata(name: &str) -> Option<&'static EntityMeta> {
+    ENTITIES.iter().find(|entity| entity.name == name)
+}
+
+/// Iterate over the metadata of every known entity
+pub fn all_entities() -> impl Iterator<Item = &'static EntityMeta> {
+    ENTITIES.iter()
+}
+
+/// Whether `entity` has a queryable field named `field`, usable in const contexts
+pub(crate) const fn has_field(entity: &str, field: &str) -> bool {
+    let mut i = 0;
+    while i < ENTITIES.len() {
+        if str_eq(ENTITIES[i].name, entity) {
+            let fields = ENTITIES[i].fields;
+            let mut j = 0;
+            while j < fields.len() {
+                if str_eq(fields[j].name, field) {
+                    return true;
+                }
+                j += 1;
+            }
+        }
+        i += 1;
+    }
+    false
+}
+
+/// The queryable field of `entity` closest to `field`, if `field` isn't one itself
+///
+/// Only fields within a couple of edits of `field`, ignoring case, and fewer
+/// edits than a third of its length are considered, so unrelated fields that
+/// just aren't in the metadata, like `Id2`, aren't mistaken for typos.
+pub(crate) const fn closest_field(entity: &str, field: &str) -> Option<&'static str> {
+    if has_field(entity, field) {
+        return None;
+    }
+    let mut closest = None;
+    let mut best = usize::MAX;
+    let mut i = 0;
+    while i < ENTITIES.len() {
+        if str_eq(ENTITIES[i].name, entity) {
+            let fields = ENTITIES[i].fields;
+            let mut j = 0;
+            while j < fields.len() {
+                let distance = edit_distance(fields[j].name, field);
+                if distance < best {
+                    best = distance;
+                    closest = Some(fields[j].name);
+                }
+                j += 1;
+            }
+        }
+        i += 1;
+    }
+    if best <= 2 && best * 3 < field.len() {
+        closest
+    } else {
+        None
+    }
+}
+
+/// The longest name [`edit_distance`] compares
+const MAX_NAME_LEN: usize = 64;
+
+/// The Levenshtein distance between `a` and `b`, ignoring ASCII case
+const fn edit_distance(a: &str, b: &str) -> usize {
+    let (a, b) = (a.as_bytes(), b.as_bytes());
+    if a.len() >= MAX_NAME_LEN || b.len() >= MAX_NAME_LEN {
+        return usize::MAX;
+    }
+    let mut previous = [0; MAX_NAME_LEN];
+    let mut current = [0; MAX_NAME_LEN];
+    let mut j = 0;
+    while j <= b.len() {
+        previous[j] = j;
+        j += 1;
+    }
+    let mut i = 0;
+    while i < a.len() {
+        current[0] = i + 1;
+        let mut j = 0;
+        while j < b.len() {
+            let substitution = if a[i].eq_ignore_ascii_case(&b[j]) {
+                previous[j]
+            } else {
+                previous[j] + 1
+            };
+            let mut distance = substitution;
+            if previous[j + 1] + 1 < distance {
+                distance = previous[j + 1] + 1;
+            }
+            if current[j] + 1 < distance {
+                distance = current[j] + 1;
+            }
+            current[j + 1] = distance;
+            j += 1;
+        }
+        previous = current;
+        i += 1;
+    }
+    previous[b.len()]
+}
+
+const fn str_eq(a: &str, b: &str) -> bool {
+    let (a, b) = (a.as_bytes(), b.as_bytes());
+    if a.len() != b.len() {
+        return false;
+    }
+    let mut i = 0;
+    while i < a.len() {
+        if a[i] != b[i] {
+            return false;
+        }
+        i += 1;
+    }
+    true
+}
+
+const fn field(name: &'static str, field_type: FieldType, sortable: bool) -> FieldMeta {
+    FieldMeta {
+        name,
+        field_type,
+        sortable,
+    }
+}
+
+const fn entity(name: &'static str, fields: &'static [FieldMeta]) -> EntityMeta {
+    EntityMeta {
+        name,
+        fields,
+        singleton: false,
+        min_minor_version: None,
+    }
+}
+
+const fn singleton(name: &'static str) -> EntityMeta {
+    EntityMeta {
+        name,
+        fields: &[],
+        singleton: true,
+        min_minor_version: None,
+    }
+}
+
+const ID: FieldMeta = field("Id", FieldType::Id, true);
+const CREATE_TIME: FieldMeta = field("MetaData.CreateTime", FieldType::DateTime, true);
+const LAST_UPDATED_TIME: FieldMeta = field("MetaData.LastUpdatedTime", FieldType::DateTime, true);
+// Shared by the sales entities QuickBooks can email or print
+const EMAIL_STATUS: FieldMeta = field(
+    "EmailStatus",
+    FieldType::Enum(&["NotSet", "NeedToSend", "EmailSent"]),
+    false,
+);
+const PRINT_STATUS: FieldMeta = field(
+    "PrintStatus",
+    FieldType::Enum(&["NotSet", "NeedToPrint", "PrintComplete"]),
+    false,
+);
+
+static ENTITIES: &[EntityMeta] = &[
+    entity(
+        "Account",
+        &[
+            ID,
+            field("Name", FieldType::String, true),
+            field("AccountType", FieldType::String, true),
+            field("Classification", FieldType::String, true),
+            field("Active", FieldType::Boolean, false),
+            field("CurrentBalance", FieldType::Number, true),
+            CREATE_TIME,
+            LAST_UPDATED_TIME,
+        ],
+    ),
+    entity(
+        "Bill",
+        &[
+            ID,
+            field("DocNumber", FieldType::String, true),
+            field("TxnDate", FieldType::DateTime, true),
+            field("DueDate", FieldType::DateTime, true),
+            field("VendorRef", FieldType::Id, false),
+            field("TotalAmt", FieldType::Number, true),
+            field("Balance", FieldType::Number, true),
+            CREATE_TIME,
+            LAST_UPDATED_TIME,
+        ],
+    ),
+    singleton("CompanyInfo"),
+    entity(
+        "Customer",
+        &[
+            ID,
+            field("DisplayName", FieldType::String, true),
+            field("GivenName", FieldType::String, true),
+            field("FamilyName", FieldType::String, true),
+            field("CompanyName", FieldType::String, true),
+            field("PrimaryEmailAddr", FieldType::String, false),
+            field("Balance", FieldType::Number, true),
+            field("Active", FieldType::Boolean, false),
+            CREATE_TIME,
+            LAST_UPDATED_TIME,
+        ],
+    ),
+    entity(
+        "Employee",
+        &[
+            ID,
+            field("DisplayName", FieldType::String, true),
+            field("GivenName", FieldType::String, true),
+            field("FamilyName", FieldType::String, true),
+            field("Active", FieldType::Boolean, false),
+            CREATE_TIME,
+            LAST_UPDATED_TIME,
+        ],
+    ),
+    entity(
+        "Estimate",
+        &[
+            ID,
+            field("DocNumber", FieldType::String, true),
+            field("TxnDate", FieldType::DateTime, true),
+            field("ExpirationDate", FieldType::DateTime, true),
+            field("CustomerRef", FieldType::Id, false),
+            field("TotalAmt", FieldType::Number, true),
+            EMAIL_STATUS,
+            PRINT_STATUS,
+            CREATE_TIME,
+            LAST_UPDATED_TIME,
+        ],
+    ),
+    entity(
+        "Invoice",
+        &[
+            ID,
+            field("DocNumber", FieldType::String, true),
+            field("TxnDate", FieldType::DateTime, true),
+            field("DueDate", FieldType::DateTime, true),
+            field("CustomerRef", FieldType::Id, false),
+            field("TotalAmt", FieldType::Number, true),
+            field("Balance", FieldType::Number, true),
+            EMAIL_STATUS,
+            PRINT_STATUS,
+            CREATE_TIME,
+            LAST_UPDATED_TIME,
+        ],
+    ),
+    entity(
+        "Item",
+        &[
+            ID,
+            field("Name", FieldType::String, true),
+            field("Sku", FieldType::String, true),
+            field("Type", FieldType::String, true),
+            field("Active", FieldType::Boolean, false),
+            CREATE_TIME,
+            LAST_UPDATED_TIME,
+        ],
+    ),
+    entity(
+        "JournalEntry",
+        &[
+            ID,
+            field("DocNumber", FieldType::String, true),
+            field("TxnDate", FieldType::DateTime, true),
+            CREATE_TIME,
+            LAST_UPDATED_TIME,
+        ],
+    ),
+    entity(
+        "Payment",
+        &[
+            ID,
+            field("TxnDate", FieldType::DateTime, true),
+            field("CustomerRef", FieldType::Id, false),
+            field("TotalAmt", FieldType::Number, true),
+            CREATE_TIME,
+            LAST_UPDATED_TIME,
+        ],
+    ),
+    singleton("Preferences"),
+    entity(
+        "SalesReceipt",
+        &[
+            ID,
+            field("DocNumber", FieldType::String, true),
+            field("TxnDate", FieldType::DateTime, true),
+            field("CustomerRef", FieldType::Id, false),
+            field("TotalAmt", FieldType::Number, true),
+            EMAIL_STATUS,
+            PRINT_STATUS,
+            CREATE_TIME,
+            LAST_UPDATED_TIME,
+        ],
+    ),
+    entity(
+        "Vendor",
+        &[
+            ID,
+            field("DisplayName", FieldType::String, true),
+            field("GivenName", FieldType::String, true),
+            field("FamilyName", FieldType::String, true),
+            field("CompanyName", FieldType::String, true),
+            field("Balance", FieldType::Number, true),
+            field("Active", FieldType::Boolean, false),
+            CREATE_TIME,
+            LAST_UPDATED_TIME,
+        ],
+    ),
+];
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_customer_metadata() {
+        let customer = entity_metadata("Customer").unwrap();
+        assert!(!customer.singleton);
+
+        let display_name = customer.field("DisplayName").unwrap();
+        assert!(display_name.sortable);
+        assert!(
+            display_name
+                .field_type
+                .allowed_operators()
+                .contains(&Operator::Like)
+        );
+        assert!(customer.field("Nonexistent").is_none());
+    }
+
+    #[test]
+    fn test_journal_entry_lines_are_not_queryable() {
+        let journal_entry = entity_metadata("JournalEntry").unwrap();
+        assert!(journal_entry.field("TxnDate").is_some());
+        assert!(journal_entry.field("DocNumber").is_some());
+        assert!(journal_entry.field("Line").is_none());
+    }
+
+    #[test]
+    fn test_delivery_status_values() {
+        for name in ["Invoice", "Estimate", "SalesReceipt"] {
+            let entity = entity_metadata(name).unwrap();
+            let email = entity.field("EmailStatus").unwrap().field_type;
+            let print = entity.field("PrintStatus").unwrap().field_type;
+            assert_eq!(
+                email.allowed_values(),
+                Some(&["NotSet", "NeedToSend", "EmailSent"][..])
+            );
+            assert_eq!(
+                print.allowed_values(),
+                Some(&["NotSet", "NeedToPrint", "PrintComplete"][..])
+            );
+            assert!(!email.allowed_operators().contains(&Operator::Like));
+        }
+        assert!(!has_field("Customer", "EmailStatus"));
+        assert_eq!(FieldType::String.allowed_values(), None);
+    }
+
+    #[test]
+    fn test_has_field() {
+        const _: () = assert!(has_field("Customer", "Balance"));
+        assert!(!has_field("Customer", "Nonexistent"));
+        assert!(!has_field("Unknown", "Balance"));
+    }
+
+    #[test]
+    fn test_closest_field() {
+        assert_eq!(closest_field("Customer", "DsplayName"), Some("DisplayName"));
+        assert_eq!(
+            closest_field("Customer", "Displayname"),
+            Some("DisplayName")
+        );
+        assert_eq!(
+            closest_field("Invoice", "MetaData.CreateTim"),
+            Some("MetaData.CreateTime")
+        );
+        assert_eq!(closest_field("Customer", "DisplayName"), None);
+        // Fields missing from the metadata that aren't near a known one
+        assert_eq!(closest_field("Customer", "Title"), None);
+        assert_eq!(closest_field("Customer", "Id2"), None);
+        assert_eq!(closest_field("Unknown", "DsplayName"), None);
+        assert_eq!(edit_distance("kitten", "Sitting"), 3);
+    }
+
+    #[test]
+    fn test_singletons() {
+        assert!(entity_metadata("Preferences").unwrap().singleton);
+        assert!(entity_metadata("CompanyInfo").unwrap().singleton);
+        assert!(entity_metadata("Unknown").is_none());
+    }
+
+    #[test]
+    fn test_all_entities_unique() {
+        let mut names: Vec<_> = all_entities().map(|entity| entity.name).collect();
+        let count = names.len();
+        names.sort_unstable();
+        names.dedup();
+        assert_eq!(names.len(), count);
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_serialization_is_stable() {
+        assert_eq!(
+            serde_json::to_string(entity_metadata("Preferences").unwrap()).unwrap(),
+            r#"{"name":"Preferences","fields":[],"singleton":true,"min_minor_version":null}"#
+        );
+        assert_eq!(
+            serde_json::to_string(&ID).unwrap(),
+            r#"{"name":"Id","field_type":"Id","sortable":true}"#
+        );
+    }
+}
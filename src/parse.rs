@@ -0,0 +1,570 @@
+use std::borrow::Cow;
+
+use quickbooks_types::QBItem;
+
+use crate::{Aggregate, Connective, Operator, Order, Query, ValidationError, WhereClause};
+
+impl<QB: QBItem> Query<QB> {
+    /// Parse a query string, such as one pasted from the QuickBooks API explorer
+    ///
+    /// This accepts the QuickBooks query language as rendered by
+    /// [`Query::query_string`]: a `select` of `*` or a list of fields and
+    /// aggregates, conditions joined by `and`/`or` with parenthesized groups,
+    /// `group by`, `ORDERBY` or `order by`, and `MAXRESULTS`/`STARTPOSITION` or
+    /// `limit`/`offset`. Null checks may be written in either
+    /// [`NullStyle`](crate::NullStyle). Keywords and the entity name may be
+    /// written in any case; string literals keep theirs. Field names are taken
+    /// as written.
+    ///
+    /// The entity selected from must be `QB`, and `select count(*)` isn't
+    /// accepted since it isn't a query for entities.
+    pub fn from_sql_str(sql: &str) -> Result<Self, ValidationError> {
+        let mut parser = Parser {
+            tokens: tokenize(sql)?,
+            next: 0,
+            end: sql.len(),
+        };
+        let query = parser.query()?;
+        match parser.peek() {
+            Some(_) => Err(parser.error("expected the end of the query")),
+            None => Ok(query),
+        }
+    }
+}
+
+/// A token of a query string
+#[derive(Debug, PartialEq)]
+enum Token<'a> {
+    /// A keyword, field name or entity name, including dotted paths
+    Word(&'a str),
+    /// The text between the quotes of a string literal, still escaped
+    Str(&'a str),
+    Number(&'a str),
+    Symbol(&'static str),
+}
+
+const SYMBOLS: [&str; 11] = ["!=", "<>", "<=", ">=", "=", "<", ">", "(", ")", ",", "*"];
+
+/// Split `sql` into tokens, each paired with its byte offset
+fn tokenize(sql: &str) -> Result<Vec<(usize, Token<'_>)>, ValidationError> {
+    let mut tokens = Vec::new();
+    let mut rest = sql;
+    while let Some(c) = rest.chars().next() {
+        let position = sql.len() - rest.len();
+        if c.is_whitespace() {
+            rest = &rest[c.len_utf8()..];
+            continue;
+        }
+
+        let len = if c == '\'' {
+            // Quotes inside literals are escaped with a backslash, or doubled
+            let bytes = rest.as_bytes();
+            let mut i = 1;
+            loop {
+                match bytes.get(i) {
+                    Some(b'\\') => i += 2,
+                    Some(b'\'') if bytes.get(i + 1) == Some(&b'\'') => i += 2,
+                    Some(b'\'') => break,
+                    Some(_) => i += 1,
+                    None => {
+                        return Err(ValidationError::Syntax {
+                            position,
+                            message: "unterminated string literal".into(),
+                        });
+                    }
+                }
+            }
+            tokens.push((position, Token::Str(&rest[1..i])));
+            i + 1
+        } else if c.is_ascii_digit()
+            || (c == '-' && rest[1..].starts_with(|c: char| c.is_ascii_digit()))
+        {
+            let len = 1 + rest[1..]
+                .find(|c: char| !c.is_ascii_digit() && c != '.')
+                .unwrap_or(rest.len() - 1);
+            tokens.push((position, Token::Number(&rest[..len])));
+            len
+        } else if c.is_alphanumeric() || c == '_' {
+            let len = rest
+                .find(|c: char| !c.is_alphanumeric() && c != '_' && c != '.')
+                .unwrap_or(rest.len());
+            tokens.push((position, Token::Word(&rest[..len])));
+            len
+        } else if let Some(symbol) = SYMBOLS.iter().find(|symbol| rest.starts_with(**symbol)) {
+            tokens.push((position, Token::Symbol(symbol)));
+            symbol.len()
+        } else {
+            return Err(ValidationError::Syntax {
+                position,
+                message: format!("unexpected character `{c}`").into(),
+            });
+        };
+        rest = &rest[len..];
+    }
+    Ok(tokens)
+}
+
+/// Undo the escaping of a string literal's quotes, and its backslashes unless
+/// it's a `LIKE` pattern, mirroring how values are rendered
+fn unescape(literal: &str, like: bool) -> String {
+    let mut value = String::with_capacity(literal.len());
+    let mut chars = literal.chars().peekable();
+    while let Some(c) = chars.next() {
+        match (c, chars.peek()) {
+            ('\\', Some('\'')) | ('\'', Some('\'')) => {
+                value.push('\'');
+                chars.next();
+            }
+            ('\\', Some('\\')) if !like => {
+                value.push('\\');
+                chars.next();
+            }
+            (c, _) => value.push(c),
+        }
+    }
+    value
+}
+
+struct Parser<'a> {
+    tokens: Vec<(usize, Token<'a>)>,
+    next: usize,
+    /// The length of the query string, where errors at its end are reported
+    end: usize,
+}
+
+impl<'a> Parser<'a> {
+    fn peek(&self) -> Option<&Token<'a>> {
+        self.tokens.get(self.next).map(|(_, token)| token)
+    }
+
+    fn error(&self, message: impl Into<Cow<'static, str>>) -> ValidationError {
+        let position = self
+            .tokens
+            .get(self.next)
+            .map_or(self.end, |(position, _)| *position);
+        ValidationError::Syntax {
+            position,
+            message: message.into(),
+        }
+    }
+
+    /// Whether the next token is `keyword`, in any case
+    fn peek_keyword(&self, keyword: &str) -> bool {
+        self.peek_keyword_at(0, keyword)
+    }
+
+    fn peek_keyword_at(&self, ahead: usize, keyword: &str) -> bool {
+        matches!(
+            self.tokens.get(self.next + ahead),
+            Some((_, Token::Word(word))) if word.eq_ignore_ascii_case(keyword)
+        )
+    }
+
+    /// Consume the next token if it's `keyword`
+    fn eat_keyword(&mut self, keyword: &str) -> bool {
+        let matched = self.peek_keyword(keyword);
+        self.next += usize::from(matched);
+        matched
+    }
+
+    fn expect_keyword(&mut self, keyword: &str) -> Result<(), ValidationError> {
+        match self.eat_keyword(keyword) {
+            true => Ok(()),
+            false => Err(self.error(format!("expected `{keyword}`"))),
+        }
+    }
+
+    /// Consume the next token if it's `symbol`
+    fn eat_symbol(&mut self, symbol: &'static str) -> bool {
+        let matched = self.peek() == Some(&Token::Symbol(symbol));
+        self.next += usize::from(matched);
+        matched
+    }
+
+    fn expect_symbol(&mut self, symbol: &'static str) -> Result<(), ValidationError> {
+        match self.eat_symbol(symbol) {
+            true => Ok(()),
+            false => Err(self.error(format!("expected `{symbol}`"))),
+        }
+    }
+
+    /// Consume a field or entity name
+    fn name(&mut self, what: &str) -> Result<&'a str, ValidationError> {
+        match self.peek() {
+            Some(&Token::Word(word)) => {
+                self.next += 1;
+                Ok(word)
+            }
+            _ => Err(self.error(format!("expected {what}"))),
+        }
+    }
+
+    fn number(&mut self) -> Result<u32, ValidationError> {
+        match self.peek() {
+            Some(&Token::Number(number)) => match number.parse() {
+                Ok(number) => {
+                    self.next += 1;
+                    Ok(number)
+                }
+                Err(_) => Err(self.error(format!("`{number}` is not a valid count"))),
+            },
+            _ => Err(self.error("expected a number")),
+        }
+    }
+
+    /// Consume a condition value; numbers and booleans may be written unquoted
+    fn value(&mut self, like: bool) -> Result<String, ValidationError> {
+        let value = match self.peek() {
+            Some(&Token::Str(literal)) => unescape(literal, like),
+            Some(&Token::Number(number)) => number.to_string(),
+            Some(&Token::Word(word))
+                if word.eq_ignore_ascii_case("true") || word.eq_ignore_ascii_case("false") =>
+            {
+                word.to_ascii_lowercase()
+            }
+            _ => return Err(self.error("expected a value")),
+        };
+        self.next += 1;
+        Ok(value)
+    }
+
+    fn query<QB: QBItem>(&mut self) -> Result<Query<QB>, ValidationError> {
+        let mut query = Query::new();
+
+        self.expect_keyword("select")?;
+        if self.eat_keyword("distinct") {
+            query = query.distinct();
+        }
+        if !self.eat_symbol("*") {
+            loop {
+                query = self.selected_field(query)?;
+                if !self.eat_symbol(",") {
+                    break;
+                }
+            }
+        }
+
+        self.expect_keyword("from")?;
+        let entity = self.name("an entity name")?;
+        if !entity.eq_ignore_ascii_case(QB::name()) {
+            self.next -= 1;
+            return Err(self.error(format!(
+                "the query selects from `{entity}` rather than `{}`",
+                QB::name()
+            )));
+        }
+
+        if self.eat_keyword("where") {
+            query = self.conditions(query, Connective::And)?;
+        }
+
+        if self.eat_keyword("group") {
+            self.expect_keyword("by")?;
+            loop {
+                query = query.group_by(self.name("a field name")?.to_string());
+                if !self.eat_symbol(",") {
+                    break;
+                }
+            }
+        }
+
+        let ordered = if self.eat_keyword("order") {
+            self.expect_keyword("by")?;
+            true
+        } else {
+            self.eat_keyword("orderby")
+        };
+        if ordered {
+            loop {
+                let field = self.name("a field name")?.to_string();
+                let order = if self.eat_keyword("desc") {
+                    Order::Desc
+                } else {
+                    self.eat_keyword("asc");
+                    Order::Asc
+                };
+                query = query.order_by(field, order);
+                if !self.eat_symbol(",") {
+                    break;
+                }
+            }
+        }
+
+        // QuickBooks' STARTPOSITION counts from 1, where `offset` counts from 0
+        let mut number = None;
+        let mut offset = None;
+        loop {
+            if self.eat_keyword("maxresults") || self.eat_keyword("limit") {
+                number = Some(self.number()?);
+            } else if self.eat_keyword("startposition") {
+                match self.number()? {
+                    0 => {
+                        self.next -= 1;
+                        return Err(self.error("STARTPOSITION counts from 1"));
+                    }
+                    position => offset = Some(position - 1),
+                }
+            } else if self.eat_keyword("offset") {
+                offset = Some(self.number()?);
+            } else {
+                break;
+            }
+        }
+        match (number, offset) {
+            (Some(number), offset) => {
+                query = query.limit(number, offset.filter(|&offset| offset > 0));
+            }
+            (None, Some(_)) => {
+                return Err(self.error("a start position requires `MAXRESULTS` or `limit`"));
+            }
+            (None, None) => {}
+        }
+
+        Ok(query)
+    }
+
+    /// Parse a selected field, e.g. `DisplayName`, `count(Id)` or `Balance as total`
+    fn selected_field<QB: QBItem>(
+        &mut self,
+        query: Query<QB>,
+    ) -> Result<Query<QB>, ValidationError> {
+        let aggregate = [
+            ("count", Aggregate::Count),
+            ("max", Aggregate::Max),
+            ("min", Aggregate::Min),
+            ("sum", Aggregate::Sum),
+        ]
+        .into_iter()
+        .find(|(name, _)| {
+            self.peek_keyword(name)
+                && matches!(
+                    self.tokens.get(self.next + 1),
+                    Some((_, Token::Symbol("(")))
+                )
+        });
+
+        let (aggregate, field) = match aggregate {
+            Some((_, aggregate)) => {
+                self.next += 2;
+                if self.peek() == Some(&Token::Symbol("*")) {
+                    return Err(self.error(
+                        "`count(*)` doesn't select entities; use `Query::count_query_string`",
+                    ));
+                }
+                let field = self.name("a field name")?;
+                self.expect_symbol(")")?;
+                (Some(aggregate), field.to_string())
+            }
+            None => (None, self.name("a field name or `*`")?.to_string()),
+        };
+        let alias = if self.eat_keyword("as") {
+            Some(self.name("an alias")?.to_string())
+        } else {
+            None
+        };
+
+        Ok(match (aggregate, alias) {
+            (Some(aggregate), Some(alias)) => query.select_aggregate_as(aggregate, field, alias),
+            (Some(aggregate), None) => query.select_aggregate(aggregate, field),
+            (None, Some(alias)) => query.select_field_as(field, alias),
+            (None, None) => query.select_field(field),
+        })
+    }
+
+    /// Parse a chain of conditions joined by `and`/`or`, grouping parenthesized ones
+    ///
+    /// `join` is the connective preceding the chain, applied to its first condition.
+    fn conditions<QB: QBItem>(
+        &mut self,
+        mut query: Query<QB>,
+        mut join: Connective,
+    ) -> Result<Query<QB>, ValidationError> {
+        loop {
+            if self.eat_symbol("(") {
+                let start = query.condition.len();
+                query = self.conditions(query, join)?;
+                self.expect_symbol(")")?;
+                let end = query.condition.len();
+                query = query.group(start..end);
+            } else {
+                let condition = self.condition()?.joined_by(join);
+                query = query.with_condition(condition);
+            }
+
+            join = if self.eat_keyword("and") {
+                Connective::And
+            } else if self.eat_keyword("or") {
+                Connective::Or
+            } else {
+                return Ok(query);
+            };
+        }
+    }
+
+    /// Parse a single condition, e.g. `Balance > '100'` or `Id IN ('1', '2')`
+    fn condition(&mut self) -> Result<WhereClause, ValidationError> {
+        let negated = self.eat_keyword("not");
+        let field = self.name("a field name")?.to_string();
+
+        let operator = match self.peek() {
+            Some(Token::Symbol("=")) => Operator::Equal,
+            Some(Token::Symbol("!=" | "<>")) => Operator::NotEqual,
+            Some(Token::Symbol("<")) => Operator::Less,
+            Some(Token::Symbol(">")) => Operator::Greater,
+            Some(Token::Symbol("<=")) => Operator::LessEqual,
+            Some(Token::Symbol(">=")) => Operator::GreaterEqual,
+            _ if self.peek_keyword("like") => Operator::Like,
+            _ if self.peek_keyword("in") => Operator::In,
+            _ if self.peek_keyword("between") => Operator::Between,
+            _ if self.peek_keyword("not") && self.peek_keyword_at(1, "like") => {
+                self.next += 1;
+                Operator::NotLike
+            }
+            _ if self.peek_keyword("not") && self.peek_keyword_at(1, "in") => {
+                self.next += 1;
+                Operator::NotIn
+            }
+            _ if self.peek_keyword("is") => {
+                self.next += 1;
+                let operator = match self.eat_keyword("not") {
+                    true => Operator::IsNotNull,
+                    false => Operator::IsNull,
+                };
+                self.expect_keyword("null")?;
+                return Ok(WhereClause {
+                    negated,
+                    ..WhereClause::new(field, operator)
+                });
+            }
+            _ => return Err(self.error("expected an operator")),
+        };
+        self.next += 1;
+
+        // Null checks rendered with `NullStyle::EqualsNull`
+        if matches!(operator, Operator::Equal | Operator::NotEqual) && self.eat_keyword("null") {
+            let operator = match operator {
+                Operator::Equal => Operator::IsNull,
+                _ => Operator::IsNotNull,
+            };
+            return Ok(WhereClause {
+                negated,
+                ..WhereClause::new(field, operator)
+            });
+        }
+
+        let like = matches!(operator, Operator::Like | Operator::NotLike);
+        let mut values = Vec::new();
+        match operator {
+            Operator::In | Operator::NotIn => {
+                self.expect_symbol("(")?;
+                loop {
+                    values.push(self.value(false)?);
+                    if !self.eat_symbol(",") {
+                        break;
+                    }
+                }
+                self.expect_symbol(")")?;
+            }
+            Operator::Between => {
+                values.push(self.value(false)?);
+                self.expect_keyword("and")?;
+                values.push(self.value(false)?);
+            }
+            _ => values.push(self.value(like)?),
+        }
+
+        Ok(WhereClause {
+            values: values.into(),
+            negated,
+            ..WhereClause::new(field, operator)
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{NullStyle, qb_sql};
+    use quickbooks_types::{Customer, Invoice};
+
+    #[test]
+    fn test_from_sql_str_uppercase() {
+        let query = Query::<Customer>::from_sql_str(
+            "SELECT DisplayName, Balance FROM CUSTOMER \
+             WHERE DisplayName LIKE 'ACME%' AND Title = 'Mr' \
+             ORDER BY Balance DESC LIMIT 10 OFFSET 20",
+        )
+        .unwrap();
+        assert_eq!(
+            query.query_string(),
+            "select DisplayName, Balance from Customer \
+             where DisplayName LIKE 'ACME%' and Title = 'Mr' \
+             ORDERBY Balance DESC STARTPOSITION 21 MAXRESULTS 10"
+        );
+
+        let query = Query::<Customer>::from_sql_str(
+            "Select * From Customer Where Title In ('Dr', 'mr') Order By DisplayName",
+        )
+        .unwrap();
+        assert_eq!(
+            query.query_string(),
+            "select * from Customer where Title IN ('Dr', 'mr') ORDERBY DisplayName ASC"
+        );
+    }
+
+    #[test]
+    fn test_from_sql_str_round_trip() {
+        let ids = ["1", "2"];
+        let queries = [
+            qb_sql!(
+                select display_name from Customer
+                where (balance between 100 and 500 or title is null)
+                and not display_name like "O'Brien\\%"
+                and id not in (ids)
+                order by display_name asc, balance desc
+                maxresults 5 startposition 11
+            ),
+            qb_sql!(select distinct title from Customer where balance >= 0 and notes != "C:\\path"),
+            qb_sql!(select count(id), max(balance) from Customer group by title),
+        ];
+        for query in queries {
+            let rendered = query.query_string();
+            let parsed = Query::<Customer>::from_sql_str(&rendered).unwrap();
+            assert_eq!(parsed.query_string(), rendered);
+        }
+
+        let query = qb_sql!(select * from Customer where title is null or notes is not null)
+            .null_style(NullStyle::EqualsNull);
+        let rendered = query.query_string();
+        assert_eq!(
+            rendered,
+            "select * from Customer where Title = NULL or Notes != NULL"
+        );
+        let parsed = Query::<Customer>::from_sql_str(&rendered).unwrap();
+        assert_eq!(
+            parsed.null_style(NullStyle::EqualsNull).query_string(),
+            rendered
+        );
+        let parsed =
+            Query::<Customer>::from_sql_str("select * from Customer where Notes <> NULL").unwrap();
+        assert_eq!(parsed.conditions()[0].operator, Operator::IsNotNull);
+    }
+
+    #[test]
+    fn test_from_sql_str_errors() {
+        let syntax = |sql: &str| match Query::<Customer>::from_sql_str(sql) {
+            Err(ValidationError::Syntax { position, .. }) => position,
+            other => panic!("expected a syntax error for {sql:?}, got {other:?}"),
+        };
+
+        assert_eq!(syntax("select * from Invoice"), 14);
+        assert_eq!(syntax("select * from Customer where Title = 'Dr"), 37);
+        assert_eq!(syntax("select * from Customer where Title ~ 'Dr'"), 35);
+        assert_eq!(syntax("select * from Customer where Title ="), 36);
+        assert_eq!(syntax("select count(*) from Customer"), 13);
+        assert_eq!(syntax("select * from Customer STARTPOSITION 0"), 37);
+        assert_eq!(syntax("select * from Customer limit 10 extra"), 32);
+        assert_eq!(syntax("select * from Customer order Title"), 29);
+        assert!(Query::<Invoice>::from_sql_str("select * from invoice").is_ok());
+    }
+}
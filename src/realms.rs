@@ -0,0 +1,407 @@
+use std::collections::{HashMap, VecDeque};
+use std::sync::{Mutex, MutexGuard, PoisonError};
+use std::time::{Duration, Instant};
+
+use quickbooks_types::QBItem;
+use serde::de::DeserializeOwned;
+
+use crate::{Query, QueryError, QueryExecutor, RetryPolicy};
+
+/// The results of running one query against several companies (realms)
+#[derive(Debug)]
+pub struct RealmResults<QB> {
+    /// The result for each realm, tagged with its company ID, in the order the realms were given
+    pub results: Vec<(String, Result<Vec<QB>, QueryError>)>,
+}
+
+/// Aggregate counts over a set of [`RealmResults`]
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Default)]
+pub struct RealmSummary {
+    pub succeeded: usize,
+    pub failed: usize,
+    /// The number of entities returned across all successful realms
+    pub total_rows: usize,
+}
+
+impl<QB> RealmResults<QB> {
+    /// Count the successful and failed realms and the rows they returned
+    pub fn summary(&self) -> RealmSummary {
+        self.results
+            .iter()
+            .fold(RealmSummary::default(), |mut summary, (_, result)| {
+                match result {
+                    Ok(items) => {
+                        summary.succeeded += 1;
+                        summary.total_rows += items.len();
+                    }
+                    Err(_) => summary.failed += 1,
+                }
+                summary
+            })
+    }
+}
+
+/// A realm waiting to be queried, and the number of attempts made so far
+#[derive(Debug, Clone, Copy)]
+struct RealmJob {
+    index: usize,
+    attempt: u32,
+}
+
+/// The realms left to query, with retries delayed per company ID
+///
+/// A realm that fails with an error the retry policy accepts goes back in the
+/// queue and its company is blocked for the policy's backoff, doubled for each
+/// attempt. Other companies keep being queried in the meantime.
+struct RealmSchedule<'a> {
+    pending: VecDeque<RealmJob>,
+    blocked_until: HashMap<&'a str, Instant>,
+}
+
+impl<'a> RealmSchedule<'a> {
+    fn new(realms: usize) -> Self {
+        Self {
+            pending: (0..realms)
+                .map(|index| RealmJob { index, attempt: 0 })
+                .collect(),
+            blocked_until: HashMap::new(),
+        }
+    }
+
+    /// Take the next realm to query and how long to wait before querying it
+    ///
+    /// Realms whose company isn't blocked come first, in the order they were queued.
+    fn next(&mut self, company_ids: impl Fn(usize) -> &'a str) -> Option<(RealmJob, Duration)> {
+        let now = Instant::now();
+        let (position, wait) = self
+            .pending
+            .iter()
+            .map(|job| match self.blocked_until.get(company_ids(job.index)) {
+                Some(until) => until.saturating_duration_since(now),
+                None => Duration::ZERO,
+            })
+            .enumerate()
+            .min_by_key(|&(_, wait)| wait)?;
+        Some((self.pending.remove(position)?, wait))
+    }
+
+    /// Queue the realm again if `result` should be retried, returning the result otherwise
+    fn retry<T>(
+        &mut self,
+        job: RealmJob,
+        company_id: &'a str,
+        policy: &RetryPolicy,
+        result: Result<T, QueryError>,
+    ) -> Option<Result<T, QueryError>> {
+        match result {
+            Err(err) if job.attempt < policy.max_retries && policy.should_retry(&err) => {
+                let backoff = policy.backoff * 2u32.saturating_pow(job.attempt);
+                self.blocked_until
+                    .insert(company_id, Instant::now() + backoff);
+                self.pending.push_back(RealmJob {
+                    attempt: job.attempt + 1,
+                    ..job
+                });
+                None
+            }
+            result => Some(result),
+        }
+    }
+}
+
+impl<QB: QBItem + DeserializeOwned + Send + Sync> Query<QB> {
+    /// Execute the query against every realm, running at most `max_concurrency` requests at once
+    ///
+    /// A failure in one realm doesn't affect the others; each result is tagged with
+    /// the executor's company ID. A new realm is started as soon as a request
+    /// finishes. Retries follow the query's retry policy, with the backoff applied
+    /// per company ID, so a throttled company doesn't hold up the others.
+    ///
+    /// # Panics
+    /// Panics if `max_concurrency` is zero.
+    pub fn execute_across_realms<E: QueryExecutor + Sync>(
+        &self,
+        executors: &[E],
+        max_concurrency: usize,
+    ) -> RealmResults<QB> {
+        assert!(max_concurrency > 0, "concurrency must be greater than zero");
+        // Retries are scheduled across realms rather than slept through by one request
+        let mut once = self.clone();
+        once.retry.max_retries = 0;
+        let schedule = Mutex::new(RealmSchedule::new(executors.len()));
+        let results = Mutex::new((0..executors.len()).map(|_| None).collect::<Vec<_>>());
+
+        std::thread::scope(|scope| {
+            for _ in 0..max_concurrency.min(executors.len()) {
+                scope.spawn(|| {
+                    loop {
+                        let next = lock(&schedule).next(|index| executors[index].company_id());
+                        // The realms still running requeue their own retries
+                        let Some((job, wait)) = next else { break };
+                        std::thread::sleep(wait);
+                        let executor = &executors[job.index];
+                        let result = once.execute_with(executor);
+                        if let Some(result) =
+                            lock(&schedule).retry(job, executor.company_id(), &self.retry, result)
+                        {
+                            lock(&results)[job.index] = Some(result);
+                        }
+                    }
+                });
+            }
+        });
+
+        let results = results.into_inner().unwrap_or_else(PoisonError::into_inner);
+        RealmResults {
+            results: executors
+                .iter()
+                .zip(results)
+                .map(|(executor, result)| {
+                    let result = result.expect("every realm is queried until it has a result");
+                    (executor.company_id().to_string(), result)
+                })
+                .collect(),
+        }
+    }
+}
+
+/// Lock `mutex`, ignoring poisoning, since a panicking realm fails the whole scope anyway
+fn lock<T>(mutex: &Mutex<T>) -> MutexGuard<'_, T> {
+    mutex.lock().unwrap_or_else(PoisonError::into_inner)
+}
+
+#[cfg(feature = "async")]
+impl<QB: QBItem + DeserializeOwned> Query<QB> {
+    /// Execute the query against every realm asynchronously, running at most
+    /// `max_concurrency` requests at once
+    ///
+    /// A failure in one realm doesn't affect the others; each result is tagged with
+    /// the executor's company ID. Like [`Query::execute_across_realms`], a new realm
+    /// is started as soon as a request finishes and retries follow the query's
+    /// retry policy per company ID. The backoff is waited out on a helper thread,
+    /// so no particular async runtime is needed.
+    ///
+    /// # Panics
+    /// Panics if `max_concurrency` is zero.
+    pub async fn execute_across_realms_async(
+        &self,
+        executors: &[crate::AsyncHttpExecutor<'_>],
+        max_concurrency: usize,
+    ) -> RealmResults<QB> {
+        use futures::StreamExt;
+        use futures::stream::FuturesUnordered;
+
+        assert!(max_concurrency > 0, "concurrency must be greater than zero");
+        let mut schedule = RealmSchedule::new(executors.len());
+        let mut results: Vec<_> = (0..executors.len()).map(|_| None).collect();
+        let mut running = FuturesUnordered::new();
+        loop {
+            while running.len() < max_concurrency {
+                let Some((job, wait)) = schedule.next(|index| executors[index].company_id()) else {
+                    break;
+                };
+                running.push(async move {
+                    if !wait.is_zero() {
+                        sleep(wait).await;
+                    }
+                    (job, self.execute_async(&executors[job.index]).await)
+                });
+            }
+            let Some((job, result)) = running.next().await else {
+                break;
+            };
+            let company_id = executors[job.index].company_id();
+            if let Some(result) = schedule.retry(job, company_id, &self.retry, result) {
+                results[job.index] = Some(result);
+            }
+        }
+
+        RealmResults {
+            results: executors
+                .iter()
+                .zip(results)
+                .map(|(executor, result)| {
+                    let result = result.expect("every realm is queried until it has a result");
+                    (executor.company_id().to_string(), result)
+                })
+                .collect(),
+        }
+    }
+}
+
+/// Wait for `duration` without depending on an async runtime's timer
+#[cfg(feature = "async")]
+async fn sleep(duration: Duration) {
+    let (sender, receiver) = futures::channel::oneshot::channel();
+    std::thread::spawn(move || {
+        std::thread::sleep(duration);
+        let _ = sender.send(());
+    });
+    let _ = receiver.await;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{QbEnvironment, qb_sql};
+    use quickbooks_types::Customer;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    /// Executor that always returns the same response for a realm
+    struct RealmExecutor {
+        company_id: &'static str,
+        response: &'static str,
+    }
+
+    impl QueryExecutor for RealmExecutor {
+        fn environment(&self) -> QbEnvironment {
+            QbEnvironment::Production
+        }
+
+        fn company_id(&self) -> &str {
+            self.company_id
+        }
+
+        fn get(&self, _url: &str) -> Result<String, QueryError> {
+            Ok(self.response.to_string())
+        }
+    }
+
+    #[test]
+    fn test_execute_across_realms() {
+        let executors = [
+            RealmExecutor {
+                company_id: "1",
+                response: r#"{"QueryResponse":{"Customer":[{"Id":"1"},{"Id":"2"}]}}"#,
+            },
+            RealmExecutor {
+                company_id: "2",
+                response: r#"{"Fault":{"Error":[{"Message":"ThrottleExceeded","code":"3001"}],"type":"SERVICE"}}"#,
+            },
+            RealmExecutor {
+                company_id: "3",
+                response: r#"{"QueryResponse":{"Customer":[{"Id":"7"}]}}"#,
+            },
+        ];
+        let query = qb_sql!(select * from Customer);
+
+        let results = query.execute_across_realms(&executors, 2);
+
+        let realms: Vec<_> = results.results.iter().map(|(id, _)| id.as_str()).collect();
+        assert_eq!(realms, vec!["1", "2", "3"]);
+        assert!(results.results[1].1.as_ref().unwrap_err().is_retryable());
+        assert_eq!(
+            results.summary(),
+            RealmSummary {
+                succeeded: 2,
+                failed: 1,
+                total_rows: 3,
+            }
+        );
+    }
+
+    /// Executor that records when its requests start and finish, failing the first
+    /// `throttled` of them with a 429
+    struct TracingExecutor<'a> {
+        company_id: &'static str,
+        delay: Duration,
+        throttled: usize,
+        requests: AtomicUsize,
+        events: &'a Mutex<Vec<String>>,
+    }
+
+    impl<'a> TracingExecutor<'a> {
+        fn new(company_id: &'static str, events: &'a Mutex<Vec<String>>) -> Self {
+            Self {
+                company_id,
+                delay: Duration::ZERO,
+                throttled: 0,
+                requests: AtomicUsize::new(0),
+                events,
+            }
+        }
+    }
+
+    impl QueryExecutor for TracingExecutor<'_> {
+        fn environment(&self) -> QbEnvironment {
+            QbEnvironment::Production
+        }
+
+        fn company_id(&self) -> &str {
+            self.company_id
+        }
+
+        fn get(&self, _url: &str) -> Result<String, QueryError> {
+            let request = self.requests.fetch_add(1, Ordering::SeqCst);
+            lock(self.events).push(format!("start {}", self.company_id));
+            std::thread::sleep(self.delay);
+            lock(self.events).push(format!("end {}", self.company_id));
+            if request < self.throttled {
+                return Err(QueryError::Http {
+                    status: 429,
+                    body: String::new(),
+                });
+            }
+            Ok(r#"{"QueryResponse":{"Customer":[{"Id":"1"}]}}"#.to_string())
+        }
+    }
+
+    #[test]
+    fn test_realm_starts_when_slot_frees() {
+        let events = Mutex::new(Vec::new());
+        let executors = [
+            TracingExecutor {
+                delay: Duration::from_millis(200),
+                ..TracingExecutor::new("1", &events)
+            },
+            TracingExecutor::new("2", &events),
+            TracingExecutor::new("3", &events),
+        ];
+        let query = qb_sql!(select * from Customer);
+
+        let results = query.execute_across_realms(&executors, 2);
+
+        assert_eq!(results.summary().succeeded, 3);
+        // The third realm takes the second realm's slot without waiting for the first
+        let events = events.into_inner().unwrap();
+        let position = |event: &str| events.iter().position(|e| e == event).unwrap();
+        assert!(position("start 3") < position("end 1"));
+    }
+
+    #[test]
+    fn test_throttled_realm_is_retried_per_company() {
+        let events = Mutex::new(Vec::new());
+        let executors = [
+            TracingExecutor {
+                throttled: 2,
+                ..TracingExecutor::new("1", &events)
+            },
+            TracingExecutor::new("2", &events),
+        ];
+        let query = qb_sql!(select * from Customer)
+            .retries(2)
+            .retry_backoff(Duration::from_millis(50));
+
+        let results = query.execute_across_realms(&executors, 1);
+
+        assert_eq!(results.summary().succeeded, 2);
+        assert_eq!(executors[0].requests.load(Ordering::SeqCst), 3);
+        assert_eq!(executors[1].requests.load(Ordering::SeqCst), 1);
+        // The other company is queried while the throttled one backs off
+        let events = events.into_inner().unwrap();
+        assert_eq!(events[2], "start 2");
+
+        // A company still throttled after the last retry fails with the throttling error
+        let events = Mutex::new(Vec::new());
+        let executors = [TracingExecutor {
+            throttled: 5,
+            ..TracingExecutor::new("1", &events)
+        }];
+        let results = query.execute_across_realms(&executors, 2);
+        assert!(matches!(
+            results.results[0].1,
+            Err(QueryError::Http { status: 429, .. })
+        ));
+        assert_eq!(executors[0].requests.load(Ordering::SeqCst), 3);
+    }
+}
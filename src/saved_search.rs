@@ -0,0 +1,71 @@
+use quickbooks_types::QBItem;
+use serde::{Deserialize, Serialize};
+
+use crate::Query;
+
+/// A named query that can be persisted and later turned back into a [`Query`]
+///
+/// QuickBooks Online has no saved query API, so this is meant to be stored by
+/// the application, e.g. for a "saved reports" feature. The rendered query
+/// string is stored alongside the query for display.
+#[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
+#[serde(bound = "")]
+pub struct SavedSearch<QB> {
+    pub name: String,
+    /// The name of the entity the query selects from
+    pub entity: String,
+    pub query_string: String,
+    query: Query<QB>,
+}
+
+impl<QB: QBItem> Query<QB> {
+    /// Save the query under `name`
+    pub fn to_saved_search(&self, name: &str) -> SavedSearch<QB> {
+        SavedSearch {
+            name: name.to_string(),
+            entity: QB::name().to_string(),
+            query_string: self.query_string(),
+            query: self.clone(),
+        }
+    }
+
+    /// Restore a query saved with [`Query::to_saved_search`]
+    ///
+    /// Returns `None` if the search was saved for a different entity.
+    pub fn from_saved_search(search: SavedSearch<QB>) -> Option<Self> {
+        (search.entity == QB::name()).then_some(search.query)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::qb_sql;
+    use quickbooks_types::{Customer, Invoice};
+
+    #[test]
+    fn test_saved_search_round_trip() {
+        let query = qb_sql!(
+            select display_name, balance from Customer
+            where balance > 1000
+            order by balance desc
+        );
+
+        let json = serde_json::to_string(&query.to_saved_search("Top customers")).unwrap();
+        let saved: SavedSearch<Customer> = serde_json::from_str(&json).unwrap();
+
+        assert_eq!(saved.name, "Top customers");
+        assert_eq!(saved.query_string, query.query_string());
+        let restored = Query::from_saved_search(saved).unwrap();
+        assert_eq!(restored.query_string(), query.query_string());
+    }
+
+    #[test]
+    fn test_saved_search_entity_mismatch() {
+        let json =
+            serde_json::to_string(&qb_sql!(select * from Customer).to_saved_search("All")).unwrap();
+        let saved: SavedSearch<Invoice> = serde_json::from_str(&json).unwrap();
+
+        assert!(Query::from_saved_search(saved).is_none());
+    }
+}
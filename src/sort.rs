@@ -0,0 +1,81 @@
+use std::cmp::Ordering;
+
+use crate::{Nulls, Order};
+
+/// Sort query results by a key on the client, for fields QuickBooks can't order by
+///
+/// Entities whose key is `None` are placed first or last according to `nulls`,
+/// regardless of `order`, so exports come out in the same order every time.
+/// The sort is stable, so entities with equal keys keep the order QuickBooks
+/// returned them in.
+///
+/// ```ignore
+/// let mut customers = query.execute_with(&executor)?;
+/// sort_results(&mut customers, Order::Desc, Nulls::Last, |c| c.balance);
+/// ```
+pub fn sort_results<QB, K: Ord>(
+    items: &mut [QB],
+    order: Order,
+    nulls: Nulls,
+    mut key: impl FnMut(&QB) -> Option<K>,
+) {
+    items.sort_by(|a, b| match (key(a), key(b)) {
+        (Some(a), Some(b)) => match order {
+            Order::Asc => a.cmp(&b),
+            Order::Desc => b.cmp(&a),
+        },
+        (None, None) => Ordering::Equal,
+        (None, Some(_)) => match nulls {
+            Nulls::First => Ordering::Less,
+            Nulls::Last => Ordering::Greater,
+        },
+        (Some(_), None) => match nulls {
+            Nulls::First => Ordering::Greater,
+            Nulls::Last => Ordering::Less,
+        },
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use quickbooks_types::Customer;
+
+    fn customers(names: &[Option<&str>]) -> Vec<Customer> {
+        names
+            .iter()
+            .enumerate()
+            .map(|(id, name)| Customer {
+                id: Some(id.to_string()),
+                display_name: name.map(String::from),
+                ..Default::default()
+            })
+            .collect()
+    }
+
+    fn ids(customers: &[Customer]) -> Vec<&str> {
+        customers.iter().filter_map(|c| c.id.as_deref()).collect()
+    }
+
+    #[test]
+    fn test_sort_results_nulls() {
+        let names = [Some("Globex"), None, Some("Acme"), None, Some("Initech")];
+        let key = |c: &Customer| c.display_name.clone();
+
+        let mut sorted = customers(&names);
+        sort_results(&mut sorted, Order::Asc, Nulls::First, key);
+        assert_eq!(ids(&sorted), vec!["1", "3", "2", "0", "4"]);
+
+        let mut sorted = customers(&names);
+        sort_results(&mut sorted, Order::Asc, Nulls::Last, key);
+        assert_eq!(ids(&sorted), vec!["2", "0", "4", "1", "3"]);
+
+        let mut sorted = customers(&names);
+        sort_results(&mut sorted, Order::Desc, Nulls::First, key);
+        assert_eq!(ids(&sorted), vec!["1", "3", "4", "0", "2"]);
+
+        let mut sorted = customers(&names);
+        sort_results(&mut sorted, Order::Desc, Nulls::Last, key);
+        assert_eq!(ids(&sorted), vec!["4", "0", "2", "1", "3"]);
+    }
+}
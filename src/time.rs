@@ -0,0 +1,162 @@
+use std::fmt;
+
+/// A fixed offset from UTC, such as a QuickBooks company's time zone
+///
+/// QuickBooks compares timestamps in the company's time zone, so a UTC
+/// timestamp right at a day boundary can match different records than
+/// intended. See [`Query::normalize_times_to`](crate::Query::normalize_times_to).
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct UtcOffset {
+    minutes: i32,
+}
+
+impl UtcOffset {
+    pub const UTC: UtcOffset = UtcOffset { minutes: 0 };
+
+    /// An offset of a whole number of hours, e.g. `UtcOffset::hours(-5)` for EST
+    pub const fn hours(hours: i32) -> Self {
+        UtcOffset {
+            minutes: hours * 60,
+        }
+    }
+
+    /// An offset in minutes, for time zones such as India's `+05:30`
+    pub const fn minutes(minutes: i32) -> Self {
+        UtcOffset { minutes }
+    }
+
+    /// The offset in minutes east of UTC
+    pub const fn as_minutes(self) -> i32 {
+        self.minutes
+    }
+}
+
+impl fmt::Display for UtcOffset {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let sign = if self.minutes < 0 { '-' } else { '+' };
+        let minutes = self.minutes.unsigned_abs();
+        write!(f, "{sign}{:02}:{:02}", minutes / 60, minutes % 60)
+    }
+}
+
+/// Rewrite an RFC 3339 timestamp with an explicit offset, such as
+/// `2024-01-01T00:00:00Z`, as the same instant in `offset`
+///
+/// Returns `None` for anything else, including dates without a time and
+/// timestamps without an offset, which are already in the company's time zone.
+pub(crate) fn convert_timestamp(value: &str, offset: UtcOffset) -> Option<String> {
+    let (date, time) = value.split_once('T')?;
+    let mut date_parts = date.splitn(3, '-');
+    let year: i64 = date_parts.next()?.parse().ok()?;
+    let month: u32 = date_parts.next()?.parse().ok()?;
+    let day: u32 = date_parts.next()?.parse().ok()?;
+
+    let (clock, source) = if let Some(clock) = time.strip_suffix('Z') {
+        (clock, 0)
+    } else {
+        let split = time.rfind(['+', '-'])?;
+        (&time[..split], parse_offset(&time[split..])?)
+    };
+    let (clock, fraction) = match clock.split_once('.') {
+        Some((clock, fraction)) => (clock, Some(fraction)),
+        None => (clock, None),
+    };
+    let mut clock_parts = clock.splitn(3, ':');
+    let hour: i64 = clock_parts.next()?.parse().ok()?;
+    let minute: i64 = clock_parts.next()?.parse().ok()?;
+    let second: i64 = clock_parts.next()?.parse().ok()?;
+    if !(1..=12).contains(&month) || !(1..=31).contains(&day) || hour > 23 || minute > 59 {
+        return None;
+    }
+
+    let seconds = days_from_civil(year, month, day) * 86_400
+        + hour * 3600
+        + minute * 60
+        + second
+        + i64::from(offset.minutes - source) * 60;
+    let (year, month, day) = civil_from_days(seconds.div_euclid(86_400));
+    let time_of_day = seconds.rem_euclid(86_400);
+
+    let mut converted = format!(
+        "{year:04}-{month:02}-{day:02}T{:02}:{:02}:{:02}",
+        time_of_day / 3600,
+        time_of_day / 60 % 60,
+        time_of_day % 60
+    );
+    if let Some(fraction) = fraction {
+        converted.push('.');
+        converted.push_str(fraction);
+    }
+    converted.push_str(&offset.to_string());
+    Some(converted)
+}
+
+/// Parse an offset such as `+05:30`, in minutes
+fn parse_offset(offset: &str) -> Option<i32> {
+    let (sign, rest) = offset.split_at(1);
+    let (hours, minutes) = rest.split_once(':')?;
+    let minutes = hours.parse::<i32>().ok()? * 60 + minutes.parse::<i32>().ok()?;
+    Some(if sign == "-" { -minutes } else { minutes })
+}
+
+// Conversions between dates and days since 1970-01-01, from
+// http://howardhinnant.github.io/date_algorithms.html
+fn days_from_civil(year: i64, month: u32, day: u32) -> i64 {
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let year_of_era = year - era * 400;
+    let month = i64::from(month);
+    let day_of_year = (153 * (month + if month > 2 { -3 } else { 9 }) + 2) / 5 + i64::from(day) - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    era * 146_097 + day_of_era - 719_468
+}
+
+fn civil_from_days(days: i64) -> (i64, u32, u32) {
+    let days = days + 719_468;
+    let era = days.div_euclid(146_097);
+    let day_of_era = days - era * 146_097;
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let mp = (5 * day_of_year + 2) / 153;
+    let day = (day_of_year - (153 * mp + 2) / 5 + 1) as u32;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 } as u32;
+    let year = year_of_era + era * 400 + i64::from(month <= 2);
+    (year, month, day)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_convert_utc_timestamp() {
+        assert_eq!(
+            convert_timestamp("2024-01-01T03:30:00Z", UtcOffset::hours(-8)).as_deref(),
+            Some("2023-12-31T19:30:00-08:00")
+        );
+        assert_eq!(
+            convert_timestamp("2024-02-28T20:00:00.250+00:00", UtcOffset::minutes(330)).as_deref(),
+            Some("2024-02-29T01:30:00.250+05:30")
+        );
+        assert_eq!(
+            convert_timestamp("2024-03-01T01:00:00+05:00", UtcOffset::UTC).as_deref(),
+            Some("2024-02-29T20:00:00+00:00")
+        );
+    }
+
+    #[test]
+    fn test_non_timestamps_are_left_alone() {
+        for value in ["2024-01-01", "2024-01-01T00:00:00", "Oak & Co", "1000"] {
+            assert_eq!(convert_timestamp(value, UtcOffset::hours(-5)), None);
+        }
+    }
+
+    #[test]
+    fn test_offset_display() {
+        assert_eq!(UtcOffset::hours(-5).to_string(), "-05:00");
+        assert_eq!(UtcOffset::minutes(330).to_string(), "+05:30");
+        assert_eq!(UtcOffset::UTC.to_string(), "+00:00");
+    }
+}
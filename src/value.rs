@@ -0,0 +1,316 @@
+use std::borrow::Cow;
+use std::ops::{Deref, DerefMut};
+use std::sync::{Arc, LazyLock};
+
+#[cfg(feature = "time")]
+use crate::UtcOffset;
+
+/// A value that can be compared against in a query condition
+///
+/// Values written in [`qb_sql!`](crate::qb_sql) conditions are rendered with
+/// this trait rather than [`Display`](std::fmt::Display), so types whose display
+/// form isn't what QuickBooks expects can be formatted correctly. Date and time
+/// types from `chrono` and `time` are supported behind the features of the same
+/// names, and render in the ISO 8601 form QuickBooks uses, e.g.
+/// `2015-07-25T10:33:00-07:00`.
+pub trait QueryValue {
+    /// The value as written between the quotes of a query literal
+    ///
+    /// Quotes and backslashes are escaped when the query is rendered, so they
+    /// shouldn't be escaped here.
+    fn to_query_literal(&self) -> String;
+}
+
+impl<T: QueryValue + ?Sized> QueryValue for &T {
+    fn to_query_literal(&self) -> String {
+        (**self).to_query_literal()
+    }
+}
+
+/// `None` renders as an empty literal, `''`
+impl<T: QueryValue> QueryValue for Option<T> {
+    fn to_query_literal(&self) -> String {
+        self.as_ref()
+            .map(QueryValue::to_query_literal)
+            .unwrap_or_default()
+    }
+}
+
+macro_rules! display_query_value {
+    ($($ty:ty),*) => {
+        $(
+            impl QueryValue for $ty {
+                fn to_query_literal(&self) -> String {
+                    self.to_string()
+                }
+            }
+        )*
+    };
+}
+
+display_query_value!(
+    i8,
+    i16,
+    i32,
+    i64,
+    i128,
+    isize,
+    u8,
+    u16,
+    u32,
+    u64,
+    u128,
+    usize,
+    f32,
+    f64,
+    bool,
+    char,
+    str,
+    String,
+    Cow<'_, str>
+);
+
+#[cfg(feature = "chrono")]
+impl QueryValue for chrono::NaiveDate {
+    fn to_query_literal(&self) -> String {
+        self.format("%Y-%m-%d").to_string()
+    }
+}
+
+#[cfg(feature = "chrono")]
+impl QueryValue for chrono::NaiveDateTime {
+    fn to_query_literal(&self) -> String {
+        self.format("%Y-%m-%dT%H:%M:%S%.f").to_string()
+    }
+}
+
+#[cfg(feature = "chrono")]
+impl<Tz: chrono::TimeZone> QueryValue for chrono::DateTime<Tz>
+where
+    Tz::Offset: std::fmt::Display,
+{
+    fn to_query_literal(&self) -> String {
+        // QuickBooks expects an explicit offset, so UTC is written `+00:00` rather than `Z`
+        self.to_rfc3339_opts(chrono::SecondsFormat::AutoSi, false)
+    }
+}
+
+#[cfg(feature = "time")]
+impl QueryValue for time::Date {
+    fn to_query_literal(&self) -> String {
+        format!(
+            "{:04}-{:02}-{:02}",
+            self.year(),
+            u8::from(self.month()),
+            self.day()
+        )
+    }
+}
+
+#[cfg(feature = "time")]
+impl QueryValue for time::PrimitiveDateTime {
+    fn to_query_literal(&self) -> String {
+        let mut literal = self.date().to_query_literal();
+        literal.push('T');
+        push_time(&mut literal, self.time());
+        literal
+    }
+}
+
+#[cfg(feature = "time")]
+impl QueryValue for time::OffsetDateTime {
+    fn to_query_literal(&self) -> String {
+        let mut literal = self.date().to_query_literal();
+        literal.push('T');
+        push_time(&mut literal, self.time());
+        let offset = UtcOffset::minutes(i32::from(self.offset().whole_minutes()));
+        literal.push_str(&offset.to_string());
+        literal
+    }
+}
+
+/// Append `time` as `HH:MM:SS`, followed by the fraction of a second if it has one
+#[cfg(feature = "time")]
+fn push_time(literal: &mut String, time: time::Time) {
+    literal.push_str(&format!(
+        "{:02}:{:02}:{:02}",
+        time.hour(),
+        time.minute(),
+        time.second()
+    ));
+    if time.nanosecond() != 0 {
+        let fraction = format!("{:09}", time.nanosecond());
+        literal.push('.');
+        literal.push_str(fraction.trim_end_matches('0'));
+    }
+}
+
+/// The values a [`WhereClause`](crate::WhereClause) compares against
+///
+/// This derefs to the rendered values. Values from an iterator, e.g. with
+/// [`WhereClause::in_iter`](crate::WhereClause::in_iter), are only rendered when
+/// they're first read, such as when the query is rendered or validated, and only
+/// once however often the clause is cloned or rendered.
+#[derive(Clone, Default)]
+pub struct Values(Repr);
+
+/// Values rendered on first read, shared between clones of the clause
+type DeferredValues = LazyLock<Vec<String>, Box<dyn FnOnce() -> Vec<String> + Send>>;
+
+#[derive(Clone)]
+enum Repr {
+    Rendered(Vec<String>),
+    Deferred(Arc<DeferredValues>),
+}
+
+impl Default for Repr {
+    fn default() -> Self {
+        Repr::Rendered(Vec::new())
+    }
+}
+
+impl Values {
+    /// Values rendered from `values` when they're first read
+    ///
+    /// The iterator's `size_hint` pre-sizes the list, so exact-size iterators
+    /// only allocate once.
+    pub fn deferred<I>(values: I) -> Self
+    where
+        I: IntoIterator + Send + 'static,
+        I::Item: QueryValue,
+    {
+        Values(Repr::Deferred(Arc::new(LazyLock::new(Box::new(
+            move || {
+                let values = values.into_iter();
+                let mut rendered = Vec::with_capacity(values.size_hint().0);
+                rendered.extend(values.map(|value| value.to_query_literal()));
+                rendered
+            },
+        )))))
+    }
+}
+
+impl Deref for Values {
+    type Target = Vec<String>;
+
+    fn deref(&self) -> &Vec<String> {
+        match &self.0 {
+            Repr::Rendered(values) => values,
+            Repr::Deferred(values) => values,
+        }
+    }
+}
+
+/// Renders deferred values, copying them if the clause was cloned
+impl DerefMut for Values {
+    fn deref_mut(&mut self) -> &mut Vec<String> {
+        if let Repr::Deferred(values) = &self.0 {
+            self.0 = Repr::Rendered(Vec::clone(values));
+        }
+        match &mut self.0 {
+            Repr::Rendered(values) => values,
+            Repr::Deferred(_) => unreachable!("deferred values were just rendered"),
+        }
+    }
+}
+
+impl From<Vec<String>> for Values {
+    fn from(values: Vec<String>) -> Self {
+        Values(Repr::Rendered(values))
+    }
+}
+
+impl std::fmt::Debug for Values {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_list().entries(self.iter()).finish()
+    }
+}
+
+impl PartialEq for Values {
+    fn eq(&self, other: &Self) -> bool {
+        **self == **other
+    }
+}
+
+impl<T> PartialEq<Vec<T>> for Values
+where
+    String: PartialEq<T>,
+{
+    fn eq(&self, other: &Vec<T>) -> bool {
+        **self == *other
+    }
+}
+
+#[cfg(feature = "serde")]
+impl serde::Serialize for Values {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        (**self).serialize(serializer)
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de> serde::Deserialize<'de> for Values {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        Vec::deserialize(deserializer).map(Values::from)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_primitive_literals_match_display() {
+        assert_eq!(42i32.to_query_literal(), "42");
+        assert_eq!(1000.5f64.to_query_literal(), "1000.5");
+        assert_eq!(true.to_query_literal(), "true");
+        assert_eq!("O'Brien".to_query_literal(), "O'Brien");
+        assert_eq!((&&String::from("Acme")).to_query_literal(), "Acme");
+    }
+
+    #[test]
+    fn test_option_literals() {
+        assert_eq!(Some(1000.0f64).to_query_literal(), "1000");
+        assert_eq!(Some("Acme").to_query_literal(), "Acme");
+        assert_eq!(None::<i32>.to_query_literal(), "");
+    }
+
+    #[cfg(feature = "chrono")]
+    #[test]
+    fn test_chrono_literals() {
+        use chrono::{FixedOffset, NaiveDate, TimeZone, Utc};
+
+        let date = NaiveDate::from_ymd_opt(2015, 7, 25).unwrap();
+        assert_eq!(date.to_query_literal(), "2015-07-25");
+        assert_eq!(
+            date.and_hms_opt(10, 33, 0).unwrap().to_query_literal(),
+            "2015-07-25T10:33:00"
+        );
+
+        let pacific = FixedOffset::west_opt(7 * 3600).unwrap();
+        let timestamp = pacific.with_ymd_and_hms(2015, 7, 25, 10, 33, 0).unwrap();
+        assert_eq!(timestamp.to_query_literal(), "2015-07-25T10:33:00-07:00");
+        assert_eq!(
+            timestamp.with_timezone(&Utc).to_query_literal(),
+            "2015-07-25T17:33:00+00:00"
+        );
+    }
+
+    #[cfg(feature = "time")]
+    #[test]
+    fn test_time_literals() {
+        use time::{Date, Month, Time};
+
+        let date = Date::from_calendar_date(2015, Month::July, 25).unwrap();
+        assert_eq!(date.to_query_literal(), "2015-07-25");
+
+        let timestamp = date.with_time(Time::from_hms_milli(10, 33, 0, 250).unwrap());
+        assert_eq!(timestamp.to_query_literal(), "2015-07-25T10:33:00.25");
+        assert_eq!(
+            timestamp
+                .assume_offset(time::UtcOffset::from_hms(-7, 0, 0).unwrap())
+                .to_query_literal(),
+            "2015-07-25T10:33:00.25-07:00"
+        );
+    }
+}
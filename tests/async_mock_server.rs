@@ -0,0 +1,189 @@
+//! End-to-end tests running queries through `AsyncHttpExecutor` against a local
+//! mock QuickBooks server.
+#![cfg(feature = "async")]
+
+use httpmock::prelude::*;
+use quick_oxibooks_sql::{
+    AsyncHttpExecutor, FaultCode, QbEnvironment, Query, QueryError, RealmSummary, qb_sql,
+};
+use quickbooks_types::Customer;
+use std::time::Duration;
+
+fn executor<'a>(server: &MockServer, client: &'a reqwest::Client) -> AsyncHttpExecutor<'a> {
+    AsyncHttpExecutor::new(client, QbEnvironment::Sandbox, "1234", "test-token")
+        .with_base_url(format!("{}/v3", server.base_url()))
+}
+
+#[tokio::test]
+async fn test_async_query_round_trip() {
+    let server = MockServer::start_async().await;
+    let mock = server
+        .mock_async(|when, then| {
+            when.method(GET)
+                .path("/v3/company/1234/query")
+                .query_param("query", "select * from Customer where DisplayName LIKE 'O%'")
+                .header("Authorization", "Bearer test-token")
+                .header("Accept", "application/json");
+            then.status(200).body(
+                r#"{"QueryResponse":{"Customer":[{"Id":"1","DisplayName":"Oak & Co"}]},"time":"2024-01-01T00:00:00.000-08:00"}"#,
+            );
+        })
+        .await;
+
+    let client = reqwest::Client::new();
+    let query = qb_sql!(select * from Customer where display_name like "O%");
+    let customers = query
+        .execute_async(&executor(&server, &client))
+        .await
+        .unwrap();
+
+    mock.assert_async().await;
+    assert_eq!(customers.len(), 1);
+    assert_eq!(customers[0].display_name.as_deref(), Some("Oak & Co"));
+}
+
+#[tokio::test]
+async fn test_async_fault_response() {
+    let server = MockServer::start_async().await;
+    server
+        .mock_async(|when, then| {
+            when.method(GET).path("/v3/company/1234/query");
+            then.status(400).body(
+                r#"{"Fault":{"Error":[{"Message":"Error parsing query","code":"4000"}],"type":"ValidationFault"}}"#,
+            );
+        })
+        .await;
+
+    let client = reqwest::Client::new();
+    let query: Query<Customer> = Query::new();
+    let err = query
+        .execute_async(&executor(&server, &client))
+        .await
+        .unwrap_err();
+
+    assert!(matches!(
+        err,
+        QueryError::Fault(ref fault) if fault.code() == Some(FaultCode::QueryParserError)
+    ));
+}
+
+#[tokio::test]
+async fn test_async_query_timeout() {
+    let server = MockServer::start_async().await;
+    server
+        .mock_async(|when, then| {
+            when.method(GET).path("/v3/company/1234/query");
+            then.status(200)
+                .delay(Duration::from_millis(500))
+                .body(r#"{"QueryResponse":{},"time":"2024-01-01T00:00:00.000-08:00"}"#);
+        })
+        .await;
+
+    let client = reqwest::Client::new();
+    let query: Query<Customer> = Query::new().with_timeout(Duration::from_millis(50));
+    let err = query
+        .execute_async(&executor(&server, &client))
+        .await
+        .unwrap_err();
+
+    assert!(matches!(err, QueryError::Timeout));
+}
+
+#[tokio::test]
+async fn test_async_across_realms() {
+    let server = MockServer::start_async().await;
+    for (realm, status, body) in [
+        (
+            "1",
+            200,
+            r#"{"QueryResponse":{"Customer":[{"Id":"1"},{"Id":"2"}]}}"#,
+        ),
+        (
+            "2",
+            401,
+            r#"{"Fault":{"Error":[{"Message":"AuthenticationFailed","code":"3200"}],"type":"AUTHENTICATION"}}"#,
+        ),
+        ("3", 200, r#"{"QueryResponse":{"Customer":[{"Id":"3"}]}}"#),
+    ] {
+        server
+            .mock_async(|when, then| {
+                when.method(GET).path(format!("/v3/company/{realm}/query"));
+                then.status(status).body(body);
+            })
+            .await;
+    }
+
+    let client = reqwest::Client::new();
+    let executors: Vec<_> = ["1", "2", "3"]
+        .into_iter()
+        .map(|realm| {
+            AsyncHttpExecutor::new(&client, QbEnvironment::Sandbox, realm, "test-token")
+                .with_base_url(format!("{}/v3", server.base_url()))
+        })
+        .collect();
+    let query: Query<Customer> = Query::new();
+
+    let results = query.execute_across_realms_async(&executors, 2).await;
+
+    let realms: Vec<_> = results.results.iter().map(|(id, _)| id.as_str()).collect();
+    assert_eq!(realms, vec!["1", "2", "3"]);
+    assert!(results.results[1].1.as_ref().unwrap_err().is_auth());
+    assert_eq!(
+        results.summary(),
+        RealmSummary {
+            succeeded: 2,
+            failed: 1,
+            total_rows: 3,
+        }
+    );
+}
+
+#[tokio::test]
+async fn test_async_across_realms_retries_throttled_realm() {
+    let server = MockServer::start_async().await;
+    let mut mocks = Vec::new();
+    for (realm, status, body) in [
+        ("1", 200, r#"{"QueryResponse":{"Customer":[{"Id":"1"}]}}"#),
+        ("2", 429, "Too Many Requests"),
+        ("3", 200, r#"{"QueryResponse":{"Customer":[{"Id":"3"}]}}"#),
+    ] {
+        let mock = server
+            .mock_async(|when, then| {
+                when.method(GET).path(format!("/v3/company/{realm}/query"));
+                then.status(status).body(body);
+            })
+            .await;
+        mocks.push(mock);
+    }
+
+    let client = reqwest::Client::new();
+    let executors: Vec<_> = ["1", "2", "3"]
+        .into_iter()
+        .map(|realm| {
+            AsyncHttpExecutor::new(&client, QbEnvironment::Sandbox, realm, "test-token")
+                .with_base_url(format!("{}/v3", server.base_url()))
+        })
+        .collect();
+    let query: Query<Customer> = Query::new()
+        .retries(2)
+        .retry_backoff(Duration::from_millis(10));
+
+    let results = query.execute_across_realms_async(&executors, 2).await;
+
+    assert!(matches!(
+        results.results[1].1,
+        Err(QueryError::Http { status: 429, .. })
+    ));
+    // Only the throttled realm is retried, once per allowed retry
+    assert_eq!(mocks[0].hits_async().await, 1);
+    assert_eq!(mocks[1].hits_async().await, 3);
+    assert_eq!(mocks[2].hits_async().await, 1);
+    assert_eq!(
+        results.summary(),
+        RealmSummary {
+            succeeded: 2,
+            failed: 1,
+            total_rows: 2,
+        }
+    );
+}
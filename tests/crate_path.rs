@@ -0,0 +1,38 @@
+//! Expands `qb_sql!` through a facade module that re-exports the crate, as a
+//! crate wrapping this one would, pointing the expansion at it with `#[crate]`.
+
+mod facade {
+    pub use quick_oxibooks_sql as sql;
+}
+
+use facade::sql::qb_sql;
+
+#[test]
+fn test_crate_path_attribute() {
+    let ids = vec![1, 2];
+    let query = qb_sql!(
+        #[crate = facade::sql]
+        select display_name from quickbooks_types::Customer
+        where id in (ids) and balance > 0
+        order by display_name desc nulls first
+    );
+    let query: facade::sql::Query<quickbooks_types::Customer> = query;
+    assert_eq!(
+        query.query_string(),
+        "select DisplayName from Customer where Id IN ('1', '2') and Balance > '0' \
+         ORDERBY DisplayName DESC"
+    );
+}
+
+#[test]
+fn test_crate_path_with_rename() {
+    fn upper(field: &str) -> String {
+        field.to_uppercase()
+    }
+    let query = qb_sql!(
+        #[crate = crate::facade::sql]
+        #[rename = upper]
+        select display_name from quickbooks_types::Customer
+    );
+    assert_eq!(query.query_string(), "select DISPLAY_NAME from Customer");
+}
@@ -0,0 +1,67 @@
+//! Expands every form of `qb_sql!` in a crate that denies warnings, so generated
+//! code that triggers a lint (e.g. `unused_mut` or `unused_unsafe`) fails the build.
+#![deny(warnings)]
+
+use quick_oxibooks_sql::{Query, qb_sql};
+use quickbooks_types::Customer;
+
+#[test]
+fn test_select_forms() {
+    let all: Query<Customer> = qb_sql!(select * from Customer);
+    let fields = qb_sql!(select display_name, balance from Customer);
+    let aliased = qb_sql!(select display_name as name from Customer);
+    let distinct = qb_sql!(select distinct display_name from Customer);
+    let count = qb_sql!(select count from Customer);
+
+    for query in [all, fields, aliased, distinct] {
+        assert!(query.query_string().starts_with("select "));
+    }
+    assert_eq!(count.query_string(), "select count(*) from Customer");
+}
+
+#[test]
+fn test_operators() {
+    let name = String::from("Acme%");
+    let ids = vec![1, 2];
+    let queries = [
+        qb_sql!(select * from Customer where id = 1),
+        qb_sql!(select * from Customer where id != 1),
+        qb_sql!(select * from Customer where id <> 1),
+        qb_sql!(select * from Customer where balance < 1),
+        qb_sql!(select * from Customer where balance > 1),
+        qb_sql!(select * from Customer where balance <= 1),
+        qb_sql!(select * from Customer where balance >= 1),
+        qb_sql!(select * from Customer where id in (1, 2)),
+        qb_sql!(select * from Customer where id in (ids)),
+        qb_sql!(select * from Customer where id not in (1, 2)),
+        qb_sql!(select * from Customer where display_name like name),
+        qb_sql!(select * from Customer where display_name not like "A%"),
+        qb_sql!(select * from Customer where not display_name like "A%"),
+        qb_sql!(select * from Customer where title is null),
+        qb_sql!(select * from Customer where title is not null),
+        qb_sql!(select * from Customer where balance between 1 and 2),
+        qb_sql!(select * from Customer where (id = 1 or id = 2) and balance > 0),
+    ];
+
+    for query in queries {
+        assert!(query.query_string().contains(" where "));
+    }
+    // Variables used in conditions are borrowed, not moved
+    assert_eq!(name, "Acme%");
+}
+
+#[test]
+fn test_order_and_limit() {
+    let skip = 20;
+    let queries = [
+        qb_sql!(select * from Customer order by display_name),
+        qb_sql!(select * from Customer order by display_name asc, balance desc),
+        qb_sql!(select * from Customer limit 10),
+        qb_sql!(select * from Customer limit 10 offset skip),
+        qb_sql!(select * from Customer maxresults 10 startposition 21),
+    ];
+
+    for query in queries {
+        assert!(query.query_string().starts_with("select * from Customer "));
+    }
+}
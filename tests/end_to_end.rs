@@ -0,0 +1,275 @@
+//! Executable specification of the `qb_sql!` -> `execute` -> typed results flow.
+//!
+//! Each test builds a query with the macro, runs it through `HttpExecutor`
+//! against a local mock server, and asserts both the exact query text the
+//! server received and the entities parsed from its response. New query
+//! features should add a case here, since the seams between field naming,
+//! encoding and response parsing are where regressions tend to hide.
+#![cfg(feature = "api")]
+
+use httpmock::prelude::*;
+use quick_oxibooks_sql::{
+    Connective, HttpExecutor, Operator, Order, QbEnvironment, Query, QueryError, WhereClause,
+    qb_sql,
+};
+use quickbooks_types::{Customer, Invoice, QBItem};
+use serde::de::DeserializeOwned;
+
+/// Run `query` against a mock server that only answers `expected` with `body`
+fn run<QB: QBItem + DeserializeOwned>(query: &Query<QB>, expected: &str, body: &str) -> Vec<QB> {
+    let server = MockServer::start();
+    let mock = server.mock(|when, then| {
+        when.method(GET)
+            .path("/v3/company/1234/query")
+            .query_param("query", expected)
+            .header("Authorization", "Bearer test-token");
+        then.status(200)
+            .header("Content-Type", "application/json")
+            .body(body);
+    });
+
+    let client = ureq::Agent::new_with_defaults();
+    let executor = HttpExecutor::new(&client, QbEnvironment::Sandbox, "1234", "test-token")
+        .with_base_url(format!("{}/v3", server.base_url()));
+    let items = query.execute_with(&executor).unwrap();
+
+    mock.assert();
+    items
+}
+
+const CUSTOMERS: &str = r#"{"QueryResponse":{"Customer":[
+    {"Id":"1","DisplayName":"Oak & Co","Balance":250.5,"Active":true},
+    {"Id":"2","DisplayName":"O'Brien's","Balance":0,"Active":true,"Title":"Mr"}
+],"startPosition":1,"maxResults":2},"time":"2024-01-01T00:00:00.000-08:00"}"#;
+
+const INVOICES: &str = r#"{"QueryResponse":{"Invoice":[
+    {"Id":"130","DocNumber":"1037","TxnDate":"2024-01-15","TotalAmt":362.07,"Balance":362.07,"CustomerRef":{"value":"3","name":"Cool Cars"}}
+],"startPosition":1,"maxResults":1},"time":"2024-01-01T00:00:00.000-08:00"}"#;
+
+fn customer_names(customers: &[Customer]) -> Vec<Option<&str>> {
+    customers
+        .iter()
+        .map(|customer| customer.display_name.as_deref())
+        .collect()
+}
+
+#[test]
+fn test_customer_comparison_operators() {
+    let cases = [
+        (
+            qb_sql!(select * from Customer where id = 1),
+            "select * from Customer where Id = '1'",
+        ),
+        (
+            qb_sql!(select * from Customer where id != 1),
+            "select * from Customer where Id != '1'",
+        ),
+        (
+            qb_sql!(select * from Customer where id <> 1),
+            "select * from Customer where Id != '1'",
+        ),
+        (
+            qb_sql!(select * from Customer where balance < 100),
+            "select * from Customer where Balance < '100'",
+        ),
+        (
+            qb_sql!(select * from Customer where balance > 100),
+            "select * from Customer where Balance > '100'",
+        ),
+        (
+            qb_sql!(select * from Customer where balance <= 100.5),
+            "select * from Customer where Balance <= '100.5'",
+        ),
+        (
+            qb_sql!(select * from Customer where balance >= 100),
+            "select * from Customer where Balance >= '100'",
+        ),
+        (
+            qb_sql!(select * from Customer where balance between 0 and 500),
+            "select * from Customer where Balance BETWEEN '0' AND '500'",
+        ),
+    ];
+
+    for (query, expected) in cases {
+        let customers = run(&query, expected, CUSTOMERS);
+        assert_eq!(
+            customer_names(&customers),
+            vec![Some("Oak & Co"), Some("O'Brien's")]
+        );
+        assert_eq!(customers[0].balance, Some(250.5));
+    }
+}
+
+#[test]
+fn test_customer_list_like_and_null_operators() {
+    let ids = [1, 2];
+    let prefix = String::from("O%");
+    let cases = [
+        (
+            qb_sql!(select * from Customer where id in (1, 2)),
+            "select * from Customer where Id IN ('1', '2')",
+        ),
+        (
+            qb_sql!(select * from Customer where id in (ids)),
+            "select * from Customer where Id IN ('1', '2')",
+        ),
+        (
+            qb_sql!(select * from Customer where id not in (3, 4)),
+            "select * from Customer where Id NOT IN ('3', '4')",
+        ),
+        (
+            qb_sql!(select * from Customer where display_name like prefix),
+            "select * from Customer where DisplayName LIKE 'O%'",
+        ),
+        (
+            qb_sql!(select * from Customer where display_name not like "A%"),
+            "select * from Customer where DisplayName NOT LIKE 'A%'",
+        ),
+        (
+            qb_sql!(select * from Customer where not display_name like "A%"),
+            "select * from Customer where NOT DisplayName LIKE 'A%'",
+        ),
+        (
+            qb_sql!(select * from Customer where title is null),
+            "select * from Customer where Title IS NULL",
+        ),
+        (
+            qb_sql!(select * from Customer where title is not null),
+            "select * from Customer where Title IS NOT NULL",
+        ),
+        (
+            qb_sql!(select * from Customer where primary_email_addr.address like "%@oak.example"),
+            "select * from Customer where PrimaryEmailAddr.Address LIKE '%@oak.example'",
+        ),
+    ];
+
+    for (query, expected) in cases {
+        let customers = run(&query, expected, CUSTOMERS);
+        assert_eq!(customers.len(), 2);
+        assert_eq!(customers[1].title.as_deref(), Some("Mr"));
+    }
+}
+
+#[test]
+fn test_customer_full_query() {
+    let query = qb_sql!(
+        select display_name, balance from Customer
+        where (display_name = "O'Brien's" or balance > 100) and active = true
+        order by display_name asc, balance desc
+        limit 2 offset 10
+    );
+
+    let customers = run(
+        &query,
+        "select DisplayName, Balance from Customer where (DisplayName = 'O\\'Brien\\'s' or Balance > '100') \
+         and Active = 'true' ORDERBY DisplayName ASC, Balance DESC STARTPOSITION 11 MAXRESULTS 2",
+        CUSTOMERS,
+    );
+
+    assert_eq!(
+        customer_names(&customers),
+        vec![Some("Oak & Co"), Some("O'Brien's")]
+    );
+}
+
+#[test]
+fn test_runtime_conditions_match_macro() {
+    let query = Query::<Customer>::new()
+        .with_condition(WhereClause::new("DisplayName", Operator::Like).add_value("O%"))
+        .with_condition(
+            WhereClause::new("Balance", Operator::Greater)
+                .add_value(100)
+                .joined_by(Connective::Or),
+        )
+        .order_by("DisplayName", Order::Asc);
+
+    assert_eq!(
+        query.query_string(),
+        qb_sql!(
+            select * from Customer
+            where display_name like "O%" or balance > 100
+            order by display_name asc
+        )
+        .query_string()
+    );
+    let customers = run(
+        &query,
+        "select * from Customer where DisplayName LIKE 'O%' or Balance > '100' ORDERBY DisplayName ASC",
+        CUSTOMERS,
+    );
+    assert_eq!(customers.len(), 2);
+}
+
+#[test]
+fn test_invoice_operators() {
+    let doc_numbers = ["1037", "1038"];
+    let cases = [
+        (
+            qb_sql!(select * from Invoice where doc_number = "1037"),
+            "select * from Invoice where DocNumber = '1037'",
+        ),
+        (
+            qb_sql!(select * from Invoice where doc_number in (doc_numbers)),
+            "select * from Invoice where DocNumber IN ('1037', '1038')",
+        ),
+        (
+            qb_sql!(select * from Invoice where total_amt >= 100 and balance != 0),
+            "select * from Invoice where TotalAmt >= '100' and Balance != '0'",
+        ),
+        (
+            qb_sql!(select * from Invoice where txn_date between "2024-01-01" and "2024-01-31"),
+            "select * from Invoice where TxnDate BETWEEN '2024-01-01' AND '2024-01-31'",
+        ),
+        (
+            qb_sql!(select * from Invoice where customer_ref = "3" order by txn_date desc),
+            "select * from Invoice where CustomerRef = '3' ORDERBY TxnDate DESC",
+        ),
+    ];
+
+    for (query, expected) in cases {
+        let invoices = run(&query, expected, INVOICES);
+        assert_eq!(invoices.len(), 1);
+        assert_eq!(invoices[0].doc_number.as_deref(), Some("1037"));
+        assert_eq!(invoices[0].total_amt, Some(362.07));
+        assert_eq!(
+            invoices[0]
+                .customer_ref
+                .as_ref()
+                .and_then(|customer| customer.value.as_deref()),
+            Some("3")
+        );
+    }
+}
+
+#[test]
+fn test_invoice_count_and_fault() {
+    let server = MockServer::start();
+    let count = server.mock(|when, then| {
+        when.method(GET)
+            .path("/v3/company/1234/query")
+            .query_param("query", "select count(*) from Invoice where Balance > '0'");
+        then.status(200)
+            .body(r#"{"QueryResponse":{"totalCount":17},"time":"2024-01-01T00:00:00.000-08:00"}"#);
+    });
+    let fault = server.mock(|when, then| {
+        when.method(GET)
+            .path("/v3/company/1234/query")
+            .query_param("query", "select * from Invoice where Balance > '0'");
+        then.status(400).body(
+            r#"{"Fault":{"Error":[{"Message":"Error parsing query","Detail":"QueryParserError","code":"4000"}],"type":"ValidationFault"}}"#,
+        );
+    });
+
+    let client = ureq::Agent::new_with_defaults();
+    let executor = HttpExecutor::new(&client, QbEnvironment::Sandbox, "1234", "test-token")
+        .with_base_url(format!("{}/v3", server.base_url()));
+    let query = qb_sql!(select * from Invoice where balance > 0);
+
+    assert_eq!(query.count(&executor).unwrap(), 17);
+    assert!(matches!(
+        query.execute_with(&executor),
+        Err(QueryError::Fault(_))
+    ));
+    count.assert();
+    fault.assert();
+}
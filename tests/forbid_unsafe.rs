@@ -0,0 +1,40 @@
+//! Expands `qb_sql!` in a crate that forbids unsafe code, so the macro stays
+//! usable there; the expansion only calls the safe builder methods.
+#![forbid(unsafe_code)]
+
+use quick_oxibooks_sql::{Query, qb_sql};
+use quickbooks_types::Customer;
+
+fn acronyms(field: &str) -> &str {
+    match field {
+        "id" => "ID",
+        other => other,
+    }
+}
+
+#[test]
+fn test_expansion_is_safe() {
+    let ids = vec![1, 2];
+    let skip = 20;
+    let queries: [Query<Customer>; 6] = [
+        qb_sql!(select display_name as name, balance from Customer),
+        qb_sql!(
+            select distinct display_name from Customer
+            where (id in (ids) or balance between 1 and 2) and not title like "Mr%"
+            order by display_name desc nulls last
+            limit 10 offset skip
+        ),
+        qb_sql!(select * from Customer where custom_column? = "x" maxresults 5),
+        qb_sql!(select * from Customer where primary_email_addr.address as "PrimaryEmailAddr" = "a@b.c"),
+        qb_sql!(select * from Customer where title is null group by display_name),
+        qb_sql!(#[rename = acronyms] select id from Customer order by id),
+    ];
+
+    for query in queries {
+        assert!(query.query_string().starts_with("select "));
+    }
+    assert_eq!(
+        qb_sql!(select count from Customer).query_string(),
+        "select count(*) from Customer"
+    );
+}
@@ -0,0 +1,226 @@
+//! End-to-end tests running queries through `HttpExecutor` against a local mock
+//! QuickBooks server, covering URL construction, encoding, headers, response
+//! parsing, paging and retries.
+//!
+//! The `minorversion` parameter isn't covered, since executors don't send one.
+#![cfg(feature = "api")]
+
+use httpmock::prelude::*;
+use quick_oxibooks_sql::{FaultCode, HttpExecutor, QbEnvironment, Query, QueryError, qb_sql};
+use quickbooks_types::Customer;
+use std::time::Duration;
+
+const COMPANY_ID: &str = "1234";
+const TOKEN: &str = "test-token";
+
+fn executor<'a>(server: &MockServer, client: &'a ureq::Agent) -> HttpExecutor<'a> {
+    HttpExecutor::new(client, QbEnvironment::Sandbox, COMPANY_ID, TOKEN)
+        .with_base_url(format!("{}/v3", server.base_url()))
+}
+
+/// Whether the request's `query` parameter parses as a customer query
+fn valid_query(request: &HttpMockRequest) -> bool {
+    let query = request
+        .query_params
+        .iter()
+        .flatten()
+        .find(|(name, _)| name == "query");
+    query.is_some_and(|(_, query)| Query::<Customer>::from_sql_str(query).is_ok())
+}
+
+#[test]
+fn test_query_round_trip() {
+    let server = MockServer::start();
+    let mock = server.mock(|when, then| {
+        when.method(GET)
+            .path("/v3/company/1234/query")
+            .query_param(
+                "query",
+                "select DisplayName, Balance from Customer where DisplayName LIKE 'O%' and Balance >= '100' ORDERBY DisplayName ASC",
+            )
+            .header("Authorization", "Bearer test-token")
+            .header("Accept", "application/json")
+            .matches(valid_query);
+        then.status(200)
+            .header("Content-Type", "application/json")
+            .body(
+                r#"{"QueryResponse":{"Customer":[{"Id":"1","DisplayName":"Oak & Co"},{"Id":"2","DisplayName":"Orchard"}],"startPosition":1,"maxResults":2},"time":"2024-01-01T00:00:00.000-08:00"}"#,
+            );
+    });
+
+    let client = ureq::Agent::new_with_defaults();
+    let query = qb_sql!(
+        select display_name, balance from Customer
+        where display_name like "O%"
+        and balance >= 100
+        order by display_name asc
+    );
+    let customers = query.execute_with(&executor(&server, &client)).unwrap();
+
+    mock.assert();
+    assert_eq!(customers.len(), 2);
+    assert_eq!(customers[0].display_name.as_deref(), Some("Oak & Co"));
+}
+
+#[test]
+fn test_empty_response() {
+    let server = MockServer::start();
+    let mock = server.mock(|when, then| {
+        when.method(GET)
+            .path("/v3/company/1234/query")
+            .query_param("query", "select * from Customer where Id IN ('1', '2')")
+            .matches(valid_query);
+        then.status(200)
+            .body(r#"{"QueryResponse":{},"time":"2024-01-01T00:00:00.000-08:00"}"#);
+    });
+
+    let client = ureq::Agent::new_with_defaults();
+    let query = qb_sql!(select * from Customer where id in (1, 2));
+    let customers: Vec<Customer> = query.execute_with(&executor(&server, &client)).unwrap();
+
+    mock.assert();
+    assert!(customers.is_empty());
+}
+
+#[test]
+fn test_fault_response() {
+    let server = MockServer::start();
+    server.mock(|when, then| {
+        when.method(GET).path("/v3/company/1234/query");
+        then.status(401).body(
+            r#"{"fault":{"error":[{"message":"message=AuthenticationFailed; errorCode=003200; statusCode=401","detail":"Token expired","code":"3200"}],"type":"AUTHENTICATION"}}"#,
+        );
+    });
+
+    let client = ureq::Agent::new_with_defaults();
+    let query: Query<Customer> = Query::new();
+    let err = query.execute_with(&executor(&server, &client)).unwrap_err();
+
+    assert!(matches!(
+        err,
+        QueryError::Fault(ref fault) if fault.code() == Some(FaultCode::AuthenticationFailed)
+    ));
+    assert!(err.is_auth());
+}
+
+#[test]
+fn test_error_status_without_fault() {
+    let server = MockServer::start();
+    server.mock(|when, then| {
+        when.method(GET).path("/v3/company/1234/query");
+        then.status(503).body("Service Unavailable");
+    });
+
+    let client = ureq::Agent::new_with_defaults();
+    let query: Query<Customer> = Query::new();
+    let err = query.execute_with(&executor(&server, &client)).unwrap_err();
+
+    assert!(matches!(err, QueryError::Http { status: 503, .. }));
+    assert!(err.is_retryable());
+}
+
+#[test]
+fn test_query_timeout() {
+    let server = MockServer::start();
+    let mock = server.mock(|when, then| {
+        when.method(GET).path("/v3/company/1234/query");
+        then.status(200)
+            .delay(Duration::from_millis(500))
+            .body(r#"{"QueryResponse":{},"time":"2024-01-01T00:00:00.000-08:00"}"#);
+    });
+
+    let client = ureq::Agent::new_with_defaults();
+    let executor = executor(&server, &client);
+    let query: Query<Customer> = Query::new();
+
+    let err = query
+        .clone()
+        .with_timeout(Duration::from_millis(50))
+        .execute_with(&executor)
+        .unwrap_err();
+    assert!(matches!(err, QueryError::Timeout));
+
+    // Without a timeout the slow response still arrives
+    assert!(query.execute_with(&executor).unwrap().is_empty());
+    mock.assert_hits(2);
+}
+
+#[test]
+fn test_paged_query() {
+    let server = MockServer::start();
+    let pages = [
+        (
+            "select * from Customer ORDERBY Id ASC MAXRESULTS 2",
+            r#"{"QueryResponse":{"Customer":[{"Id":"1"},{"Id":"2"}],"startPosition":1,"maxResults":2}}"#,
+        ),
+        (
+            "select * from Customer ORDERBY Id ASC STARTPOSITION 3 MAXRESULTS 2",
+            r#"{"QueryResponse":{"Customer":[{"Id":"3"}],"startPosition":3,"maxResults":1}}"#,
+        ),
+    ];
+    let mocks: Vec<_> = pages
+        .into_iter()
+        .map(|(query, body)| {
+            server.mock(|when, then| {
+                when.method(GET)
+                    .path("/v3/company/1234/query")
+                    .query_param("query", query)
+                    .matches(valid_query);
+                then.status(200).body(body);
+            })
+        })
+        .collect();
+
+    let client = ureq::Agent::new_with_defaults();
+    let executor = executor(&server, &client);
+    let query = qb_sql!(select * from Customer order by id);
+    let pages: Vec<Vec<Customer>> = query
+        .execute_paged(&executor, 2)
+        .collect::<Result<_, _>>()
+        .unwrap();
+
+    for mock in &mocks {
+        mock.assert();
+    }
+    let ids: Vec<Vec<_>> = pages
+        .iter()
+        .map(|page| page.iter().map(|c| c.id.as_deref().unwrap()).collect())
+        .collect();
+    assert_eq!(ids, vec![vec!["1", "2"], vec!["3"]]);
+}
+
+#[test]
+fn test_throttled_request_is_retried() {
+    let server = MockServer::start();
+    let mut throttled = server.mock(|when, then| {
+        when.method(GET)
+            .path("/v3/company/1234/query")
+            .matches(valid_query);
+        then.status(429).body("Too Many Requests");
+    });
+
+    let client = ureq::Agent::new_with_defaults();
+    let executor = executor(&server, &client);
+    let query = qb_sql!(select * from Customer where active = true)
+        .retries(1)
+        .retry_backoff(Duration::from_millis(500));
+
+    let customers = std::thread::scope(|scope| {
+        let request = scope.spawn(|| query.execute_with(&executor));
+        // Let the retry succeed once the first attempt has been throttled
+        while throttled.hits() == 0 {
+            std::thread::sleep(Duration::from_millis(10));
+        }
+        throttled.delete();
+        server.mock(|when, then| {
+            when.method(GET)
+                .path("/v3/company/1234/query")
+                .query_param("query", "select * from Customer where Active = 'true'");
+            then.status(200)
+                .body(r#"{"QueryResponse":{"Customer":[{"Id":"1"}]}}"#);
+        });
+        request.join().unwrap()
+    });
+
+    assert_eq!(customers.unwrap().len(), 1);
+}
@@ -0,0 +1,27 @@
+//! Expands `qb_sql!` with only the macro itself in scope, so the expansion can't
+//! rely on the caller importing `Query`, `WhereClause`, `Operator` and friends.
+#![deny(warnings)]
+
+use quick_oxibooks_sql::qb_sql;
+
+#[test]
+fn test_expansion_needs_no_imports() {
+    let ids = vec![1, 2];
+    let query = qb_sql!(
+        select display_name from quickbooks_types::Customer
+        where (id in (ids) or balance between 1 and 2) and not title like "Mr%"
+        or display_name in ("Acme", "Globex")
+        order by display_name desc nulls last, balance
+        limit 10
+    );
+    assert_eq!(
+        query.query_string(),
+        "select DisplayName from Customer \
+         where (Id IN ('1', '2') or Balance BETWEEN '1' AND '2') and NOT Title LIKE 'Mr%' \
+         or DisplayName IN ('Acme', 'Globex') ORDERBY DisplayName DESC, Balance ASC MAXRESULTS 10"
+    );
+    assert_eq!(
+        qb_sql!(select count from quickbooks_types::Customer).query_string(),
+        "select count(*) from Customer"
+    );
+}
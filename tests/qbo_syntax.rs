@@ -0,0 +1,33 @@
+//! Checks that rendered queries match the QuickBooks Online query grammar.
+
+use quick_oxibooks_sql::{Dialect, Order, Query, qb_sql};
+use quickbooks_types::Customer;
+
+#[test]
+fn test_full_query_matches_qbo_grammar() {
+    let query = qb_sql!(
+        select display_name, balance from Customer
+        where display_name like "A%"
+        and balance > 100
+        order by display_name asc, balance desc
+        limit 20 offset 40
+    );
+
+    assert_eq!(
+        query.query_string(),
+        "select DisplayName, Balance from Customer where DisplayName LIKE 'A%' and Balance > '100' \
+         ORDERBY DisplayName ASC, Balance DESC STARTPOSITION 41 MAXRESULTS 20"
+    );
+}
+
+#[test]
+fn test_standard_dialect_keeps_order_by() {
+    let query = Query::<Customer>::new()
+        .order_by("DisplayName", Order::Asc)
+        .dialect(Dialect::Standard);
+
+    assert_eq!(
+        query.query_string(),
+        "select * from Customer order by DisplayName ASC"
+    );
+}
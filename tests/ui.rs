@@ -0,0 +1,12 @@
+//! Compile-fail tests pinning where `qb_sql!` reports unknown fields and
+//! mismatched condition values.
+//!
+//! Each case should underline only the offending field or value. After an
+//! intentional change to the diagnostics, regenerate the expected output with
+//! `TRYBUILD=overwrite cargo test --test ui`.
+
+#[test]
+fn test_error_spans() {
+    let cases = trybuild::TestCases::new();
+    cases.compile_fail("tests/ui/*.rs");
+}
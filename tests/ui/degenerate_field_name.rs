@@ -0,0 +1,5 @@
+use quick_oxibooks_sql::qb_sql;
+
+fn main() {
+    let _query = qb_sql!(select * from quickbooks_types::Customer where display__name = "Acme");
+}
@@ -0,0 +1,6 @@
+use quick_oxibooks_sql::qb_sql;
+use quickbooks_types::Customer;
+
+fn main() {
+    let _query = qb_sql!(select * from Customer where balance like "foo");
+}
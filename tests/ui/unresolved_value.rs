@@ -0,0 +1,8 @@
+use quick_oxibooks_sql::qb_sql;
+use quickbooks_types::Customer;
+
+// A value named like a field is a variable like any other, so an unresolved one
+// gets rustc's missing variable error rather than an explanation about fields
+fn main() {
+    let _query = qb_sql!(select * from Customer where balance > deposit_total);
+}